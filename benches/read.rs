@@ -0,0 +1,33 @@
+//! Compares reading all chunks of region from file and from memory mapped file.
+use anvil_region::position::RegionPosition;
+use anvil_region::region::Region;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs::File;
+
+const REGION_PATH: &str = "test/region/r.0.0.mca";
+
+fn read_chunks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_chunks");
+
+    group.bench_function("file", |b| {
+        b.iter(|| {
+            let file = File::open(REGION_PATH).unwrap();
+            let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+            region.readable_chunks().count()
+        })
+    });
+
+    group.bench_function("mmap", |b| {
+        b.iter(|| {
+            let mut region = Region::load_mmap(RegionPosition::new(0, 0), REGION_PATH).unwrap();
+
+            region.readable_chunks().count()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, read_chunks);
+criterion_main!(benches);
@@ -0,0 +1,295 @@
+//! Provider wrapper which mirrors written chunks to backup.
+//!
+//! Every chunk which was successfully written to primary provider are written
+//! to backup provider as well, so backup are kept up to date without copying
+//! whole region files.
+use crate::error::{ChunkReadError, ChunkWriteError};
+use crate::position::ChunkPosition;
+use crate::provider::ChunkProvider;
+use log::warn;
+use nbt::CompoundTag;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+
+/// Chunk provider which mirrors every successful chunk write to backup provider.
+///
+/// Chunks are read from primary provider only. Backup can be written on the caller
+/// thread, where backup errors are returned by `save_chunk`, or on a background thread,
+/// where positions of chunks which failed to be mirrored are kept.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::backup::WriteThroughChunkProvider;
+/// use anvil_region::cache::CachedRegionProvider;
+/// use anvil_region::fixtures::minimal_chunk;
+/// use anvil_region::position::ChunkPosition;
+/// use anvil_region::provider::{ChunkProvider, FolderRegionProvider};
+///
+/// # let dir = std::env::temp_dir().join("anvil-region-write-through-doc");
+/// # std::fs::create_dir_all(&dir).unwrap();
+/// let primary = CachedRegionProvider::new(FolderRegionProvider::new(dir.join("region")), 16);
+/// let backup = CachedRegionProvider::new(FolderRegionProvider::new(dir.join("backup")), 16);
+///
+/// let mut provider = WriteThroughChunkProvider::new_async(primary, backup);
+/// provider.save_chunk(ChunkPosition::new(4, 2), minimal_chunk(4, 2)).unwrap();
+///
+/// let (_, mut backup, failed_positions) = provider.finish();
+///
+/// assert!(failed_positions.is_empty());
+/// assert!(backup.load_chunk(ChunkPosition::new(4, 2)).is_ok());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct WriteThroughChunkProvider<P, B> {
+    /// Provider where chunks are read and written.
+    primary: Option<P>,
+    /// Provider where written chunks are mirrored.
+    backup: Option<Backup<B>>,
+    /// Positions of chunks which failed to be mirrored by background thread.
+    failed_positions: Arc<Mutex<Vec<ChunkPosition>>>,
+}
+
+enum Backup<B> {
+    /// Backup which are written on the caller thread.
+    Sync(B),
+    /// Backup which are owned and written by background thread.
+    Async {
+        sender: Sender<(ChunkPosition, CompoundTag)>,
+        worker: JoinHandle<B>,
+    },
+}
+
+impl<P: ChunkProvider, B: ChunkProvider> WriteThroughChunkProvider<P, B> {
+    /// Creates provider which writes backup on the caller thread.
+    pub fn new(primary: P, backup: B) -> Self {
+        WriteThroughChunkProvider {
+            primary: Some(primary),
+            backup: Some(Backup::Sync(backup)),
+            failed_positions: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Creates provider which writes backup on a background thread.
+    ///
+    /// Failures of mirroring are logged and positions of failed chunks are kept.
+    pub fn new_async(primary: P, backup: B) -> Self
+    where
+        B: Send + 'static,
+    {
+        let failed_positions = Arc::new(Mutex::new(Vec::new()));
+        let worker_failed_positions = failed_positions.clone();
+        let (sender, receiver) = channel::<(ChunkPosition, CompoundTag)>();
+
+        let worker = thread::spawn(move || {
+            let mut backup = backup;
+
+            for (position, chunk_compound_tag) in receiver {
+                if let Err(write_error) = backup.save_chunk(position, chunk_compound_tag) {
+                    warn!(
+                        target: "anvil-region",
+                        "Chunk x: {}, z: {} backup failed: {:?}",
+                        position.x, position.z, write_error
+                    );
+
+                    worker_failed_positions.lock().unwrap().push(position);
+                }
+            }
+
+            backup
+        });
+
+        WriteThroughChunkProvider {
+            primary: Some(primary),
+            backup: Some(Backup::Async { sender, worker }),
+            failed_positions,
+        }
+    }
+
+    /// Returns positions of chunks which failed to be mirrored by background thread so far.
+    pub fn failed_positions(&self) -> Vec<ChunkPosition> {
+        self.failed_positions.lock().unwrap().clone()
+    }
+
+    /// Waits until all chunks are mirrored and returns primary and backup providers
+    /// with positions of chunks which failed to be mirrored by background thread.
+    ///
+    /// Panic of background thread are resumed on the caller thread.
+    pub fn finish(mut self) -> (P, B, Vec<ChunkPosition>) {
+        let primary = self.primary.take().unwrap();
+        let backup = finish_backup(self.backup.take().unwrap());
+
+        (primary, backup, self.failed_positions())
+    }
+}
+
+impl<P: ChunkProvider, B: ChunkProvider> ChunkProvider for WriteThroughChunkProvider<P, B> {
+    fn load_chunk(&mut self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+        self.primary.as_mut().unwrap().load_chunk(position)
+    }
+
+    /// Writes chunk to primary provider and mirrors it to backup on success.
+    fn save_chunk(
+        &mut self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        let primary = self.primary.as_mut().unwrap();
+        primary.save_chunk(position, chunk_compound_tag.clone())?;
+
+        match self.backup.as_mut().unwrap() {
+            Backup::Sync(backup) => backup.save_chunk(position, chunk_compound_tag),
+            Backup::Async { sender, .. } => {
+                // Background thread stops only when it panicked.
+                if sender.send((position, chunk_compound_tag)).is_err() {
+                    self.failed_positions.lock().unwrap().push(position);
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<P, B> Drop for WriteThroughChunkProvider<P, B> {
+    /// Waits until background thread mirrors all chunks.
+    ///
+    /// Panic of background thread are logged and discarded, because panicking in drop
+    /// may abort the process, use `finish` to propagate it.
+    fn drop(&mut self) {
+        if let Some(Backup::Async { sender, worker }) = self.backup.take() {
+            drop(sender);
+
+            if worker.join().is_err() {
+                warn!(target: "anvil-region", "Backup thread panicked");
+            }
+        }
+    }
+}
+
+fn finish_backup<B>(backup: Backup<B>) -> B {
+    match backup {
+        Backup::Sync(backup) => backup,
+        Backup::Async { sender, worker } => {
+            drop(sender);
+
+            match worker.join() {
+                Ok(backup) => backup,
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backup::WriteThroughChunkProvider;
+    use crate::cache::CachedRegionProvider;
+    use crate::error::{ChunkReadError, ChunkWriteError};
+    use crate::fixtures::{minimal_chunk, temp_world};
+    use crate::position::ChunkPosition;
+    use crate::provider::{ChunkProvider, FolderRegionProvider};
+    use nbt::CompoundTag;
+    use std::io;
+    use std::panic;
+    use std::panic::AssertUnwindSafe;
+
+    /// Provider which fails to write chunks.
+    struct FailingProvider;
+
+    impl ChunkProvider for FailingProvider {
+        fn load_chunk(&mut self, _: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+            Err(io::Error::from(io::ErrorKind::NotFound).into())
+        }
+
+        fn save_chunk(&mut self, _: ChunkPosition, _: CompoundTag) -> Result<(), ChunkWriteError> {
+            Err(io::Error::from(io::ErrorKind::PermissionDenied).into())
+        }
+    }
+
+    /// Provider which panics when chunk are written.
+    struct PanickingProvider;
+
+    impl ChunkProvider for PanickingProvider {
+        fn load_chunk(&mut self, _: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+            Err(io::Error::from(io::ErrorKind::NotFound).into())
+        }
+
+        fn save_chunk(&mut self, _: ChunkPosition, _: CompoundTag) -> Result<(), ChunkWriteError> {
+            panic!("Backup write panicked")
+        }
+    }
+
+    #[test]
+    fn test_mirror_writes() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let primary = FolderRegionProvider::new(dir.join("region"));
+        let backup = FolderRegionProvider::new(dir.join("backup"));
+
+        let mut provider = WriteThroughChunkProvider::new(
+            CachedRegionProvider::new(primary, 4),
+            CachedRegionProvider::new(backup, 4),
+        );
+
+        for &(x, z) in &[(0, 0), (40, -3), (-1, 100)] {
+            let position = ChunkPosition::new(x, z);
+            provider.save_chunk(position, minimal_chunk(x, z)).unwrap();
+        }
+
+        let (mut primary, mut backup, failed_positions) = provider.finish();
+        assert!(failed_positions.is_empty());
+
+        for &(x, z) in &[(0, 0), (40, -3), (-1, 100)] {
+            let position = ChunkPosition::new(x, z);
+
+            assert!(primary.load_chunk(position).is_ok());
+            assert!(backup.load_chunk(position).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_backup_failures() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("write-through-failures");
+
+        let primary = CachedRegionProvider::new(FolderRegionProvider::new(&dir), 4);
+        let mut provider = WriteThroughChunkProvider::new(primary, FailingProvider);
+
+        // Chunk are written to primary even if backup failed.
+        let position = ChunkPosition::new(1, 2);
+        assert!(provider.save_chunk(position, minimal_chunk(1, 2)).is_err());
+        assert!(provider.load_chunk(position).is_ok());
+
+        let primary = CachedRegionProvider::new(FolderRegionProvider::new(&dir), 4);
+        let mut provider = WriteThroughChunkProvider::new_async(primary, FailingProvider);
+
+        provider.save_chunk(position, minimal_chunk(1, 2)).unwrap();
+        let (_, _, failed_positions) = provider.finish();
+
+        assert_eq!(failed_positions, vec![position]);
+    }
+
+    #[test]
+    fn test_backup_panic() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("write-through-panic");
+        let position = ChunkPosition::new(1, 2);
+
+        // Dropping provider doesn't propagate panic of background thread.
+        let primary = CachedRegionProvider::new(FolderRegionProvider::new(&dir), 4);
+        let mut provider = WriteThroughChunkProvider::new_async(primary, PanickingProvider);
+
+        provider.save_chunk(position, minimal_chunk(1, 2)).unwrap();
+        drop(provider);
+
+        let primary = CachedRegionProvider::new(FolderRegionProvider::new(&dir), 4);
+        let mut provider = WriteThroughChunkProvider::new_async(primary, PanickingProvider);
+
+        provider.save_chunk(position, minimal_chunk(1, 2)).unwrap();
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| provider.finish())).is_err());
+    }
+}
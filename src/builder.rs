@@ -0,0 +1,288 @@
+use crate::compression::CompressionScheme;
+use crate::error::ChunkWriteError;
+use crate::position::RegionChunkPosition;
+use crate::region::{REGION_CHUNKS, REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH};
+use byteorder::{BigEndian, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Write;
+
+/// Maximum chunk length in bytes, sectors count are stored in one byte of chunk offset.
+pub(crate) const BUILDER_CHUNK_MAXIMUM_BYTES_LENGTH: u32 = REGION_SECTOR_BYTES_LENGTH as u32 * 255;
+
+/// Assembles region from already compressed chunks data.
+///
+/// Chunks can be inserted in any order, region is written in one pass with
+/// chunks data placed one after another without gaps.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::builder::RegionBuilder;
+/// use anvil_region::compression::CompressionScheme;
+/// use anvil_region::position::{RegionChunkPosition, RegionPosition};
+/// use anvil_region::region::Region;
+/// use nbt::encode::write_zlib_compound_tag;
+/// use nbt::CompoundTag;
+/// use std::io::Cursor;
+///
+/// let mut chunk_compound_tag = CompoundTag::new();
+/// chunk_compound_tag.insert_i32("xPos", 4);
+///
+/// let mut data = Vec::new();
+/// write_zlib_compound_tag(&mut data, &chunk_compound_tag).unwrap();
+///
+/// let mut builder = RegionBuilder::new();
+/// builder
+///     .insert(RegionChunkPosition::new(4, 2), CompressionScheme::Zlib, data, 1570215508)
+///     .unwrap();
+///
+/// let mut buffer = Vec::new();
+/// builder.write_to(&mut buffer).unwrap();
+///
+/// let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(buffer)).unwrap();
+/// let read_compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+///
+/// assert_eq!(read_compound_tag.get_i32("xPos").unwrap(), 4);
+/// ```
+#[derive(Default)]
+pub struct RegionBuilder {
+    /// Chunks ordered by metadata index.
+    chunks: BTreeMap<usize, BuilderChunk>,
+}
+
+/// Already compressed chunk data.
+struct BuilderChunk {
+    /// Compression scheme of chunk data.
+    compression_scheme: CompressionScheme,
+    /// Compressed chunk data.
+    data: Vec<u8>,
+    /// Last time in seconds when chunk was modified.
+    last_modified_timestamp: u32,
+}
+
+impl BuilderChunk {
+    /// Amount of sectors required to store chunk.
+    fn sectors(&self) -> u32 {
+        chunk_length(&self.data).div_ceil(REGION_SECTOR_BYTES_LENGTH as u32)
+    }
+}
+
+/// Returns length of chunk data with length and compression scheme prefix.
+fn chunk_length(data: &[u8]) -> u32 {
+    // 4 bytes for data length and 1 byte for compression scheme.
+    data.len() as u32 + 5
+}
+
+impl RegionBuilder {
+    pub fn new() -> Self {
+        RegionBuilder {
+            chunks: BTreeMap::new(),
+        }
+    }
+
+    /// Inserts compressed chunk data at the specified position.
+    ///
+    /// If chunk at position was inserted before it will be replaced.
+    pub fn insert(
+        &mut self,
+        position: RegionChunkPosition,
+        compression_scheme: CompressionScheme,
+        data: Vec<u8>,
+        last_modified_timestamp: u32,
+    ) -> Result<&mut Self, ChunkWriteError> {
+        let length = chunk_length(&data);
+
+        if length > BUILDER_CHUNK_MAXIMUM_BYTES_LENGTH {
+            return Err(ChunkWriteError::LengthExceedsMaximum {
+                length,
+                region: None,
+                position: Some(position),
+            });
+        }
+
+        let chunk = BuilderChunk {
+            compression_scheme,
+            data,
+            last_modified_timestamp,
+        };
+
+        self.chunks.insert(position.metadata_index(), chunk);
+
+        Ok(self)
+    }
+
+    /// Writes region with all inserted chunks.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        let mut offsets = [0u32; REGION_CHUNKS];
+        let mut timestamps = [0u32; REGION_CHUNKS];
+
+        // First two sectors are used to store metadata.
+        let mut sector_index =
+            (REGION_HEADER_BYTES_LENGTH / REGION_SECTOR_BYTES_LENGTH as u64) as u32;
+
+        for (index, chunk) in &self.chunks {
+            let sectors = chunk.sectors();
+
+            offsets[*index] = (sector_index << 8) | sectors;
+            timestamps[*index] = chunk.last_modified_timestamp;
+
+            sector_index += sectors;
+        }
+
+        for value in offsets.iter().chain(timestamps.iter()) {
+            writer.write_u32::<BigEndian>(*value)?;
+        }
+
+        let padding = [0u8; REGION_SECTOR_BYTES_LENGTH as usize];
+
+        for chunk in self.chunks.values() {
+            writer.write_u32::<BigEndian>(chunk.data.len() as u32 + 1)?;
+            writer.write_u8(chunk.compression_scheme.id())?;
+            writer.write_all(&chunk.data)?;
+
+            // Padding to align sector.
+            let padding_len =
+                chunk.sectors() * REGION_SECTOR_BYTES_LENGTH as u32 - chunk_length(&chunk.data);
+
+            writer.write_all(&padding[..padding_len as usize])?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::RegionBuilder;
+    use crate::compression::CompressionScheme;
+    use crate::error::ChunkWriteError;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::region::{Region, REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH};
+    use nbt::encode::{write_gzip_compound_tag, write_zlib_compound_tag};
+    use nbt::CompoundTag;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_to() {
+        let mut write_compound_tag_1 = CompoundTag::new();
+        write_compound_tag_1.insert_str("test_str", "test");
+
+        let mut write_compound_tag_2 = CompoundTag::new();
+        write_compound_tag_2.insert_i32_vec("test_i32_vec", (0..3000).collect::<Vec<_>>());
+
+        let mut data_1 = Vec::new();
+        write_gzip_compound_tag(&mut data_1, &write_compound_tag_1).unwrap();
+
+        let mut data_2 = Vec::new();
+        write_zlib_compound_tag(&mut data_2, &write_compound_tag_2).unwrap();
+
+        let mut builder = RegionBuilder::new();
+
+        builder
+            .insert(
+                RegionChunkPosition::new(31, 31),
+                CompressionScheme::Gzip,
+                data_1,
+                1,
+            )
+            .unwrap()
+            .insert(
+                RegionChunkPosition::new(0, 0),
+                CompressionScheme::Zlib,
+                data_2,
+                2,
+            )
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        builder.write_to(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer.len() as u64,
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 3
+        );
+
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(buffer)).unwrap();
+
+        let read_compound_tag_1 = region.read_chunk(RegionChunkPosition::new(31, 31)).unwrap();
+        let read_compound_tag_2 = region.read_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+
+        assert_eq!(read_compound_tag_1.get_str("test_str").unwrap(), "test");
+        assert_eq!(
+            read_compound_tag_2
+                .get_i32_vec("test_i32_vec")
+                .unwrap()
+                .len(),
+            3000
+        );
+    }
+
+    #[test]
+    fn test_insert_length_exceeds_maximum() {
+        let mut builder = RegionBuilder::new();
+        let data = vec![0; REGION_SECTOR_BYTES_LENGTH as usize * 256];
+
+        let error = builder
+            .insert(
+                RegionChunkPosition::new(0, 0),
+                CompressionScheme::Zlib,
+                data,
+                0,
+            )
+            .err()
+            .unwrap();
+
+        match error {
+            ChunkWriteError::LengthExceedsMaximum { length, .. } => {
+                assert_eq!(length, REGION_SECTOR_BYTES_LENGTH as u32 * 256 + 5)
+            }
+            _ => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", error),
+        }
+    }
+
+    #[test]
+    fn test_insert_sectors_count_boundary() {
+        let mut builder = RegionBuilder::new();
+
+        // With prefix chunk takes exactly 256 sectors which doesn't fit into one byte.
+        let data = vec![0; 1_048_571];
+
+        let error = builder
+            .insert(
+                RegionChunkPosition::new(0, 0),
+                CompressionScheme::Zlib,
+                data,
+                0,
+            )
+            .err()
+            .unwrap();
+
+        match error {
+            ChunkWriteError::LengthExceedsMaximum { length, .. } => assert_eq!(length, 1_048_576),
+            _ => panic!("Expected `LengthExceedsMaximum` but got `{:?}`", error),
+        }
+
+        // With prefix chunk takes exactly 255 sectors.
+        let data = vec![0; REGION_SECTOR_BYTES_LENGTH as usize * 255 - 5];
+
+        builder
+            .insert(
+                RegionChunkPosition::new(0, 0),
+                CompressionScheme::Zlib,
+                data,
+                0,
+            )
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        builder.write_to(&mut buffer).unwrap();
+
+        assert_eq!(
+            buffer.len() as u64,
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 255
+        );
+        // Last byte of chunk offset stores sectors count.
+        assert_eq!(buffer[3], 255);
+    }
+}
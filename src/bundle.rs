@@ -0,0 +1,221 @@
+//! Single file bundle of world regions.
+//!
+//! Bundle starts with magic, regions count and index of region entries, each entry
+//! contains region position with offset of region data from the beginning of file and its length.
+//! Regions data are stored one after another as is.
+use crate::position::RegionPosition;
+use crate::provider::{FolderRegionProvider, RegionProvider};
+use crate::region::Region;
+use crate::source::Window;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic which starts bundle.
+const BUNDLE_MAGIC: &[u8] = b"ANVLBNDL";
+/// Length of index entry in bytes.
+const BUNDLE_INDEX_ENTRY_BYTES_LENGTH: u64 = 4 + 4 + 8 + 8;
+
+/// Read only provider of regions stored in bundle.
+pub struct BundleRegionProvider {
+    /// Path to bundle file.
+    path: PathBuf,
+    /// Offset from the beginning of file and length of every region data in bundle.
+    index: BTreeMap<RegionPosition, (u64, u64)>,
+}
+
+impl BundleRegionProvider {
+    /// Opens bundle and reads its index.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+
+        let index = read_index(&mut file)?;
+
+        Ok(BundleRegionProvider { path, index })
+    }
+
+    pub fn iter_positions(&self) -> impl Iterator<Item = RegionPosition> + '_ {
+        self.index.keys().copied()
+    }
+}
+
+impl RegionProvider<Window<File>> for BundleRegionProvider {
+    /// Returns region from bundle or empty region if bundle doesn't contain it.
+    fn get_region(&self, position: RegionPosition) -> Result<Region<Window<File>>, io::Error> {
+        let (offset, len) = self.index.get(&position).copied().unwrap_or((0, 0));
+        let file = File::open(&self.path)?;
+
+        Region::load_at(position, file, offset, len)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.index.contains_key(&position)
+    }
+}
+
+/// Packs all regions of folder into bundle at current writer position.
+///
+/// Regions are opened read only.
+pub fn pack_bundle<W: Write + Seek>(
+    provider: &FolderRegionProvider,
+    writer: &mut W,
+) -> Result<(), io::Error> {
+    let mut positions: Vec<_> = provider.iter_positions()?.collect();
+    positions.sort();
+
+    let index_len = BUNDLE_INDEX_ENTRY_BYTES_LENGTH * positions.len() as u64;
+    let start = writer.stream_position()?;
+
+    writer.write_all(BUNDLE_MAGIC)?;
+    writer.write_u32::<BigEndian>(positions.len() as u32)?;
+    writer.write_all(&vec![0; index_len as usize])?;
+
+    let mut offset = BUNDLE_MAGIC.len() as u64 + 4 + index_len;
+    let mut index = Vec::with_capacity(positions.len());
+
+    for position in positions {
+        let mut region = provider.open_region(position, true)?;
+        let len = region.copy_raw_to(writer)?;
+
+        index.push((position, start + offset, len));
+        offset += len;
+    }
+
+    writer.seek(SeekFrom::Start(start + BUNDLE_MAGIC.len() as u64 + 4))?;
+
+    for (position, offset, len) in index {
+        writer.write_i32::<BigEndian>(position.x)?;
+        writer.write_i32::<BigEndian>(position.z)?;
+        writer.write_u64::<BigEndian>(offset)?;
+        writer.write_u64::<BigEndian>(len)?;
+    }
+
+    writer.seek(SeekFrom::Start(start + offset))?;
+
+    Ok(())
+}
+
+/// Unpacks all regions of bundle into folder.
+pub fn unpack_bundle(
+    bundle: &BundleRegionProvider,
+    provider: &FolderRegionProvider,
+) -> Result<(), io::Error> {
+    for position in bundle.iter_positions() {
+        let mut data = Vec::new();
+        bundle.get_region(position)?.copy_raw_to(&mut data)?;
+
+        let mut region = provider.get_region(position)?;
+        region.replace_from(&mut data.as_slice())?;
+        region.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Reads bundle index.
+fn read_index<R: Read>(reader: &mut R) -> Result<BTreeMap<RegionPosition, (u64, u64)>, io::Error> {
+    let mut magic = [0; BUNDLE_MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+
+    if magic != BUNDLE_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Source is not a region bundle",
+        ));
+    }
+
+    let regions = reader.read_u32::<BigEndian>()?;
+    let mut index = BTreeMap::new();
+
+    for _ in 0..regions {
+        let x = reader.read_i32::<BigEndian>()?;
+        let z = reader.read_i32::<BigEndian>()?;
+        let offset = reader.read_u64::<BigEndian>()?;
+        let len = reader.read_u64::<BigEndian>()?;
+
+        index.insert(RegionPosition::new(x, z), (offset, len));
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{
+        pack_bundle, read_index, unpack_bundle, BundleRegionProvider,
+        BUNDLE_INDEX_ENTRY_BYTES_LENGTH, BUNDLE_MAGIC,
+    };
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+    use std::fs::File;
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_pack_unpack_bundle() {
+        let chunks = [(0, 0), (40, -3)];
+
+        let source_world = temp_world(&chunks);
+        let source_dir = source_world.path();
+        let destination_world = temp_world(&[]);
+        let destination_dir = destination_world.path();
+        let bundle_path = source_dir.join("bundle.bin");
+
+        let source_folder = source_dir.join("region");
+        let source = FolderRegionProvider::new(source_folder.to_str().unwrap());
+
+        let mut bundle_file = File::create(&bundle_path).unwrap();
+        pack_bundle(&source, &mut bundle_file).unwrap();
+
+        let bundle = BundleRegionProvider::open(&bundle_path).unwrap();
+
+        assert_eq!(
+            bundle.iter_positions().collect::<Vec<_>>(),
+            vec![RegionPosition::new(0, 0), RegionPosition::new(1, -1)]
+        );
+
+        let destination_folder = destination_dir.join("region");
+        let destination = FolderRegionProvider::new(destination_folder.to_str().unwrap());
+
+        unpack_bundle(&bundle, &destination).unwrap();
+
+        for &(chunk_x, chunk_z) in &chunks {
+            let position = ChunkPosition::new(chunk_x, chunk_z);
+
+            let mut region = bundle.get_region(position.region()).unwrap();
+            let chunk_compound_tag = region.read_chunk(position.region_chunk()).unwrap();
+            let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), chunk_x);
+
+            let mut region = destination.get_region(position.region()).unwrap();
+            let chunk_compound_tag = region.read_chunk(position.region_chunk()).unwrap();
+            let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), chunk_x);
+        }
+    }
+
+    #[test]
+    fn test_pack_bundle_offsets() {
+        let world = temp_world(&[(0, 0)]);
+        let provider = FolderRegionProvider::new(world.path().join("region").to_str().unwrap());
+
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_all(&[0; 16]).unwrap();
+        pack_bundle(&provider, &mut cursor).unwrap();
+
+        cursor.seek(SeekFrom::Start(16)).unwrap();
+        let index = read_index(&mut cursor).unwrap();
+
+        let data_offset = 16 + BUNDLE_MAGIC.len() as u64 + 4 + BUNDLE_INDEX_ENTRY_BYTES_LENGTH;
+        let (offset, len) = index[&RegionPosition::new(0, 0)];
+
+        assert_eq!(offset, data_offset);
+        assert_eq!(cursor.get_ref().len() as u64, offset + len);
+    }
+}
@@ -0,0 +1,245 @@
+//! Provider wrapper which keeps recently used regions open.
+use crate::error::{ChunkReadError, ChunkWriteError};
+use crate::position::{ChunkPosition, RegionPosition};
+use crate::provider::{ChunkProvider, RegionProvider};
+use crate::region::Region;
+use nbt::CompoundTag;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Seek, Write};
+
+/// Keeps up to specified amount of regions open, so header of region are not
+/// read again on every chunk access.
+///
+/// When cache are full the least recently used region are evicted. Evicted regions
+/// are flushed on drop where flush errors are only logged, call `flush` to receive them.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::cache::CachedRegionProvider;
+/// use anvil_region::position::ChunkPosition;
+/// use anvil_region::provider::FolderRegionProvider;
+///
+/// let provider = FolderRegionProvider::new("test/region");
+/// let mut cache = CachedRegionProvider::new(provider, 16);
+///
+/// let chunk_compound_tag = cache.load_chunk(ChunkPosition::new(4, 2)).unwrap();
+/// let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+///
+/// assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+/// ```
+pub struct CachedRegionProvider<S, P> {
+    /// Provider which regions are cached.
+    provider: P,
+    /// Maximum amount of open regions.
+    capacity: usize,
+    /// Open regions with their last access time.
+    regions: BTreeMap<RegionPosition, CachedRegion<S>>,
+    /// Counter which are increased on every region access.
+    clock: u64,
+}
+
+struct CachedRegion<S> {
+    region: Region<S>,
+    /// Value of clock when region was accessed last time.
+    last_used: u64,
+}
+
+impl<S, P: RegionProvider<S>> CachedRegionProvider<S, P> {
+    /// Creates cache which keeps up to `capacity` regions open, at least one region are kept.
+    pub fn new(provider: P, capacity: usize) -> Self {
+        CachedRegionProvider {
+            provider,
+            capacity: capacity.max(1),
+            regions: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns open region, region are loaded from provider if it not cached.
+    pub fn region(&mut self, position: RegionPosition) -> Result<&mut Region<S>, io::Error> {
+        self.clock += 1;
+
+        if !self.regions.contains_key(&position) {
+            let region = self.provider.get_region(position)?;
+
+            if self.regions.len() >= self.capacity {
+                self.evict_least_recently_used();
+            }
+
+            let cached_region = CachedRegion {
+                region,
+                last_used: self.clock,
+            };
+
+            self.regions.insert(position, cached_region);
+        }
+
+        let cached_region = self.regions.get_mut(&position).unwrap();
+        cached_region.last_used = self.clock;
+
+        Ok(&mut cached_region.region)
+    }
+
+    /// Closes cached region, so the next access loads it from provider again.
+    ///
+    /// Used when region was changed bypassing the cache, for example by another process.
+    pub fn invalidate(&mut self, position: RegionPosition) {
+        self.regions.remove(&position);
+    }
+
+    /// Returns `true` if region are open.
+    pub fn is_cached(&self, position: RegionPosition) -> bool {
+        self.regions.contains_key(&position)
+    }
+
+    /// Returns amount of open regions.
+    pub fn cached_count(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Closes region if it cached and removes it from provider.
+    ///
+    /// Returns whether region existed.
+    pub fn remove_region(&mut self, position: RegionPosition) -> Result<bool, io::Error> {
+        self.regions.remove(&position);
+        self.provider.remove_region(position)
+    }
+
+    /// Returns wrapped provider, open regions are dropped.
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let least_recently_used = self
+            .regions
+            .iter()
+            .min_by_key(|(_, cached_region)| cached_region.last_used)
+            .map(|(position, _)| *position);
+
+        if let Some(position) = least_recently_used {
+            self.regions.remove(&position);
+        }
+    }
+}
+
+impl<S: Read + Seek, P: RegionProvider<S>> CachedRegionProvider<S, P> {
+    /// Reads chunk at position in the world.
+    pub fn load_chunk(&mut self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+        let region = self.region(position.region())?;
+
+        region.read_chunk(position.region_chunk())
+    }
+}
+
+impl<S: Write + Seek, P: RegionProvider<S>> CachedRegionProvider<S, P> {
+    /// Writes chunk at position in the world.
+    pub fn save_chunk(
+        &mut self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        let region = self.region(position.region())?;
+
+        region.write_chunk(position.region_chunk(), chunk_compound_tag)
+    }
+
+    /// Flushes all open regions.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        for cached_region in self.regions.values_mut() {
+            cached_region.region.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Read + Write + Seek, P: RegionProvider<S>> ChunkProvider for CachedRegionProvider<S, P> {
+    fn load_chunk(&mut self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+        CachedRegionProvider::load_chunk(self, position)
+    }
+
+    fn save_chunk(
+        &mut self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        CachedRegionProvider::save_chunk(self, position, chunk_compound_tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::CachedRegionProvider;
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::{ChunkProvider, FolderRegionProvider};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let provider = FolderRegionProvider::new(dir.to_str().unwrap());
+        let mut cache = CachedRegionProvider::new(provider, 2);
+
+        for (index, &(x, z)) in [(0, 0), (32, 0), (0, 1), (64, 0)].iter().enumerate() {
+            let mut chunk_compound_tag = CompoundTag::new();
+            chunk_compound_tag.insert_i32("index", index as i32);
+
+            cache
+                .save_chunk(ChunkPosition::new(x, z), chunk_compound_tag)
+                .unwrap();
+        }
+
+        // Region 0, 0 are used more recently than region 1, 0.
+        assert_eq!(cache.cached_count(), 2);
+        assert!(cache.is_cached(RegionPosition::new(0, 0)));
+        assert!(!cache.is_cached(RegionPosition::new(1, 0)));
+        assert!(cache.is_cached(RegionPosition::new(2, 0)));
+
+        // Evicted region was flushed.
+        let chunk_compound_tag = cache.load_chunk(ChunkPosition::new(32, 0)).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("index").unwrap(), 1);
+        assert!(!cache.is_cached(RegionPosition::new(0, 0)));
+
+        cache.flush().unwrap();
+
+        let chunk_compound_tag = cache.load_chunk(ChunkPosition::new(0, 1)).unwrap();
+        assert_eq!(chunk_compound_tag.get_i32("index").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_chunk_provider() {
+        fn copy_chunk<P: ChunkProvider>(provider: &mut P, from: ChunkPosition, to: ChunkPosition) {
+            let chunk_compound_tag = provider.load_chunk(from).unwrap();
+            provider.save_chunk(to, chunk_compound_tag).unwrap();
+        }
+
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("index", 7);
+
+        let mut cache = CachedRegionProvider::new(FolderRegionProvider::new(dir), 1);
+        cache
+            .save_chunk(ChunkPosition::new(-1, 5), chunk_compound_tag)
+            .unwrap();
+
+        copy_chunk(
+            &mut cache,
+            ChunkPosition::new(-1, 5),
+            ChunkPosition::new(100, -100),
+        );
+        drop(cache);
+
+        let mut cache = CachedRegionProvider::new(FolderRegionProvider::new(dir), 1);
+        let chunk_compound_tag = cache.load_chunk(ChunkPosition::new(100, -100)).unwrap();
+
+        assert_eq!(chunk_compound_tag.get_i32("index").unwrap(), 7);
+    }
+}
@@ -0,0 +1,201 @@
+//! Provider which falls back through chain of providers.
+//!
+//! Useful when hot regions are kept locally and cold regions are stored
+//! in archives or on remote server.
+use crate::position::RegionPosition;
+use crate::provider::RegionProvider;
+use crate::region::Region;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+
+/// Source which can be read and seeked, allows to chain providers with different sources.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Provider which tries every provider in order.
+///
+/// Region are returned by the first provider which contains it, if region can't be
+/// loaded the next provider are tried. Region which no provider contains are
+/// returned by the first provider, so it can be created there.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::chain::{BoxedSourceProvider, ChainRegionProvider};
+/// use anvil_region::position::{RegionChunkPosition, RegionPosition};
+/// use anvil_region::provider::{FolderRegionProvider, RegionProvider};
+/// use anvil_region::storage::{MemoryRegionStorage, StorageRegionProvider};
+///
+/// let local = StorageRegionProvider::new(MemoryRegionStorage::default());
+/// let archive = FolderRegionProvider::read_only("test/region");
+///
+/// let provider = ChainRegionProvider::new()
+///     .with(BoxedSourceProvider::new(local))
+///     .with(BoxedSourceProvider::new(archive));
+///
+/// let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+/// assert!(region.read_chunk(RegionChunkPosition::new(4, 2)).is_ok());
+/// ```
+pub struct ChainRegionProvider<S> {
+    providers: Vec<Box<dyn RegionProvider<S>>>,
+}
+
+impl<S> ChainRegionProvider<S> {
+    pub fn new() -> Self {
+        ChainRegionProvider {
+            providers: Vec::new(),
+        }
+    }
+
+    /// Adds provider to the end of chain.
+    pub fn with<P: RegionProvider<S> + 'static>(mut self, provider: P) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// Returns amount of providers in chain.
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    /// Returns `true` if chain doesn't contain providers.
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+impl<S> Default for ChainRegionProvider<S> {
+    fn default() -> Self {
+        ChainRegionProvider::new()
+    }
+}
+
+impl<S> From<Vec<Box<dyn RegionProvider<S>>>> for ChainRegionProvider<S> {
+    fn from(providers: Vec<Box<dyn RegionProvider<S>>>) -> Self {
+        ChainRegionProvider { providers }
+    }
+}
+
+impl<S> RegionProvider<S> for ChainRegionProvider<S> {
+    fn get_region(&self, position: RegionPosition) -> Result<Region<S>, io::Error> {
+        let mut last_io_error = None;
+
+        for provider in &self.providers {
+            if !provider.region_exists(position) {
+                continue;
+            }
+
+            match provider.get_region(position) {
+                Ok(region) => return Ok(region),
+                Err(io_error) => last_io_error = Some(io_error),
+            }
+        }
+
+        if let Some(io_error) = last_io_error {
+            return Err(io_error);
+        }
+
+        match self.providers.first() {
+            Some(provider) => provider.get_region(position),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Chain doesn't contain providers",
+            )),
+        }
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.providers
+            .iter()
+            .any(|provider| provider.region_exists(position))
+    }
+}
+
+/// Provider which returns regions of wrapped provider with boxed source.
+///
+/// Source of region are reloaded, so region settings of wrapped provider are not kept.
+pub struct BoxedSourceProvider<P, S> {
+    provider: P,
+    _source: PhantomData<fn() -> S>,
+}
+
+impl<P, S> BoxedSourceProvider<P, S> {
+    pub fn new(provider: P) -> Self {
+        BoxedSourceProvider {
+            provider,
+            _source: PhantomData,
+        }
+    }
+}
+
+impl<P, S> RegionProvider<Box<dyn ReadSeek>> for BoxedSourceProvider<P, S>
+where
+    P: RegionProvider<S>,
+    S: Read + Seek + 'static,
+{
+    fn get_region(&self, position: RegionPosition) -> Result<Region<Box<dyn ReadSeek>>, io::Error> {
+        let mut source = self.provider.get_region(position)?.into_inner()?;
+        source.seek(SeekFrom::Start(0))?;
+
+        Region::load(position, Box::new(source))
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.provider.region_exists(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chain::ChainRegionProvider;
+    use crate::fixtures::{minimal_chunk, minimal_world, temp_world};
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+
+    #[test]
+    fn test_fallback() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+        let cold_world = dir.join("cold");
+        let hot_world = dir.join("hot");
+        minimal_world(&cold_world, &[(0, 0), (32, 0)]).unwrap();
+        minimal_world(&hot_world, &[(1, 1)]).unwrap();
+
+        let cold_folder = cold_world.join("region");
+        let hot_folder = hot_world.join("region");
+
+        let provider = ChainRegionProvider::new()
+            .with(FolderRegionProvider::new(&hot_folder))
+            .with(FolderRegionProvider::read_only(cold_folder));
+
+        assert_eq!(provider.len(), 2);
+        assert!(provider.region_exists(RegionPosition::new(1, 0)));
+        assert!(!provider.region_exists(RegionPosition::new(2, 0)));
+
+        // Region are contained by both providers, the first one are used.
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(region.contains_chunk(RegionChunkPosition::new(1, 1)));
+        assert!(!region.contains_chunk(RegionChunkPosition::new(0, 0)));
+
+        let region = provider.get_region(RegionPosition::new(1, 0)).unwrap();
+        assert!(region.contains_chunk(RegionChunkPosition::new(0, 0)));
+
+        // Missing region are created by the first provider.
+        let mut region = provider.get_region(RegionPosition::new(2, 0)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(0, 0), minimal_chunk(64, 0))
+            .unwrap();
+
+        assert!(hot_folder.join("r.2.0.mca").exists());
+    }
+
+    #[test]
+    fn test_empty_chain() {
+        let provider = ChainRegionProvider::<std::fs::File>::new();
+
+        assert!(provider.is_empty());
+        assert!(provider.get_region(RegionPosition::new(0, 0)).is_err());
+    }
+}
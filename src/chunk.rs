@@ -0,0 +1,153 @@
+//! Helpers to create chunk compound tags which are accepted by the game.
+use crate::position::ChunkPosition;
+use nbt::{CompoundTag, Tag};
+
+/// First data version where chunk data are stored in root instead of `Level` compound tag.
+pub const ROOT_CHUNK_DATA_VERSION: i32 = 2844;
+
+/// Builds chunk compound tag with mandatory fields filled for specified data version.
+///
+/// Chunk contains no sections, so the game treats it as empty chunk and
+/// recalculates heightmaps and light.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::chunk::ChunkTagBuilder;
+/// use anvil_region::position::ChunkPosition;
+///
+/// let chunk_compound_tag = ChunkTagBuilder::new(3465, ChunkPosition::new(4, 2))
+///     .insert("custom", 42)
+///     .build();
+///
+/// assert_eq!(chunk_compound_tag.get_i32("DataVersion").unwrap(), 3465);
+/// assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), 4);
+/// assert_eq!(chunk_compound_tag.get_i32("custom").unwrap(), 42);
+/// ```
+pub struct ChunkTagBuilder {
+    /// Data version of the game which format are used.
+    data_version: i32,
+    /// Chunk position in the world.
+    position: ChunkPosition,
+    /// Chunk generation status.
+    status: String,
+    /// Additional tags which are inserted after mandatory fields.
+    tags: Vec<(String, Tag)>,
+}
+
+impl ChunkTagBuilder {
+    pub fn new(data_version: i32, position: ChunkPosition) -> Self {
+        let status = if data_version >= ROOT_CHUNK_DATA_VERSION {
+            "minecraft:full"
+        } else {
+            "full"
+        };
+
+        ChunkTagBuilder {
+            data_version,
+            position,
+            status: status.to_owned(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Sets chunk generation status, by default chunk are fully generated.
+    pub fn status(mut self, status: &str) -> Self {
+        self.status = status.to_owned();
+        self
+    }
+
+    /// Inserts tag to chunk data, mandatory fields with the same name are replaced.
+    ///
+    /// Before 1.18 chunk data are stored in `Level` compound tag.
+    pub fn insert(mut self, name: &str, tag: impl Into<Tag>) -> Self {
+        self.tags.push((name.to_owned(), tag.into()));
+        self
+    }
+
+    pub fn build(self) -> CompoundTag {
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_i32("DataVersion", self.data_version);
+
+        let mut data_compound_tag = CompoundTag::new();
+        data_compound_tag.insert_i32("xPos", self.position.x);
+        data_compound_tag.insert_i32("zPos", self.position.z);
+        data_compound_tag.insert_str("Status", self.status);
+        data_compound_tag.insert_i64("LastUpdate", 0);
+        data_compound_tag.insert_i64("InhabitedTime", 0);
+        data_compound_tag.insert_bool("isLightOn", false);
+        data_compound_tag.insert_compound_tag("Heightmaps", CompoundTag::new());
+
+        if self.data_version >= ROOT_CHUNK_DATA_VERSION {
+            // Since 1.18 world starts from y -64 which are section -4.
+            data_compound_tag.insert_i32("yPos", -4);
+            data_compound_tag.insert_compound_tag_vec("sections", Vec::new());
+            data_compound_tag.insert_compound_tag_vec("block_entities", Vec::new());
+        } else {
+            data_compound_tag.insert_compound_tag_vec("Sections", Vec::new());
+            data_compound_tag.insert_compound_tag_vec("TileEntities", Vec::new());
+            data_compound_tag.insert_compound_tag_vec("Entities", Vec::new());
+        }
+
+        for (name, tag) in self.tags {
+            data_compound_tag.insert(name, tag);
+        }
+
+        if self.data_version >= ROOT_CHUNK_DATA_VERSION {
+            for (name, tag) in data_compound_tag.iter() {
+                chunk_compound_tag.insert(name, tag.clone());
+            }
+        } else {
+            chunk_compound_tag.insert_compound_tag("Level", data_compound_tag);
+        }
+
+        chunk_compound_tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkTagBuilder;
+    use crate::position::ChunkPosition;
+
+    #[test]
+    fn test_build_root_chunk() {
+        let chunk_compound_tag = ChunkTagBuilder::new(3465, ChunkPosition::new(-3, 7))
+            .insert("InhabitedTime", 100i64)
+            .build();
+
+        assert_eq!(chunk_compound_tag.get_i32("DataVersion").unwrap(), 3465);
+        assert_eq!(chunk_compound_tag.get_i32("xPos").unwrap(), -3);
+        assert_eq!(chunk_compound_tag.get_i32("zPos").unwrap(), 7);
+        assert_eq!(chunk_compound_tag.get_i32("yPos").unwrap(), -4);
+        assert_eq!(
+            chunk_compound_tag.get_str("Status").unwrap(),
+            "minecraft:full"
+        );
+        assert_eq!(chunk_compound_tag.get_i64("InhabitedTime").unwrap(), 100);
+        assert!(chunk_compound_tag
+            .get_compound_tag_vec("sections")
+            .unwrap()
+            .is_empty());
+        assert!(!chunk_compound_tag.contains_key("Level"));
+    }
+
+    #[test]
+    fn test_build_level_chunk() {
+        let chunk_compound_tag = ChunkTagBuilder::new(2586, ChunkPosition::new(31, 16))
+            .status("features")
+            .build();
+
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(chunk_compound_tag.get_i32("DataVersion").unwrap(), 2586);
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 31);
+        assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 16);
+        assert_eq!(level_compound_tag.get_str("Status").unwrap(), "features");
+        assert!(level_compound_tag
+            .get_compound_tag_vec("Sections")
+            .unwrap()
+            .is_empty());
+        assert!(!level_compound_tag.contains_key("yPos"));
+    }
+}
@@ -0,0 +1,52 @@
+/// Gzip compression type value.
+const GZIP_COMPRESSION_TYPE: u8 = 1;
+/// Zlib compression type value.
+const ZLIB_COMPRESSION_TYPE: u8 = 2;
+/// Uncompressed type value.
+const UNCOMPRESSED_COMPRESSION_TYPE: u8 = 3;
+/// LZ4 compression type value.
+#[cfg(feature = "lz4")]
+const LZ4_COMPRESSION_TYPE: u8 = 4;
+
+/// Compression scheme which are used to store chunk data.
+///
+/// Variants depends on enabled features and can be added in minor releases,
+/// so matching requires a wildcard arm.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[non_exhaustive]
+pub enum CompressionScheme {
+    /// Gzip compression, not used by the game in practice.
+    Gzip,
+    /// Zlib compression, default for the game.
+    Zlib,
+    /// Chunk data stored without compression.
+    Uncompressed,
+    /// LZ4 compression, used by the game since 24w04a when configured.
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl CompressionScheme {
+    /// Returns compression scheme by type id stored in chunk data.
+    pub fn from_id(id: u8) -> Option<CompressionScheme> {
+        match id {
+            GZIP_COMPRESSION_TYPE => Some(CompressionScheme::Gzip),
+            ZLIB_COMPRESSION_TYPE => Some(CompressionScheme::Zlib),
+            UNCOMPRESSED_COMPRESSION_TYPE => Some(CompressionScheme::Uncompressed),
+            #[cfg(feature = "lz4")]
+            LZ4_COMPRESSION_TYPE => Some(CompressionScheme::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Returns compression scheme type id stored in chunk data.
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressionScheme::Gzip => GZIP_COMPRESSION_TYPE,
+            CompressionScheme::Zlib => ZLIB_COMPRESSION_TYPE,
+            CompressionScheme::Uncompressed => UNCOMPRESSED_COMPRESSION_TYPE,
+            #[cfg(feature = "lz4")]
+            CompressionScheme::Lz4 => LZ4_COMPRESSION_TYPE,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Storage of region files encrypted at rest with AES-256-GCM.
+//!
+//! Every region are stored as `r.x.z.mca.enc` which contains random nonce followed by
+//! encrypted region file. Region filename are authenticated with region data, so
+//! encrypted file can't be swapped with file of another region unnoticed.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::position::{RegionChunkPosition, RegionPosition};
+//! use anvil_region::provider::{FolderRegionProvider, RegionProvider};
+//! use nbt::CompoundTag;
+//!
+//! # let folder = std::env::temp_dir().join("anvil-region-encryption-doc");
+//! # let _ = std::fs::remove_dir_all(&folder);
+//! let key = [42; 32];
+//! let mut provider = FolderRegionProvider::new(&folder).encrypted(&key);
+//!
+//! let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! region.write_chunk(RegionChunkPosition::new(4, 2), CompoundTag::new()).unwrap();
+//!
+//! provider.save_region(region).unwrap();
+//! provider.flush().unwrap();
+//!
+//! let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! assert!(region.contains_chunk(RegionChunkPosition::new(4, 2)));
+//! # std::fs::remove_dir_all(&folder).unwrap();
+//! ```
+use crate::position::RegionPosition;
+use crate::provider::{FilenameScheme, FolderRegionProvider};
+use crate::storage::{write_atomically, RegionStorage, StorageRegionProvider};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Extension which are appended to region filename of encrypted region.
+const ENCRYPTED_EXTENSION: &str = "enc";
+/// Length of nonce which starts encrypted file.
+const NONCE_BYTES_LENGTH: usize = 12;
+
+/// Storage of encrypted region files in folder.
+pub struct EncryptedRegionStorage {
+    /// Folder where region files located.
+    folder_path: PathBuf,
+    /// Naming of region files before encrypted extension are appended.
+    filename_scheme: FilenameScheme,
+    cipher: Aes256Gcm,
+}
+
+impl EncryptedRegionStorage {
+    /// Creates storage which encrypts regions with 256 bit key.
+    pub fn new<P: AsRef<Path>>(folder: P, key: &[u8; 32]) -> Self {
+        EncryptedRegionStorage {
+            folder_path: folder.as_ref().to_path_buf(),
+            filename_scheme: FilenameScheme::default(),
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Sets naming of region files, Anvil scheme are used by default.
+    pub fn filename_scheme(mut self, filename_scheme: FilenameScheme) -> Self {
+        self.filename_scheme = filename_scheme;
+        self
+    }
+
+    fn encrypted_region_path(&self, position: RegionPosition) -> PathBuf {
+        let filename = self.filename_scheme.filename(position);
+
+        self.folder_path
+            .join(format!("{}.{}", filename, ENCRYPTED_EXTENSION))
+    }
+}
+
+impl RegionStorage for EncryptedRegionStorage {
+    /// Returns decrypted region, region which can't be decrypted with the key
+    /// or was modified are returned as `InvalidData` error.
+    fn get(&self, position: RegionPosition) -> Result<Option<Vec<u8>>, io::Error> {
+        let encrypted_data = match fs::read(self.encrypted_region_path(position)) {
+            Ok(encrypted_data) => encrypted_data,
+            Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(io_error) => return Err(io_error),
+        };
+
+        if encrypted_data.len() < NONCE_BYTES_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Encrypted region are shorter than nonce",
+            ));
+        }
+
+        let (nonce, encrypted_data) = encrypted_data.split_at(NONCE_BYTES_LENGTH);
+        let filename = self.filename_scheme.filename(position);
+
+        let payload = Payload {
+            msg: encrypted_data,
+            aad: filename.as_bytes(),
+        };
+
+        let data = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Region decryption failed"))?;
+
+        Ok(Some(data))
+    }
+
+    /// Encrypts region with random nonce and replaces region file atomically.
+    fn put(&mut self, position: RegionPosition, data: Vec<u8>) -> Result<(), io::Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let filename = self.filename_scheme.filename(position);
+
+        let payload = Payload {
+            msg: &data,
+            aad: filename.as_bytes(),
+        };
+
+        let encrypted_data = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| io::Error::other("Region encryption failed"))?;
+
+        let mut file_data = Vec::with_capacity(NONCE_BYTES_LENGTH + encrypted_data.len());
+        file_data.extend_from_slice(&nonce);
+        file_data.extend_from_slice(&encrypted_data);
+
+        fs::create_dir_all(&self.folder_path)?;
+
+        write_atomically(&self.encrypted_region_path(position), &file_data)
+    }
+
+    /// Returns positions of encrypted regions, missing folder are treated as empty.
+    fn list(&self) -> Result<Vec<RegionPosition>, io::Error> {
+        let mut positions = Vec::new();
+
+        let entries = match fs::read_dir(&self.folder_path) {
+            Ok(entries) => entries,
+            Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => return Ok(positions),
+            Err(io_error) => return Err(io_error),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            match path.extension() {
+                Some(extension) if extension == ENCRYPTED_EXTENSION => {}
+                _ => continue,
+            }
+
+            if let Ok(position) = self.filename_scheme.position(&path.with_extension("")) {
+                positions.push(position);
+            }
+        }
+
+        positions.sort();
+
+        Ok(positions)
+    }
+}
+
+impl FolderRegionProvider {
+    /// Returns provider of regions in folder which are stored encrypted with 256 bit key.
+    ///
+    /// Regions are decrypted into memory, changed regions must be passed to `save_region`
+    /// and written on `flush`. Filename scheme of this provider are used for encrypted files,
+    /// other settings are not applied to regions.
+    pub fn encrypted(self, key: &[u8; 32]) -> StorageRegionProvider<EncryptedRegionStorage> {
+        let storage = EncryptedRegionStorage::new(self.folder_path(), key)
+            .filename_scheme(self.filename_scheme);
+
+        StorageRegionProvider::new(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{minimal_chunk, temp_world};
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::{FilenameScheme, FolderRegionProvider, RegionProvider};
+    use std::fs;
+    use std::io;
+
+    #[test]
+    fn test_encrypted_at_rest() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("encrypted-storage");
+
+        let mut provider = FolderRegionProvider::new(&dir).encrypted(&[7; 32]);
+
+        let mut region = provider.get_region(RegionPosition::new(-1, 2)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(31, 0), minimal_chunk(-1, 64))
+            .unwrap();
+
+        provider.save_region(region).unwrap();
+        provider.flush().unwrap();
+
+        assert!(dir.join("r.-1.2.mca.enc").exists());
+        assert!(!dir.join("r.-1.2.mca").exists());
+
+        let provider = FolderRegionProvider::new(&dir).encrypted(&[7; 32]);
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(-1, 2)]
+        );
+
+        let mut region = provider.get_region(RegionPosition::new(-1, 2)).unwrap();
+        assert!(region.read_chunk(RegionChunkPosition::new(31, 0)).is_ok());
+
+        let provider = FolderRegionProvider::new(&dir).encrypted(&[8; 32]);
+        let io_error = provider
+            .get_region(RegionPosition::new(-1, 2))
+            .err()
+            .unwrap();
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+
+        // Region file swapped with file of another region are not decrypted.
+        fs::copy(dir.join("r.-1.2.mca.enc"), dir.join("r.0.0.mca.enc")).unwrap();
+
+        let provider = FolderRegionProvider::new(&dir).encrypted(&[7; 32]);
+        assert!(provider.get_region(RegionPosition::new(0, 0)).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_filename_scheme() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("encrypted-mcregion");
+
+        let provider = FolderRegionProvider::new(&dir).encrypted(&[7; 32]);
+        assert_eq!(provider.iter_positions().unwrap().count(), 0);
+
+        let mut provider = FolderRegionProvider::new(&dir)
+            .filename_scheme(FilenameScheme::mcregion())
+            .encrypted(&[7; 32]);
+
+        let mut region = provider.get_region(RegionPosition::new(1, -1)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(0, 31), minimal_chunk(32, -1))
+            .unwrap();
+
+        provider.save_region(region).unwrap();
+        provider.flush().unwrap();
+
+        assert!(dir.join("r.1.-1.mcr.enc").exists());
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(1, -1)]
+        );
+
+        let provider = FolderRegionProvider::new(&dir).encrypted(&[7; 32]);
+        assert_eq!(provider.iter_positions().unwrap().count(), 0);
+    }
+}
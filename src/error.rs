@@ -1,12 +1,21 @@
-use crate::position::RegionChunkPosition;
+use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
 use nbt::decode::TagDecodeError;
+use std::error::Error;
+use std::fmt;
 use std::io;
 
 /// Possible errors while loading the chunk.
+///
+/// Variants can be added in minor releases, so matching requires a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ChunkReadError {
     /// Chunk at specified coordinates inside region not found.
-    ChunkNotFound { position: RegionChunkPosition },
+    ChunkNotFound {
+        position: RegionChunkPosition,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+    },
     /// Chunk length overlaps declared maximum.
     ///
     /// This should not occur under normal conditions.
@@ -17,6 +26,51 @@ pub enum ChunkReadError {
         length: u32,
         /// Chunk maximum expected length.
         maximum_length: u32,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+    /// Chunk data length prefix are zero, so there is not even compression scheme byte.
+    ///
+    /// Region file are corrupted.
+    ZeroLength {
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+    /// Header entry of chunk points to sector after the end of region file.
+    ///
+    /// Region file are truncated or header are corrupted.
+    OffsetPastEnd {
+        position: RegionChunkPosition,
+        /// Sector where chunk should start.
+        start_sector_index: u32,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+    },
+    /// Header entry of chunk has offset, but zero sectors count.
+    ///
+    /// Header are corrupted.
+    ZeroSectorsCount {
+        position: RegionChunkPosition,
+        /// Sector where chunk should start.
+        start_sector_index: u32,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+    },
+    /// Sectors of chunk are also used by another chunk.
+    ///
+    /// Returned only when read verification are enabled.
+    ///
+    /// Header are corrupted, data of at least one of chunks are overwritten.
+    OverlappingSectors {
+        position: RegionChunkPosition,
+        /// Position of another chunk which uses the same sectors.
+        other_position: RegionChunkPosition,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
     },
     /// Currently are only 2 types of compression: Gzip and Zlib.
     ///
@@ -26,31 +80,301 @@ pub enum ChunkReadError {
     UnsupportedCompressionScheme {
         /// Compression scheme type id.
         compression_scheme: u8,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+    /// Chunk header entry was changed while chunk data were read.
+    ///
+    /// Returned only when read verification are enabled.
+    ///
+    /// Region file are modified by another process.
+    ConcurrentModification {
+        position: RegionChunkPosition,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
     },
     /// I/O Error which happened while were reading chunk data from region file.
-    IOError { io_error: io::Error },
+    IOError {
+        io_error: io::Error,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
     /// Error while decoding binary data to NBT tag.
     ///
     /// This should not occur under normal conditions.
     ///
     /// Region file are corrupted or a developer error in the NBT library.
-    TagDecodeError { tag_decode_error: TagDecodeError },
+    TagDecodeError {
+        tag_decode_error: TagDecodeError,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+}
+
+impl ChunkReadError {
+    /// Returns `true` if chunk are not present in region.
+    ///
+    /// Chunk can be generated from scratch.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ChunkReadError::ChunkNotFound { .. })
+    }
+
+    /// Returns `true` if region file or chunk data are corrupted.
+    ///
+    /// Reading chunk again will fail the same way, chunk can be skipped or regenerated.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            ChunkReadError::LengthExceedsMaximum { .. }
+            | ChunkReadError::ZeroLength { .. }
+            | ChunkReadError::OffsetPastEnd { .. }
+            | ChunkReadError::ZeroSectorsCount { .. }
+            | ChunkReadError::OverlappingSectors { .. }
+            | ChunkReadError::UnsupportedCompressionScheme { .. }
+            | ChunkReadError::TagDecodeError { .. } => true,
+            ChunkReadError::IOError { io_error, .. } => is_corruption_io_error(io_error),
+            ChunkReadError::ChunkNotFound { .. }
+            | ChunkReadError::ConcurrentModification { .. } => false,
+        }
+    }
+
+    /// Returns `true` if reading failed because of I/O error which doesn't mean
+    /// corruption, e.g. missing permissions or failed storage.
+    ///
+    /// Such errors are usually not caused by chunk itself, so processing should be aborted.
+    pub fn is_io(&self) -> bool {
+        match self {
+            ChunkReadError::IOError { io_error, .. } => !is_corruption_io_error(io_error),
+            _ => false,
+        }
+    }
+
+    /// Fills unknown region and chunk position of error.
+    pub(crate) fn with_context(
+        mut self,
+        region_position: RegionPosition,
+        chunk_position: Option<RegionChunkPosition>,
+    ) -> Self {
+        match &mut self {
+            ChunkReadError::LengthExceedsMaximum {
+                region, position, ..
+            }
+            | ChunkReadError::ZeroLength { region, position }
+            | ChunkReadError::UnsupportedCompressionScheme {
+                region, position, ..
+            }
+            | ChunkReadError::IOError {
+                region, position, ..
+            }
+            | ChunkReadError::TagDecodeError {
+                region, position, ..
+            } => {
+                region.get_or_insert(region_position);
+                *position = position.or(chunk_position);
+            }
+            ChunkReadError::ChunkNotFound { region, .. }
+            | ChunkReadError::OffsetPastEnd { region, .. }
+            | ChunkReadError::ZeroSectorsCount { region, .. }
+            | ChunkReadError::OverlappingSectors { region, .. }
+            | ChunkReadError::ConcurrentModification { region, .. } => {
+                region.get_or_insert(region_position);
+            }
+        }
+
+        self
+    }
 }
 
 impl From<io::Error> for ChunkReadError {
     fn from(io_error: io::Error) -> Self {
-        ChunkReadError::IOError { io_error }
+        ChunkReadError::IOError {
+            io_error,
+            region: None,
+            position: None,
+        }
     }
 }
 
 impl From<TagDecodeError> for ChunkReadError {
     fn from(tag_decode_error: TagDecodeError) -> Self {
-        ChunkReadError::TagDecodeError { tag_decode_error }
+        ChunkReadError::TagDecodeError {
+            tag_decode_error,
+            region: None,
+            position: None,
+        }
+    }
+}
+
+impl fmt::Display for ChunkReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkReadError::ChunkNotFound { position, region } => {
+                write!(f, "Chunk {} not found", position)?;
+                fmt_context(f, region, &None)
+            }
+            ChunkReadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+                region,
+                position,
+            } => {
+                write!(
+                    f,
+                    "Chunk length {} exceeds maximum {}",
+                    length, maximum_length
+                )?;
+                fmt_context(f, region, position)
+            }
+            ChunkReadError::ZeroLength { region, position } => {
+                write!(f, "Chunk length are zero")?;
+                fmt_context(f, region, position)
+            }
+            ChunkReadError::OffsetPastEnd {
+                position,
+                start_sector_index,
+                region,
+            } => {
+                write!(
+                    f,
+                    "Chunk {} starts at sector {} after the end of region",
+                    position, start_sector_index
+                )?;
+                fmt_context(f, region, &None)
+            }
+            ChunkReadError::ZeroSectorsCount {
+                position,
+                start_sector_index,
+                region,
+            } => {
+                write!(
+                    f,
+                    "Chunk {} starts at sector {} but has zero sectors",
+                    position, start_sector_index
+                )?;
+                fmt_context(f, region, &None)
+            }
+            ChunkReadError::OverlappingSectors {
+                position,
+                other_position,
+                region,
+            } => {
+                write!(
+                    f,
+                    "Chunk {} sectors overlap sectors of chunk {}",
+                    position, other_position
+                )?;
+                fmt_context(f, region, &None)
+            }
+            ChunkReadError::UnsupportedCompressionScheme {
+                compression_scheme,
+                region,
+                position,
+            } => {
+                write!(f, "Unsupported compression scheme {}", compression_scheme)?;
+                fmt_context(f, region, position)
+            }
+            ChunkReadError::ConcurrentModification { position, region } => {
+                write!(f, "Chunk {} was modified while were read", position)?;
+                fmt_context(f, region, &None)
+            }
+            ChunkReadError::IOError {
+                io_error,
+                region,
+                position,
+            } => {
+                write!(f, "I/O error")?;
+                fmt_context(f, region, position)?;
+                write!(f, ": {}", io_error)
+            }
+            ChunkReadError::TagDecodeError {
+                tag_decode_error,
+                region,
+                position,
+            } => {
+                write!(f, "Chunk decode error")?;
+                fmt_context(f, region, position)?;
+                write!(f, ": {:?}", tag_decode_error)
+            }
+        }
+    }
+}
+
+impl Error for ChunkReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChunkReadError::IOError { io_error, .. } => Some(io_error),
+            _ => None,
+        }
+    }
+}
+
+/// Chunk position are outside of region, both coordinates must be less than 32.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct PositionOutOfBoundsError {
+    pub x: u8,
+    pub z: u8,
+}
+
+impl fmt::Display for PositionOutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Region chunk position x: {}, z: {} out of bounds",
+            self.x, self.z
+        )
+    }
+}
+
+impl Error for PositionOutOfBoundsError {}
+
+/// Position string doesn't match position format.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ParsePositionError {
+    /// String which failed to be parsed.
+    pub input: String,
+}
+
+impl ParsePositionError {
+    pub(crate) fn new(input: &str) -> Self {
+        ParsePositionError {
+            input: input.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid position: {}", self.input)
+    }
+}
+
+impl Error for ParsePositionError {}
+
+/// Filename doesn't match filename scheme of region files.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct InvalidRegionFilename {
+    /// Filename which failed to be parsed.
+    pub name: String,
+}
+
+impl fmt::Display for InvalidRegionFilename {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid region filename: {}", self.name)
     }
 }
 
+impl Error for InvalidRegionFilename {}
+
 /// Possible errors while saving the chunk.
+///
+/// Variants can be added in minor releases, so matching requires a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ChunkWriteError {
     /// Chunk length exceeds 1 MB.
     ///
@@ -58,13 +382,240 @@ pub enum ChunkWriteError {
     LengthExceedsMaximum {
         /// Chunk length.
         length: u32,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+    /// Chunk coordinates tags doesn't match position where chunk are written.
+    PositionMismatch {
+        /// Position where chunk are written.
+        expected: ChunkPosition,
+        /// Position from chunk coordinates tags or `None` if tags are missing.
+        found: Option<ChunkPosition>,
     },
     /// I/O Error which happened while were writing chunk data to region.
-    IOError { io_error: io::Error },
+    IOError {
+        io_error: io::Error,
+        /// Position of region where error happened if known.
+        region: Option<RegionPosition>,
+        /// Position of chunk inside region where error happened if known.
+        position: Option<RegionChunkPosition>,
+    },
+}
+
+impl ChunkWriteError {
+    /// Returns `true` if writing failed because of I/O error.
+    pub fn is_io(&self) -> bool {
+        matches!(self, ChunkWriteError::IOError { .. })
+    }
+
+    /// Fills unknown region and chunk position of error.
+    pub(crate) fn with_context(
+        mut self,
+        region_position: RegionPosition,
+        chunk_position: Option<RegionChunkPosition>,
+    ) -> Self {
+        match &mut self {
+            ChunkWriteError::LengthExceedsMaximum {
+                region, position, ..
+            }
+            | ChunkWriteError::IOError {
+                region, position, ..
+            } => {
+                region.get_or_insert(region_position);
+                *position = position.or(chunk_position);
+            }
+            // Chunk position in the world already identifies region.
+            ChunkWriteError::PositionMismatch { .. } => {}
+        }
+
+        self
+    }
 }
 
 impl From<io::Error> for ChunkWriteError {
     fn from(io_error: io::Error) -> Self {
-        ChunkWriteError::IOError { io_error }
+        ChunkWriteError::IOError {
+            io_error,
+            region: None,
+            position: None,
+        }
+    }
+}
+
+impl fmt::Display for ChunkWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkWriteError::LengthExceedsMaximum {
+                length,
+                region,
+                position,
+            } => {
+                write!(f, "Chunk length {} exceeds maximum", length)?;
+                fmt_context(f, region, position)
+            }
+            ChunkWriteError::PositionMismatch { expected, found } => match found {
+                Some(found) => write!(f, "Expected {} but chunk tags contain {}", expected, found),
+                None => write!(f, "Expected {} but chunk tags are missing", expected),
+            },
+            ChunkWriteError::IOError {
+                io_error,
+                region,
+                position,
+            } => {
+                write!(f, "I/O error")?;
+                fmt_context(f, region, position)?;
+                write!(f, ": {}", io_error)
+            }
+        }
+    }
+}
+
+impl Error for ChunkWriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChunkWriteError::IOError { io_error, .. } => Some(io_error),
+            _ => None,
+        }
+    }
+}
+
+/// Possible errors while copying the chunk.
+#[derive(Debug)]
+pub enum ChunkCopyError {
+    /// Error which happened while were reading chunk from source.
+    ReadError { read_error: ChunkReadError },
+    /// Error which happened while were writing chunk to destination.
+    WriteError { write_error: ChunkWriteError },
+}
+
+impl From<ChunkReadError> for ChunkCopyError {
+    fn from(read_error: ChunkReadError) -> Self {
+        ChunkCopyError::ReadError { read_error }
+    }
+}
+
+impl From<ChunkWriteError> for ChunkCopyError {
+    fn from(write_error: ChunkWriteError) -> Self {
+        ChunkCopyError::WriteError { write_error }
+    }
+}
+
+impl fmt::Display for ChunkCopyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkCopyError::ReadError { read_error } => write!(f, "Read failed: {}", read_error),
+            ChunkCopyError::WriteError { write_error } => {
+                write!(f, "Write failed: {}", write_error)
+            }
+        }
+    }
+}
+
+impl Error for ChunkCopyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChunkCopyError::ReadError { read_error } => Some(read_error),
+            ChunkCopyError::WriteError { write_error } => Some(write_error),
+        }
+    }
+}
+
+/// Returns `true` if I/O error are caused by invalid or truncated data.
+fn is_corruption_io_error(io_error: &io::Error) -> bool {
+    matches!(
+        io_error.kind(),
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+    )
+}
+
+fn fmt_context(
+    f: &mut fmt::Formatter<'_>,
+    region: &Option<RegionPosition>,
+    position: &Option<RegionChunkPosition>,
+) -> fmt::Result {
+    if let Some(region) = region {
+        write!(f, " in {}", region)?;
+    }
+
+    if let Some(position) = position {
+        write!(f, " at {}", position)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{
+        ChunkReadError, ChunkWriteError, ParsePositionError, PositionOutOfBoundsError,
+    };
+    use crate::position::RegionChunkPosition;
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn test_classification() {
+        let position = RegionChunkPosition::new(4, 2);
+
+        let not_found = ChunkReadError::ChunkNotFound {
+            position,
+            region: None,
+        };
+        assert!(not_found.is_not_found());
+        assert!(!not_found.is_corruption());
+        assert!(!not_found.is_io());
+
+        let overlapping = ChunkReadError::OverlappingSectors {
+            position,
+            other_position: RegionChunkPosition::new(0, 0),
+            region: None,
+        };
+        assert!(overlapping.is_corruption());
+        assert!(!overlapping.is_io());
+
+        let truncated = ChunkReadError::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(truncated.is_corruption());
+        assert!(!truncated.is_io());
+
+        let denied = ChunkReadError::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(!denied.is_corruption());
+        assert!(denied.is_io());
+
+        let modified = ChunkReadError::ConcurrentModification {
+            position,
+            region: None,
+        };
+        assert!(!modified.is_not_found() && !modified.is_corruption() && !modified.is_io());
+
+        let write_error = ChunkWriteError::from(io::Error::from(io::ErrorKind::WriteZero));
+        assert!(write_error.is_io());
+
+        let length_error = ChunkWriteError::LengthExceedsMaximum {
+            length: 1,
+            region: None,
+            position: None,
+        };
+        assert!(!length_error.is_io());
+    }
+
+    #[test]
+    fn test_position_out_of_bounds_error() {
+        let error: Box<dyn Error> = Box::new(PositionOutOfBoundsError { x: 32, z: 1 });
+
+        assert_eq!(
+            error.to_string(),
+            "Region chunk position x: 32, z: 1 out of bounds"
+        );
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn test_parse_position_error() {
+        let error: Box<dyn Error> = Box::new(ParsePositionError::new("r.a.0"));
+
+        assert_eq!(error.to_string(), "Invalid position: r.a.0");
+        assert!(error.source().is_none());
     }
 }
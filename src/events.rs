@@ -0,0 +1,174 @@
+//! Append-only log of chunk changes which allows external systems to follow world changes.
+//!
+//! Every event are stored as a fixed length big endian record: chunk x and z coordinates,
+//! event kind and time in seconds when event happened. Event sequence number are its
+//! record index in log.
+use crate::position::ChunkPosition;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Event record length in bytes.
+const EVENT_RECORD_BYTES_LENGTH: u64 = 13;
+
+/// Kind of chunk change.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ChunkEventKind {
+    /// Chunk was written.
+    Write,
+    /// Chunk was removed.
+    Remove,
+}
+
+impl ChunkEventKind {
+    fn id(&self) -> u8 {
+        match self {
+            ChunkEventKind::Write => 1,
+            ChunkEventKind::Remove => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(ChunkEventKind::Write),
+            2 => Some(ChunkEventKind::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// Chunk change recorded in event log.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct ChunkEvent {
+    /// Event index in log.
+    pub sequence: u64,
+    /// Position of changed chunk.
+    pub position: ChunkPosition,
+    pub kind: ChunkEventKind,
+    /// Time in seconds when event happened.
+    pub timestamp: u32,
+}
+
+/// Writer of chunk changes to event log file.
+pub struct EventLog {
+    file: File,
+}
+
+impl EventLog {
+    /// Opens event log for appending, file are created if not exists.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+
+        Ok(EventLog { file })
+    }
+
+    /// Appends chunk change with current time to log.
+    pub fn record(
+        &mut self,
+        position: ChunkPosition,
+        kind: ChunkEventKind,
+    ) -> Result<(), io::Error> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        let mut buffer = Vec::with_capacity(EVENT_RECORD_BYTES_LENGTH as usize);
+        buffer.write_i32::<BigEndian>(position.x)?;
+        buffer.write_i32::<BigEndian>(position.z)?;
+        buffer.write_u8(kind.id())?;
+        buffer.write_u32::<BigEndian>(time.as_secs() as u32)?;
+
+        // Single write call so records of concurrent writers are not interleaved.
+        self.file.write_all(&buffer)
+    }
+}
+
+/// Reads events of log starting from specified sequence number.
+///
+/// Missing log are treated as empty, partially written record at the end are ignored.
+pub fn read_events<P: AsRef<Path>>(path: P, since: u64) -> Result<Vec<ChunkEvent>, io::Error> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(io_error) => return Err(io_error),
+    };
+
+    let records = file.metadata()?.len() / EVENT_RECORD_BYTES_LENGTH;
+
+    if since >= records {
+        return Ok(Vec::new());
+    }
+
+    file.seek(SeekFrom::Start(since * EVENT_RECORD_BYTES_LENGTH))?;
+
+    let mut buffer = Vec::new();
+    file.take((records - since) * EVENT_RECORD_BYTES_LENGTH)
+        .read_to_end(&mut buffer)?;
+
+    let mut cursor = buffer.as_slice();
+    let mut events = Vec::with_capacity((records - since) as usize);
+
+    for sequence in since..records {
+        let x = cursor.read_i32::<BigEndian>()?;
+        let z = cursor.read_i32::<BigEndian>()?;
+        let kind_id = cursor.read_u8()?;
+        let timestamp = cursor.read_u32::<BigEndian>()?;
+
+        let kind = ChunkEventKind::from_id(kind_id).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Unknown chunk event kind")
+        })?;
+
+        events.push(ChunkEvent {
+            sequence,
+            position: ChunkPosition::new(x, z),
+            kind,
+            timestamp,
+        });
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{read_events, ChunkEventKind, EventLog};
+    use crate::fixtures::temp_world;
+    use crate::position::ChunkPosition;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    #[test]
+    fn test_record_read_events() {
+        let world = temp_world(&[]);
+        let path = world.path().join("events.log");
+
+        assert!(read_events(&path, 0).unwrap().is_empty());
+
+        let mut event_log = EventLog::open(&path).unwrap();
+        event_log
+            .record(ChunkPosition::new(-1, 40), ChunkEventKind::Write)
+            .unwrap();
+        event_log
+            .record(ChunkPosition::new(3, 3), ChunkEventKind::Remove)
+            .unwrap();
+
+        // Partially written record.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[0, 0, 0]).unwrap();
+
+        let events = read_events(&path, 0).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].position, ChunkPosition::new(-1, 40));
+        assert_eq!(events[0].kind, ChunkEventKind::Write);
+        assert!(events[0].timestamp > 0);
+
+        let events = read_events(&path, 1).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].sequence, 1);
+        assert_eq!(events[0].kind, ChunkEventKind::Remove);
+        assert!(read_events(&path, 5).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+//! Source adapter which injects failures to test error handling.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::faulty::FaultySource;
+//! use anvil_region::position::{RegionChunkPosition, RegionPosition};
+//! use anvil_region::region::Region;
+//! use nbt::CompoundTag;
+//! use std::io::{Cursor, ErrorKind};
+//!
+//! let source = FaultySource::new(Cursor::new(Vec::new()))
+//!     .fail_writes_after(8192, ErrorKind::Other);
+//!
+//! let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+//! let result = region.write_chunk(RegionChunkPosition::new(0, 0), CompoundTag::new());
+//!
+//! assert!(result.is_err());
+//! ```
+use crate::region::{SetLen, SyncAll};
+use std::io;
+use std::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// Wraps source and injects I/O errors, short reads and delayed writes.
+///
+/// Failures are configured with builder methods, by default source behaves as wrapped one.
+pub struct FaultySource<S> {
+    /// Wrapped source.
+    inner: S,
+    /// Current position in source.
+    position: u64,
+    /// Amount of bytes read so far.
+    read_bytes: u64,
+    /// Amount of bytes written so far.
+    written_bytes: u64,
+    /// Amount of bytes after which reads fail with specified error kind.
+    read_error: Option<(u64, io::ErrorKind)>,
+    /// Amount of bytes after which writes fail with specified error kind.
+    write_error: Option<(u64, io::ErrorKind)>,
+    /// Maximum amount of bytes returned by single read.
+    read_limit: Option<usize>,
+    /// Whether writes are kept in memory until flush.
+    delay_writes: bool,
+    /// Writes which are not applied to wrapped source yet with their positions.
+    pending_writes: Vec<(u64, Vec<u8>)>,
+}
+
+impl<S> FaultySource<S> {
+    pub fn new(inner: S) -> Self {
+        FaultySource {
+            inner,
+            position: 0,
+            read_bytes: 0,
+            written_bytes: 0,
+            read_error: None,
+            write_error: None,
+            read_limit: None,
+            delay_writes: false,
+            pending_writes: Vec::new(),
+        }
+    }
+
+    /// Fails reads with specified error kind after amount of bytes was read.
+    pub fn fail_reads_after(mut self, bytes: u64, kind: io::ErrorKind) -> Self {
+        self.read_error = Some((bytes, kind));
+        self
+    }
+
+    /// Fails writes with specified error kind after amount of bytes was written.
+    pub fn fail_writes_after(mut self, bytes: u64, kind: io::ErrorKind) -> Self {
+        self.write_error = Some((bytes, kind));
+        self
+    }
+
+    /// Limits amount of bytes returned by single read.
+    pub fn short_reads(mut self, max_len: usize) -> Self {
+        self.read_limit = Some(max_len.max(1));
+        self
+    }
+
+    /// Keeps writes in memory until flush.
+    ///
+    /// Reads don't see delayed writes. Writes are lost if source dropped without flush,
+    /// which simulates crash before data reach the disk.
+    pub fn delay_writes(mut self) -> Self {
+        self.delay_writes = true;
+        self
+    }
+
+    /// Returns wrapped source, writes which are not flushed are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Write + Seek> FaultySource<S> {
+    /// Applies delayed writes to wrapped source in the order they happened.
+    fn apply_pending_writes(&mut self) -> Result<(), io::Error> {
+        for (position, data) in self.pending_writes.drain(..) {
+            self.inner.seek(SeekFrom::Start(position))?;
+            self.inner.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Read + Seek> Read for FaultySource<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut read_len = buf.len();
+
+        if let Some((bytes, kind)) = self.read_error {
+            let remaining_len = bytes.saturating_sub(self.read_bytes);
+
+            if remaining_len == 0 && read_len > 0 {
+                return Err(io::Error::new(kind, "Injected read failure"));
+            }
+
+            read_len = read_len.min(remaining_len as usize);
+        }
+
+        if let Some(read_limit) = self.read_limit {
+            read_len = read_len.min(read_limit);
+        }
+
+        self.inner.seek(SeekFrom::Start(self.position))?;
+        let len = self.inner.read(&mut buf[..read_len])?;
+
+        self.position += len as u64;
+        self.read_bytes += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl<S: Write + Seek> Write for FaultySource<S> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut write_len = buf.len();
+
+        if let Some((bytes, kind)) = self.write_error {
+            let remaining_len = bytes.saturating_sub(self.written_bytes);
+
+            if remaining_len == 0 && write_len > 0 {
+                return Err(io::Error::new(kind, "Injected write failure"));
+            }
+
+            write_len = write_len.min(remaining_len as usize);
+        }
+
+        let len = if self.delay_writes {
+            let data = buf[..write_len].to_vec();
+            self.pending_writes.push((self.position, data));
+
+            write_len
+        } else {
+            self.inner.seek(SeekFrom::Start(self.position))?;
+            self.inner.write(&buf[..write_len])?
+        };
+
+        self.position += len as u64;
+        self.written_bytes += len as u64;
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.apply_pending_writes()?;
+        self.inner.flush()
+    }
+}
+
+impl<S: Seek> Seek for FaultySource<S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => {
+                let inner_len = self.inner.seek(SeekFrom::End(0))?;
+
+                // Delayed writes can extend source.
+                let len = self
+                    .pending_writes
+                    .iter()
+                    .map(|(position, data)| position + data.len() as u64)
+                    .fold(inner_len, u64::max);
+
+                len.checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        let position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek to a negative or overflowing position",
+            )
+        })?;
+
+        self.position = position;
+
+        Ok(position)
+    }
+}
+
+impl<S: Write + Seek + SetLen> SetLen for FaultySource<S> {
+    fn set_len(&mut self, len: u64) -> Result<(), Error> {
+        self.apply_pending_writes()?;
+        self.inner.set_len(len)
+    }
+}
+
+impl<S: Write + Seek + SyncAll> SyncAll for FaultySource<S> {
+    fn sync_all(&mut self) -> Result<(), Error> {
+        self.apply_pending_writes()?;
+        self.inner.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::faulty::FaultySource;
+    use crate::fixtures::temp_world;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::region::Region;
+    use nbt::CompoundTag;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_short_reads() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let source = FaultySource::new(file).short_reads(3);
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        let compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+        let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+    }
+
+    #[test]
+    fn test_fail_reads_after() {
+        let mut source = FaultySource::new(Cursor::new(vec![1; 16]))
+            .fail_reads_after(10, ErrorKind::UnexpectedEof)
+            .short_reads(4);
+
+        let mut buffer = [0; 16];
+        assert_eq!(source.read(&mut buffer).unwrap(), 4);
+        assert_eq!(source.read(&mut buffer).unwrap(), 4);
+        assert_eq!(source.read(&mut buffer).unwrap(), 2);
+
+        let error = source.read(&mut buffer).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        // Region header can't be read.
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let source = FaultySource::new(file).fail_reads_after(100, ErrorKind::Other);
+
+        assert!(Region::load(RegionPosition::new(0, 0), source).is_err());
+    }
+
+    #[test]
+    fn test_delay_writes() {
+        let mut source = FaultySource::new(Cursor::new(vec![0; 4])).delay_writes();
+
+        source.seek(SeekFrom::Start(2)).unwrap();
+        source.write_all(&[1, 2, 3, 4]).unwrap();
+
+        assert_eq!(source.seek(SeekFrom::End(0)).unwrap(), 6);
+
+        let mut buffer = Vec::new();
+        source.seek(SeekFrom::Start(0)).unwrap();
+        source.read_to_end(&mut buffer).unwrap();
+        assert_eq!(buffer, vec![0; 4]);
+
+        source.flush().unwrap();
+        assert_eq!(source.into_inner().into_inner(), vec![0, 0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_delay_writes_lost_without_flush() {
+        let world = temp_world(&[]);
+        let path = world.path().join("delay-writes.mca");
+        let position = RegionChunkPosition::new(1, 1);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let source = FaultySource::new(file).delay_writes();
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        assert!(region.contains_chunk(position));
+
+        // Process crashed before flush, so region are not dropped.
+        std::mem::forget(region);
+
+        let file = File::open(&path).unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(!region.contains_chunk(position));
+    }
+
+    #[test]
+    fn test_safe_writes_keep_chunk_on_failure() {
+        let world = temp_world(&[]);
+        let path = world.path().join("safe-writes.mca");
+        let position = RegionChunkPosition::new(1, 1);
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let mut compound_tag = CompoundTag::new();
+        compound_tag.insert_i32_vec("data", vec![1; 100]);
+
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        region.write_chunk(position, compound_tag).unwrap();
+        region.close().unwrap();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let source = FaultySource::new(file).fail_writes_after(100, ErrorKind::Other);
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+        region.set_safe_writes(true);
+
+        let mut compound_tag = CompoundTag::new();
+        compound_tag.insert_i32_vec("data", vec![2; 100]);
+
+        assert!(region.write_chunk(position, compound_tag).is_err());
+
+        // Process crashed during write.
+        std::mem::forget(region);
+
+        let file = File::open(&path).unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let compound_tag = region.read_chunk(position).unwrap();
+
+        assert_eq!(compound_tag.get_i32_vec("data").unwrap(), &vec![1; 100]);
+    }
+
+    #[test]
+    fn test_write_chunks_failure_keeps_metadata() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(0, 0), CompoundTag::new())
+            .unwrap();
+
+        // Free sectors at the end so chunks are placed without extending source.
+        let mut data = region.into_inner().unwrap().into_inner();
+        data.extend_from_slice(&[0; 8192]);
+
+        let source = FaultySource::new(Cursor::new(data)).fail_writes_after(0, ErrorKind::Other);
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        let chunks = vec![
+            (RegionChunkPosition::new(1, 0), CompoundTag::new()),
+            (RegionChunkPosition::new(2, 0), CompoundTag::new()),
+        ];
+
+        assert!(region.write_chunks(chunks).is_err());
+        assert!(region.contains_chunk(RegionChunkPosition::new(0, 0)));
+        assert!(!region.contains_chunk(RegionChunkPosition::new(1, 0)));
+        assert!(!region.contains_chunk(RegionChunkPosition::new(2, 0)));
+    }
+}
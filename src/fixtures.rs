@@ -0,0 +1,168 @@
+//! Helpers to generate small worlds for integration tests.
+//!
+//! Downstream crates can build fixtures on the fly instead of committing binary region files.
+use crate::error::ChunkWriteError;
+use crate::position::{RegionChunkPosition, RegionPosition};
+use crate::provider::region_position_filename;
+use crate::region::Region;
+#[cfg(test)]
+use crate::temp::TempWorldProvider;
+use nbt::encode::write_gzip_compound_tag;
+use nbt::CompoundTag;
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// Writes a tiny world to `dir` with a `region` folder containing chunks at specified coordinates.
+///
+/// Every chunk contains only `Level` compound tag with `xPos` and `zPos`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::fixtures::minimal_world;
+/// use anvil_region::provider::{FolderRegionProvider, RegionProvider};
+/// use anvil_region::position::{RegionPosition, RegionChunkPosition};
+///
+/// let dir = std::env::temp_dir().join("anvil-region-minimal-world-doc");
+/// minimal_world(&dir, &[(0, 0), (-1, 40)]).unwrap();
+///
+/// let region_folder = dir.join("region");
+/// let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+/// let mut region = provider.get_region(RegionPosition::from_chunk_position(-1, 40)).unwrap();
+///
+/// let chunk_compound_tag = region
+///     .read_chunk(RegionChunkPosition::from_chunk_position(-1, 40))
+///     .unwrap();
+/// let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+///
+/// assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), -1);
+/// assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 40);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub fn minimal_world<P: AsRef<Path>>(dir: P, chunks: &[(i32, i32)]) -> Result<(), ChunkWriteError> {
+    let region_folder = dir.as_ref().join("region");
+    fs::create_dir_all(&region_folder)?;
+
+    let mut regions_chunks: BTreeMap<RegionPosition, Vec<(i32, i32)>> = BTreeMap::new();
+
+    for &(chunk_x, chunk_z) in chunks {
+        let region_position = RegionPosition::from_chunk_position(chunk_x, chunk_z);
+
+        regions_chunks
+            .entry(region_position)
+            .or_default()
+            .push((chunk_x, chunk_z));
+    }
+
+    for (region_position, region_chunks) in regions_chunks {
+        let region_path = region_folder.join(region_position_filename(region_position));
+
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(false)
+            .open(region_path)?;
+
+        let mut region = Region::load(region_position, file)?;
+
+        for (chunk_x, chunk_z) in region_chunks {
+            let region_chunk_position = RegionChunkPosition::from_chunk_position(chunk_x, chunk_z);
+            region.write_chunk(region_chunk_position, minimal_chunk(chunk_x, chunk_z))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a gzip compressed `level.dat` with the specified level name to `dir`.
+pub fn minimal_level_dat<P: AsRef<Path>>(dir: P, level_name: &str) -> Result<(), io::Error> {
+    let mut data_compound_tag = CompoundTag::new();
+    data_compound_tag.insert_str("LevelName", level_name);
+    data_compound_tag.insert_i32("version", 19133);
+    data_compound_tag.insert_bool("initialized", true);
+
+    let mut level_compound_tag = CompoundTag::new();
+    level_compound_tag.insert_compound_tag("Data", data_compound_tag);
+
+    let mut file = File::create(dir.as_ref().join("level.dat"))?;
+
+    write_gzip_compound_tag(&mut file, &level_compound_tag)
+}
+
+/// Returns chunk compound tag which contains only chunk coordinates.
+pub fn minimal_chunk(chunk_x: i32, chunk_z: i32) -> CompoundTag {
+    let mut chunk_compound_tag = CompoundTag::new();
+    let mut level_compound_tag = CompoundTag::new();
+
+    level_compound_tag.insert_i32("xPos", chunk_x);
+    level_compound_tag.insert_i32("zPos", chunk_z);
+
+    chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+    chunk_compound_tag
+}
+
+/// Creates temporary world with chunks at specified coordinates, world are removed on drop.
+#[cfg(test)]
+pub(crate) fn temp_world(chunks: &[(i32, i32)]) -> TempWorldProvider {
+    let world = TempWorldProvider::new().unwrap();
+    minimal_world(world.path(), chunks).unwrap();
+
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{minimal_level_dat, minimal_world, temp_world};
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+    use nbt::decode::read_gzip_compound_tag;
+    use std::fs::File;
+
+    #[test]
+    fn test_minimal_world() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("minimal-world");
+        let chunks = [(0, 0), (31, 31), (32, 0), (-1, -1)];
+
+        minimal_world(&dir, &chunks).unwrap();
+        minimal_level_dat(&dir, "fixture").unwrap();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut positions: Vec<_> = provider.iter_positions().unwrap().collect();
+        positions.sort();
+
+        assert_eq!(
+            positions,
+            vec![
+                RegionPosition::new(-1, -1),
+                RegionPosition::new(0, 0),
+                RegionPosition::new(1, 0),
+            ]
+        );
+
+        for &(chunk_x, chunk_z) in &chunks {
+            let region_position = RegionPosition::from_chunk_position(chunk_x, chunk_z);
+            let mut region = provider.get_region(region_position).unwrap();
+
+            let chunk_compound_tag = region
+                .read_chunk(RegionChunkPosition::from_chunk_position(chunk_x, chunk_z))
+                .unwrap();
+            let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), chunk_x);
+            assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), chunk_z);
+        }
+
+        let mut level_file = File::open(dir.join("level.dat")).unwrap();
+        let level_compound_tag = read_gzip_compound_tag(&mut level_file).unwrap();
+        let data_compound_tag = level_compound_tag.get_compound_tag("Data").unwrap();
+
+        assert_eq!(data_compound_tag.get_str("LevelName").unwrap(), "fixture");
+    }
+}
@@ -0,0 +1,170 @@
+//! Detection of region file format and crate extensions used by it.
+use crate::region::find_backup_header;
+use byteorder::{BigEndian, ReadBytesExt};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Magic which starts linear region file.
+const LINEAR_MAGIC: u64 = 0xC3FF_1318_3CCA_9D9A;
+
+/// Layout of region file data.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum RegionLayout {
+    /// Anvil region `.mca` which chunks contains sections.
+    Anvil,
+    /// McRegion `.mcr` which was used before Anvil.
+    ///
+    /// Sectors layout are the same, but chunks contains `Blocks` array instead of sections.
+    McRegion,
+    /// Linear region `.linear` where all chunks are stored in a single compressed stream.
+    Linear {
+        /// Linear format version.
+        version: u8,
+    },
+}
+
+/// Descriptor of region file format.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct RegionFormat {
+    /// Layout of region data.
+    layout: RegionLayout,
+    /// Whether backup header present at the end of source.
+    backup_header: bool,
+    /// Whether chunks metadata sidecar file present next to region file.
+    sidecar: bool,
+}
+
+impl RegionFormat {
+    /// Detects format from source header bytes, chunks are not decoded.
+    ///
+    /// McRegion sectors layout are the same as Anvil one, so it can't be detected
+    /// from source and are treated as Anvil. Sidecar presence can't be detected from source.
+    pub fn detect<S: Read + Seek>(source: &mut S) -> Result<Self, io::Error> {
+        let source_len = source.seek(SeekFrom::End(0))?;
+        source.seek(SeekFrom::Start(0))?;
+
+        // 8 bytes of magic and 1 byte of version.
+        if source_len >= 9 && source.read_u64::<BigEndian>()? == LINEAR_MAGIC {
+            let version = source.read_u8()?;
+
+            return Ok(RegionFormat {
+                layout: RegionLayout::Linear { version },
+                backup_header: false,
+                sidecar: false,
+            });
+        }
+
+        let backup_header = find_backup_header(source, source_len)?.is_some();
+
+        Ok(RegionFormat {
+            layout: RegionLayout::Anvil,
+            backup_header,
+            sidecar: false,
+        })
+    }
+
+    /// Detects format of region file.
+    ///
+    /// McRegion are recognized by `.mcr` extension, sidecar are searched next to file.
+    pub fn detect_file<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+
+        let mut format = RegionFormat::detect(&mut file)?;
+
+        if format.layout == RegionLayout::Anvil
+            && path.extension().is_some_and(|extension| extension == "mcr")
+        {
+            format.layout = RegionLayout::McRegion;
+        }
+
+        format.sidecar = path.with_extension("meta").is_file();
+
+        Ok(format)
+    }
+
+    /// Returns layout of region data.
+    pub fn layout(&self) -> RegionLayout {
+        self.layout
+    }
+
+    /// Returns `true` if backup header present at the end of source.
+    pub fn has_backup_header(&self) -> bool {
+        self.backup_header
+    }
+
+    /// Returns `true` if chunks metadata sidecar file present next to region file.
+    pub fn has_sidecar(&self) -> bool {
+        self.sidecar
+    }
+
+    /// Returns `true` if region can be read with `Region`.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self.layout, RegionLayout::Linear { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::format::{RegionFormat, RegionLayout, LINEAR_MAGIC};
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::region::Region;
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_detect_anvil() {
+        let format = RegionFormat::detect_file("test/region/r.0.0.mca").unwrap();
+
+        assert_eq!(format.layout(), RegionLayout::Anvil);
+        assert!(!format.has_backup_header());
+        assert!(!format.has_sidecar());
+        assert!(format.is_supported());
+    }
+
+    #[test]
+    fn test_detect_mcregion() {
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(Vec::new())).unwrap();
+        region.set_backup_header(true);
+
+        let mut level_compound_tag = CompoundTag::new();
+        level_compound_tag.insert_i8_vec("Blocks", vec![0; 16]);
+
+        let mut chunk_compound_tag = CompoundTag::new();
+        chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+
+        region
+            .write_chunk(RegionChunkPosition::new(3, 3), chunk_compound_tag)
+            .unwrap();
+        region.flush().unwrap();
+
+        let mut data = Vec::new();
+        region.copy_raw_to(&mut data).unwrap();
+
+        let world = temp_world(&[]);
+        let dir = world.path();
+        fs::write(dir.join("r.0.0.mcr"), data).unwrap();
+        fs::write(dir.join("r.0.0.meta"), []).unwrap();
+
+        let format = RegionFormat::detect_file(dir.join("r.0.0.mcr")).unwrap();
+
+        assert_eq!(format.layout(), RegionLayout::McRegion);
+        assert!(format.has_backup_header());
+        assert!(format.has_sidecar());
+    }
+
+    #[test]
+    fn test_detect_linear() {
+        let mut data = LINEAR_MAGIC.to_be_bytes().to_vec();
+        data.extend_from_slice(&[2, 0, 0, 0]);
+
+        let format = RegionFormat::detect(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(format.layout(), RegionLayout::Linear { version: 2 });
+        assert!(!format.is_supported());
+    }
+}
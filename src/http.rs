@@ -0,0 +1,320 @@
+//! Provider of remote regions which are read with HTTP range requests.
+//!
+//! Only sectors which are needed to read header and requested chunks are downloaded,
+//! so single chunks can be read without downloading the whole region file.
+use crate::position::RegionPosition;
+use crate::provider::{region_position_filename, RegionProvider};
+use crate::region::{Region, REGION_SECTOR_BYTES_LENGTH};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use ureq::Agent;
+
+/// Amount of sectors which are cached by every source by default.
+const DEFAULT_CACHE_SECTORS: usize = 256;
+
+/// Read only provider of regions located at base URL.
+///
+/// Region which doesn't exist on server are returned as empty region.
+pub struct HttpRegionProvider {
+    /// HTTP client which connections are reused between requests.
+    agent: Agent,
+    /// URL of folder with region files.
+    base_url: String,
+    /// Maximum amount of sectors cached by every region source.
+    cache_sectors: usize,
+}
+
+impl HttpRegionProvider {
+    pub fn new(base_url: &str) -> Self {
+        HttpRegionProvider {
+            agent: Agent::new(),
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            cache_sectors: DEFAULT_CACHE_SECTORS,
+        }
+    }
+
+    /// Sets maximum amount of fetched sectors which are cached by every region source.
+    pub fn cache_sectors(mut self, sectors: usize) -> Self {
+        self.cache_sectors = sectors;
+        self
+    }
+}
+
+impl RegionProvider<HttpSource> for HttpRegionProvider {
+    fn get_region(&self, position: RegionPosition) -> Result<Region<HttpSource>, io::Error> {
+        let url = format!("{}/{}", self.base_url, region_position_filename(position));
+        let source = HttpSource::open(self.agent.clone(), url, self.cache_sectors)?;
+
+        Region::load(position, source)
+    }
+
+    /// Requests region file headers, request errors are treated as missing region.
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        let url = format!("{}/{}", self.base_url, region_position_filename(position));
+
+        self.agent.head(&url).call().is_ok()
+    }
+}
+
+/// Readable and seekable remote file which data are fetched by sectors with range requests.
+///
+/// Fetched sectors are cached, when cache are full the earliest fetched sectors are evicted.
+pub struct HttpSource {
+    agent: Agent,
+    url: String,
+    /// Length of remote file in bytes.
+    len: u64,
+    /// Current position in file.
+    position: u64,
+    /// Data of fetched sectors by sector index.
+    sectors: BTreeMap<u64, Vec<u8>>,
+    /// Indexes of cached sectors in the order they was fetched.
+    fetch_order: VecDeque<u64>,
+    /// Maximum amount of cached sectors.
+    cache_sectors: usize,
+    /// Amount of range requests made.
+    requests: u64,
+}
+
+impl HttpSource {
+    /// Requests length of remote file, missing file are treated as empty.
+    pub fn open(agent: Agent, url: String, cache_sectors: usize) -> Result<Self, io::Error> {
+        let len = match agent.head(&url).call() {
+            Ok(response) => response
+                .header("Content-Length")
+                .and_then(|len| len.parse().ok())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "Missing content length")
+                })?,
+            Err(ureq::Error::Status(404, _)) => 0,
+            Err(error) => return Err(io::Error::other(error)),
+        };
+
+        Ok(HttpSource {
+            agent,
+            url,
+            len,
+            position: 0,
+            sectors: BTreeMap::new(),
+            fetch_order: VecDeque::new(),
+            cache_sectors: cache_sectors.max(1),
+            requests: 0,
+        })
+    }
+
+    /// Returns amount of range requests made.
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// Fetches sectors in range with a single request and caches them.
+    fn fetch_sectors(&mut self, start_sector: u64, end_sector: u64) -> Result<(), io::Error> {
+        let sector_len = REGION_SECTOR_BYTES_LENGTH as u64;
+        let start = start_sector * sector_len;
+        let end = (end_sector * sector_len).min(self.len);
+
+        let response = self
+            .agent
+            .get(&self.url)
+            .set("Range", &format!("bytes={}-{}", start, end - 1))
+            .call()
+            .map_err(io::Error::other)?;
+
+        if response.status() != 206 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Server doesn't support range requests",
+            ));
+        }
+
+        let mut data = Vec::with_capacity((end - start) as usize);
+        response.into_reader().read_to_end(&mut data)?;
+        self.requests += 1;
+
+        if data.len() as u64 != end - start {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Range response length doesn't match requested",
+            ));
+        }
+
+        for (index, sector_data) in data.chunks(sector_len as usize).enumerate() {
+            let sector_index = start_sector + index as u64;
+
+            if self.fetch_order.len() >= self.cache_sectors {
+                if let Some(evicted_sector_index) = self.fetch_order.pop_front() {
+                    self.sectors.remove(&evicted_sector_index);
+                }
+            }
+
+            self.sectors.insert(sector_index, sector_data.to_vec());
+            self.fetch_order.push_back(sector_index);
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for HttpSource {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let sector_len = REGION_SECTOR_BYTES_LENGTH as u64;
+        let read_end = (self.position + buf.len() as u64).min(self.len);
+
+        let start_sector = self.position / sector_len;
+
+        // Sectors up to the next cached one are fetched with a single request,
+        // but not more than fits in cache, so read can return less.
+        if !self.sectors.contains_key(&start_sector) {
+            let max_end_sector = read_end
+                .div_ceil(sector_len)
+                .min(start_sector + self.cache_sectors as u64);
+
+            let end_sector = (start_sector + 1..max_end_sector)
+                .find(|sector_index| self.sectors.contains_key(sector_index))
+                .unwrap_or(max_end_sector);
+
+            self.fetch_sectors(start_sector, end_sector)?;
+        }
+
+        let mut read_len = 0;
+
+        while self.position < read_end {
+            let sector_index = self.position / sector_len;
+            let sector_data = match self.sectors.get(&sector_index) {
+                Some(sector_data) => sector_data,
+                None => break,
+            };
+
+            let offset = (self.position % sector_len) as usize;
+            let len = (sector_data.len() - offset).min(buf.len() - read_len);
+
+            buf[read_len..read_len + len].copy_from_slice(&sector_data[offset..offset + len]);
+            read_len += len;
+            self.position += len as u64;
+        }
+
+        Ok(read_len)
+    }
+}
+
+impl Seek for HttpSource {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        let position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek to a negative or overflowing position",
+            )
+        })?;
+
+        self.position = position;
+
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::http::HttpRegionProvider;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::RegionProvider;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves region file with range requests support, other files are not found.
+    fn serve_region(data: Vec<u8>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut range = None;
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line.trim().is_empty() {
+                        break;
+                    }
+
+                    if let Some(value) = line.strip_prefix("Range: bytes=") {
+                        let (start, end) = value.trim().split_once('-').unwrap();
+                        range = Some((start.parse().unwrap(), end.parse::<usize>().unwrap()));
+                    }
+                }
+
+                if !request_line.contains("/r.0.0.mca") {
+                    let response = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\
+                        Connection: close\r\n\r\n";
+                    stream.write_all(response.as_bytes()).unwrap();
+                    continue;
+                }
+
+                let (status, body) = match range {
+                    Some((start, end)) => ("206 Partial Content", &data[start..=end]),
+                    None => ("200 OK", &data[..]),
+                };
+
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+
+                if !request_line.starts_with("HEAD") {
+                    stream.write_all(body).unwrap();
+                }
+            }
+        });
+
+        format!("http://{}/world/region/", address)
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        let data = fs::read("test/region/r.0.0.mca").unwrap();
+        let base_url = serve_region(data);
+
+        let provider = HttpRegionProvider::new(&base_url).cache_sectors(8);
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+
+        let mut requests = Vec::new();
+
+        for _ in 0..2 {
+            let compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+            let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+            requests.push(region.get_ref().requests());
+        }
+
+        // Chunk sectors are cached after the first read.
+        assert_eq!(requests[0], requests[1]);
+
+        let region = provider.get_region(RegionPosition::new(1, 0)).unwrap();
+        assert!(region.is_empty());
+
+        assert!(provider.region_exists(RegionPosition::new(0, 0)));
+        assert!(!provider.region_exists(RegionPosition::new(1, 0)));
+    }
+}
@@ -27,30 +27,72 @@
 //! ## Write
 //!
 //! ```
+//! use anvil_region::chunk::ChunkTagBuilder;
 //! use anvil_region::provider::{FolderRegionProvider, RegionProvider};
-//! use nbt::CompoundTag;
-//! use anvil_region::position::{RegionPosition, RegionChunkPosition};
+//! use anvil_region::position::{ChunkPosition, RegionPosition, RegionChunkPosition};
 //!
-//! let provider = FolderRegionProvider::new("test/region");
+//! # let folder = std::env::temp_dir().join("anvil-region-doc-write");
+//! # std::fs::create_dir_all(&folder).unwrap();
+//! let provider = FolderRegionProvider::new(folder.to_str().unwrap());
 //!
 //! let region_position = RegionPosition::from_chunk_position(31, 16);
 //! let region_chunk_position = RegionChunkPosition::from_chunk_position(31, 16);
 //!
 //! let mut region = provider.get_region(region_position).unwrap();
 //!
-//! let mut chunk_compound_tag = CompoundTag::new();
-//! let mut level_compound_tag = CompoundTag::new();
-//!
-//! // To simplify example we add only coordinates.
-//! // Full list of required tags https://minecraft.gamepedia.com/Chunk_format.
-//! level_compound_tag.insert_i32("xPos", 31);
-//! level_compound_tag.insert_i32("zPos", 16);
-//!
-//! chunk_compound_tag.insert_compound_tag("Level", level_compound_tag);
+//! // Builder fills tags required by the game for specified data version.
+//! // Full list of tags https://minecraft.wiki/w/Chunk_format.
+//! let chunk_compound_tag = ChunkTagBuilder::new(3465, ChunkPosition::new(31, 16))
+//!     .insert("InhabitedTime", 1200i64)
+//!     .build();
 //!
-//! region.write_chunk(region_chunk_position, chunk_compound_tag);
+//! region.write_chunk(region_chunk_position, chunk_compound_tag).unwrap();
+//! # std::fs::remove_dir_all(&folder).unwrap();
 //! ```
+pub mod backup;
+pub mod builder;
+pub mod bundle;
+pub mod cache;
+pub mod chain;
+pub mod chunk;
+pub mod compression;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod error;
+pub mod events;
+#[cfg(feature = "test-utils")]
+pub mod faulty;
+pub mod fixtures;
+pub mod format;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod lock;
+#[cfg(feature = "lz4")]
+mod lz4;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod position;
 pub mod provider;
 pub mod region;
+mod scratch;
+pub mod shared;
+pub mod sidecar;
+pub mod source;
+pub mod stats;
+pub mod storage;
+mod tar_gz;
+pub mod temp;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "allocation-trace")]
+pub mod trace;
+pub mod validation;
+#[cfg(feature = "notify")]
+pub mod watch;
+pub mod world;
+#[cfg(feature = "zip")]
+pub mod zip_provider;
+#[cfg(feature = "zstd")]
+pub mod zstd;
@@ -0,0 +1,139 @@
+//! Cooperative locking of world with `session.lock` file.
+//!
+//! Process which opens world writes current time in milliseconds to `session.lock`.
+//! When another process opens world it overwrites timestamp, so previous owner
+//! can detect that lock was taken over and stop writing to world.
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of session lock file in world folder.
+const SESSION_LOCK_FILENAME: &str = "session.lock";
+
+/// Session lock of world acquired by this process.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::lock::WorldLock;
+///
+/// let dir = std::env::temp_dir().join("anvil-region-world-lock-doc");
+/// std::fs::create_dir_all(&dir).unwrap();
+///
+/// let lock = WorldLock::acquire(&dir).unwrap();
+/// assert!(lock.check().is_ok());
+///
+/// // Another process opens world.
+/// let other_lock = WorldLock::acquire(&dir).unwrap();
+///
+/// assert!(lock.check().is_err());
+/// assert!(other_lock.check().is_ok());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorldLock {
+    /// Path to session lock file.
+    path: PathBuf,
+    /// Timestamp in milliseconds which was written when lock acquired.
+    timestamp: i64,
+}
+
+impl WorldLock {
+    /// Acquires lock of world by writing current timestamp to `session.lock`.
+    ///
+    /// Timestamp are guaranteed to differ from the one already written,
+    /// so owner of previous lock detects takeover.
+    pub fn acquire<P: AsRef<Path>>(world_folder: P) -> Result<WorldLock, io::Error> {
+        let path = world_folder.as_ref().join(SESSION_LOCK_FILENAME);
+        let mut timestamp = current_timestamp_millis();
+
+        if let Some(previous_timestamp) = read_timestamp(&path)? {
+            if previous_timestamp >= timestamp {
+                timestamp = previous_timestamp + 1;
+            }
+        }
+
+        let mut file = File::create(&path)?;
+        file.write_i64::<BigEndian>(timestamp)?;
+        file.sync_all()?;
+
+        Ok(WorldLock { path, timestamp })
+    }
+
+    /// Returns timestamp in milliseconds which was written when lock acquired.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Returns `true` if lock are still held by this process.
+    pub fn is_held(&self) -> Result<bool, io::Error> {
+        Ok(read_timestamp(&self.path)? == Some(self.timestamp))
+    }
+
+    /// Checks that lock are still held by this process.
+    ///
+    /// When lock was taken over by another process `PermissionDenied` error are returned.
+    pub fn check(&self) -> Result<(), io::Error> {
+        if !self.is_held()? {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Session lock of world was taken over by another process",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads timestamp from session lock, missing file and content which are not
+/// a timestamp are treated as no timestamp.
+fn read_timestamp(path: &Path) -> Result<Option<i64>, io::Error> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(io_error) => return Err(io_error),
+    };
+
+    match file.read_i64::<BigEndian>() {
+        Ok(timestamp) => Ok(Some(timestamp)),
+        Err(io_error) if io_error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(io_error) => Err(io_error),
+    }
+}
+
+fn current_timestamp_millis() -> i64 {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    time.as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::lock::WorldLock;
+    use std::fs;
+    use std::io;
+
+    #[test]
+    fn test_takeover() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let lock = WorldLock::acquire(dir).unwrap();
+        assert!(lock.is_held().unwrap());
+
+        let other_lock = WorldLock::acquire(dir).unwrap();
+        assert!(other_lock.timestamp() > lock.timestamp());
+        assert!(!lock.is_held().unwrap());
+
+        let io_error = lock.check().unwrap_err();
+        assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+
+        // Lock file of newer game versions contains snowman instead of timestamp.
+        fs::write(dir.join("session.lock"), "\u{2603}").unwrap();
+        assert!(!other_lock.is_held().unwrap());
+        assert!(WorldLock::acquire(dir).unwrap().is_held().unwrap());
+    }
+}
@@ -0,0 +1,172 @@
+//! LZ4 block stream format used by the game to compress chunks.
+//!
+//! Data are split into blocks, every block starts with `LZ4Block` magic, token with
+//! compression method, compressed and original length, checksum of original data and
+//! ends with empty block.
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io;
+use std::io::{Cursor, Read};
+use twox_hash::XxHash32;
+
+/// Magic which starts every block.
+const LZ4_BLOCK_MAGIC: &[u8] = b"LZ4Block";
+/// Block data stored as is.
+const COMPRESSION_METHOD_RAW: u8 = 0x10;
+/// Block data compressed with LZ4.
+const COMPRESSION_METHOD_LZ4: u8 = 0x20;
+/// Maximum length of original data in block.
+const BLOCK_MAXIMUM_LENGTH: usize = 64 * 1024;
+/// Maximum length of original data in block which are accepted on decompression,
+/// the largest block size which can be stored in token.
+const DECOMPRESSED_BLOCK_MAXIMUM_LENGTH: usize = 32 * 1024 * 1024;
+/// Compression level stored in token for maximum block length.
+const BLOCK_COMPRESSION_LEVEL: u8 = 6;
+/// Seed of block data checksum.
+const CHECKSUM_SEED: u32 = 0x9747_B28C;
+
+/// Compresses data to LZ4 block stream.
+pub(crate) fn compress(data: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let mut buffer = Vec::new();
+
+    for block in data.chunks(BLOCK_MAXIMUM_LENGTH) {
+        let compressed_block = lz4_flex::block::compress(block);
+
+        let (compression_method, block_data) = if compressed_block.len() < block.len() {
+            (COMPRESSION_METHOD_LZ4, compressed_block.as_slice())
+        } else {
+            (COMPRESSION_METHOD_RAW, block)
+        };
+
+        write_block_header(
+            &mut buffer,
+            compression_method,
+            block_data.len() as u32,
+            block.len() as u32,
+            checksum(block),
+        )?;
+
+        buffer.extend_from_slice(block_data);
+    }
+
+    write_block_header(&mut buffer, COMPRESSION_METHOD_RAW, 0, 0, 0)?;
+
+    Ok(buffer)
+}
+
+/// Decompresses LZ4 block stream.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let mut cursor = Cursor::new(data);
+    let mut buffer = Vec::new();
+
+    // Stream can end without empty block.
+    while (cursor.position() as usize) < data.len() {
+        let mut magic = [0; LZ4_BLOCK_MAGIC.len()];
+        cursor.read_exact(&mut magic)?;
+
+        if magic != LZ4_BLOCK_MAGIC {
+            return Err(invalid_data("Invalid LZ4 block magic"));
+        }
+
+        let compression_method = cursor.read_u8()? & 0xF0;
+        let compressed_length = cursor.read_u32::<LittleEndian>()? as usize;
+        let original_length = cursor.read_u32::<LittleEndian>()? as usize;
+        let block_checksum = cursor.read_u32::<LittleEndian>()?;
+
+        if original_length == 0 {
+            break;
+        }
+
+        // Lengths are checked before allocation, so corrupted header can't exhaust memory.
+        if compressed_length > data.len() - cursor.position() as usize {
+            return Err(invalid_data("LZ4 block exceeds stream length"));
+        }
+
+        if original_length > DECOMPRESSED_BLOCK_MAXIMUM_LENGTH {
+            return Err(invalid_data("LZ4 block original length exceeds maximum"));
+        }
+
+        let mut block_data = vec![0; compressed_length];
+        cursor.read_exact(&mut block_data)?;
+
+        let block = match compression_method {
+            COMPRESSION_METHOD_RAW if compressed_length == original_length => block_data,
+            COMPRESSION_METHOD_LZ4 => lz4_flex::block::decompress(&block_data, original_length)
+                .map_err(|error| invalid_data(&error.to_string()))?,
+            _ => return Err(invalid_data("Unsupported LZ4 block compression method")),
+        };
+
+        if block.len() != original_length || checksum(&block) != block_checksum {
+            return Err(invalid_data("LZ4 block checksum mismatch"));
+        }
+
+        buffer.extend_from_slice(&block);
+    }
+
+    Ok(buffer)
+}
+
+fn write_block_header(
+    buffer: &mut Vec<u8>,
+    compression_method: u8,
+    compressed_length: u32,
+    original_length: u32,
+    checksum: u32,
+) -> Result<(), io::Error> {
+    buffer.extend_from_slice(LZ4_BLOCK_MAGIC);
+    buffer.write_u8(compression_method | BLOCK_COMPRESSION_LEVEL)?;
+    buffer.write_u32::<LittleEndian>(compressed_length)?;
+    buffer.write_u32::<LittleEndian>(original_length)?;
+    buffer.write_u32::<LittleEndian>(checksum)
+}
+
+/// Only lower 28 bits of hash are used as checksum.
+fn checksum(data: &[u8]) -> u32 {
+    XxHash32::oneshot(CHECKSUM_SEED, data) & 0x0FFF_FFFF
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lz4::{
+        compress, decompress, write_block_header, COMPRESSION_METHOD_LZ4, LZ4_BLOCK_MAGIC,
+    };
+
+    #[test]
+    fn test_compress_decompress() {
+        let data: Vec<u8> = (0..200_000).map(|index| (index % 251) as u8).collect();
+        let compressed_data = compress(&data).unwrap();
+
+        assert!(compressed_data.starts_with(LZ4_BLOCK_MAGIC));
+        assert!(compressed_data.len() < data.len());
+        assert_eq!(decompress(&compressed_data).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decompress_checksum_mismatch() {
+        let mut compressed_data = compress(&[1, 2, 3, 4]).unwrap();
+
+        // Changing last byte of raw block data.
+        let index = compressed_data.len() - LZ4_BLOCK_MAGIC.len() - 13 - 1;
+        compressed_data[index] ^= 0xFF;
+
+        assert!(decompress(&compressed_data).is_err());
+    }
+
+    #[test]
+    fn test_decompress_invalid_lengths() {
+        let mut compressed_data = Vec::new();
+        write_block_header(&mut compressed_data, COMPRESSION_METHOD_LZ4, u32::MAX, 4, 0).unwrap();
+        compressed_data.extend_from_slice(&[0; 4]);
+
+        assert!(decompress(&compressed_data).is_err());
+
+        let mut compressed_data = Vec::new();
+        write_block_header(&mut compressed_data, COMPRESSION_METHOD_LZ4, 4, u32::MAX, 0).unwrap();
+        compressed_data.extend_from_slice(&[0; 4]);
+
+        assert!(decompress(&compressed_data).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! Read only region source backed by memory mapped file.
+//!
+//! Chunks are read directly from mapped memory without seek and read system calls,
+//! which speeds up read heavy workloads such as rendering or analysis.
+use crate::position::RegionPosition;
+use crate::region::Region;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Memory mapped region file.
+pub type MmapSource = Cursor<Mmap>;
+
+impl Region<MmapSource> {
+    /// Loads region from memory mapped file.
+    ///
+    /// File must not be modified while region are used, otherwise read data are undefined.
+    pub fn load_mmap<P: AsRef<Path>>(position: RegionPosition, path: P) -> Result<Self, io::Error> {
+        let file = File::open(path)?;
+
+        // Safety: mapping are read only and file are expected to not be modified
+        // by other processes while region are used.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        Region::load(position, Cursor::new(mmap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::region::Region;
+    use std::fs;
+
+    #[test]
+    fn test_load_mmap() {
+        let mut region =
+            Region::load_mmap(RegionPosition::new(0, 0), "test/region/r.0.0.mca").unwrap();
+
+        let chunk_compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(region.chunks().count(), region.readable_chunks().count());
+    }
+
+    #[test]
+    fn test_load_mmap_empty() {
+        let world = temp_world(&[]);
+        let path = world.path().join("load-mmap-empty.mca");
+        fs::write(&path, []).unwrap();
+
+        let region = Region::load_mmap(RegionPosition::new(0, 0), &path).unwrap();
+        assert!(region.is_empty());
+    }
+}
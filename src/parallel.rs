@@ -0,0 +1,236 @@
+//! Parallel chunks decoding backed by `rayon`.
+//!
+//! Compressed chunks data are read from source sequentially, decompression and
+//! decoding of NBT are done on the thread pool.
+//!
+//! Whole folder can also be processed with regions sharded between workers, so every
+//! region file are read by single worker only.
+use crate::error::ChunkReadError;
+use crate::position::{ChunkPosition, RegionChunkPosition};
+use crate::provider::FolderRegionProvider;
+use crate::region::{decode_chunk, Region};
+use nbt::CompoundTag;
+use rayon::prelude::*;
+use std::io;
+use std::io::{Read, Seek};
+
+/// Compression scheme type id and compressed chunk data.
+type CompressedChunk = Result<(u8, Vec<u8>), ChunkReadError>;
+
+impl<S: Read + Seek> Region<S> {
+    /// Returns parallel iterator over chunks present in region.
+    ///
+    /// All chunks data are read before iterator returned, errors of reading present
+    /// chunks are returned by iterator.
+    pub fn par_chunks(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = Result<(RegionChunkPosition, CompoundTag), ChunkReadError>>
+    {
+        compressed_chunks(self)
+            .into_par_iter()
+            .map(|(position, compressed_chunk)| {
+                let (compression_scheme, compressed_buffer) = compressed_chunk?;
+                let compound_tag = decode_chunk(compression_scheme, &compressed_buffer)?;
+
+                Ok((position, compound_tag))
+            })
+    }
+}
+
+impl FolderRegionProvider {
+    /// Returns parallel iterator over chunks present in all regions of folder.
+    ///
+    /// Regions are opened only for reading one by one, chunks are returned in unspecified order.
+    pub fn par_chunks(
+        &self,
+    ) -> Result<
+        impl ParallelIterator<Item = Result<(ChunkPosition, CompoundTag), ChunkReadError>> + '_,
+        io::Error,
+    > {
+        let chunks = self.iter_positions()?.flat_map(move |region_position| {
+            let mut region = match self.open_region(region_position, true) {
+                Ok(region) => region,
+                Err(io_error) => return vec![Err(io_error.into())],
+            };
+
+            compressed_chunks(&mut region)
+                .into_iter()
+                .map(|(position, compressed_chunk)| {
+                    let chunk_position =
+                        ChunkPosition::from_region_chunk(region_position, position);
+
+                    compressed_chunk.map(|compressed_chunk| (chunk_position, compressed_chunk))
+                })
+                .collect()
+        });
+
+        let par_chunks = chunks.par_bridge().map(|compressed_chunk| {
+            let (position, (compression_scheme, compressed_buffer)) = compressed_chunk?;
+            let compound_tag = decode_chunk(compression_scheme, &compressed_buffer)?;
+
+            Ok((position, compound_tag))
+        });
+
+        Ok(par_chunks)
+    }
+
+    /// Calls function for every chunk present in all regions of folder.
+    ///
+    /// Every region are opened only for reading and processed by single worker of
+    /// thread pool, so reads of file don't contend and chunks are decompressed on all cores.
+    /// Function are called in unspecified order, processing stops on the first error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::provider::FolderRegionProvider;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    ///
+    /// let provider = FolderRegionProvider::new("test/region");
+    /// let count = AtomicUsize::new(0);
+    ///
+    /// provider
+    ///     .par_for_each_chunk(|_, _| {
+    ///         count.fetch_add(1, Ordering::Relaxed);
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(count.into_inner() > 0);
+    /// ```
+    pub fn par_for_each_chunk<F>(&self, f: F) -> Result<(), ChunkReadError>
+    where
+        F: Fn(ChunkPosition, CompoundTag) + Sync + Send,
+    {
+        let region_positions: Vec<_> = self.iter_positions()?.collect();
+
+        region_positions
+            .into_par_iter()
+            .try_for_each(|region_position| {
+                let mut region = self.open_region(region_position, true)?;
+                let positions: Vec<_> = region.positions().collect();
+                let mut compressed_buffer = Vec::new();
+
+                for position in positions {
+                    let compound_tag = region.read_chunk_into(position, &mut compressed_buffer)?;
+                    let chunk_position =
+                        ChunkPosition::from_region_chunk(region_position, position);
+
+                    f(chunk_position, compound_tag);
+                }
+
+                Ok(())
+            })
+    }
+}
+
+/// Reads compressed data of all chunks present in region.
+fn compressed_chunks<S: Read + Seek>(
+    region: &mut Region<S>,
+) -> Vec<(RegionChunkPosition, CompressedChunk)> {
+    let positions: Vec<_> = region.positions().collect();
+
+    positions
+        .into_iter()
+        .map(|position| (position, region.read_compressed_chunk(position)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+    use rayon::prelude::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_region_par_chunks() {
+        let world = temp_world(&[(0, 0), (5, 7), (31, 31)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+
+        let chunks: Vec<_> = region
+            .par_chunks()
+            .map(|result| {
+                let (position, compound_tag) = result.unwrap();
+                let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+                assert_eq!(
+                    level_compound_tag.get_i32("xPos").unwrap(),
+                    position.x() as i32
+                );
+                assert_eq!(
+                    level_compound_tag.get_i32("zPos").unwrap(),
+                    position.z() as i32
+                );
+
+                position
+            })
+            .collect();
+
+        assert_eq!(chunks.len(), 3);
+    }
+
+    #[test]
+    fn test_provider_par_chunks() {
+        let world = temp_world(&[(0, 0), (40, -3), (-1, -1)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut positions: Vec<_> = provider
+            .par_chunks()
+            .unwrap()
+            .map(|result| result.unwrap().0)
+            .collect();
+        positions.sort();
+
+        assert_eq!(
+            positions,
+            vec![
+                ChunkPosition::new(-1, -1),
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(40, -3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_par_for_each_chunk() {
+        let world = temp_world(&[(0, 0), (1, 1), (40, -3), (-1, -1), (-33, 70)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+        let positions = Mutex::new(Vec::new());
+
+        provider
+            .par_for_each_chunk(|position, compound_tag| {
+                let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+                assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), position.x);
+                assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), position.z);
+
+                positions.lock().unwrap().push(position);
+            })
+            .unwrap();
+
+        let mut positions = positions.into_inner().unwrap();
+        positions.sort();
+
+        assert_eq!(
+            positions,
+            vec![
+                ChunkPosition::new(-33, 70),
+                ChunkPosition::new(-1, -1),
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(1, 1),
+                ChunkPosition::new(40, -3),
+            ]
+        );
+    }
+}
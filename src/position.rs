@@ -1,4 +1,24 @@
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+use crate::error::{ParsePositionError, PositionOutOfBoundsError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// Offsets of surrounding positions ordered by z and then by x coordinate.
+const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RegionPosition {
     pub x: i32,
     pub z: i32,
@@ -15,30 +35,906 @@ impl RegionPosition {
 
         RegionPosition::new(x, z)
     }
+
+    /// Returns bounds of chunks which are located in region.
+    pub fn chunk_bounds(&self) -> ChunkBounds {
+        let min = ChunkPosition::from_region_chunk(*self, RegionChunkPosition::new(0, 0));
+        let max = ChunkPosition::from_region_chunk(*self, RegionChunkPosition::new(31, 31));
+
+        ChunkBounds { min, max }
+    }
+
+    /// Returns `true` if chunk are located in region.
+    pub fn contains_chunk(&self, position: ChunkPosition) -> bool {
+        position.region() == *self
+    }
+
+    /// Returns region position moved by offset.
+    ///
+    /// Coordinates wrap around on overflow.
+    pub fn offset(&self, dx: i32, dz: i32) -> RegionPosition {
+        RegionPosition::new(self.x.wrapping_add(dx), self.z.wrapping_add(dz))
+    }
+
+    /// Returns 8 surrounding regions ordered by z and then by x coordinate.
+    ///
+    /// Coordinates wrap around on overflow, see `offset`.
+    pub fn neighbors(&self) -> [RegionPosition; 8] {
+        NEIGHBOR_OFFSETS.map(|(dx, dz)| self.offset(dx, dz))
+    }
 }
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+/// Position of chunk inside region.
+///
+/// Coordinates are private, so every position are guaranteed to be less than 32.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedRegionChunkPosition"))]
 pub struct RegionChunkPosition {
-    pub x: u8,
-    pub z: u8,
+    x: u8,
+    z: u8,
 }
 
 impl RegionChunkPosition {
+    /// Creates position inside region.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any coordinate are 32 or greater, use `try_new` for unchecked input.
     pub fn new(x: u8, z: u8) -> RegionChunkPosition {
-        debug_assert!(32 > x, "Region chunk x coordinate out of bounds");
-        debug_assert!(32 > z, "Region chunk z coordinate out of bounds");
+        assert!(32 > x, "Region chunk x coordinate out of bounds");
+        assert!(32 > z, "Region chunk z coordinate out of bounds");
 
         RegionChunkPosition { x, z }
     }
 
+    /// Creates position inside region or returns error if any coordinate are 32 or greater.
+    pub fn try_new(x: u8, z: u8) -> Result<RegionChunkPosition, PositionOutOfBoundsError> {
+        if x >= 32 || z >= 32 {
+            return Err(PositionOutOfBoundsError { x, z });
+        }
+
+        Ok(RegionChunkPosition { x, z })
+    }
+
+    /// Returns x coordinate inside region from 0 to 31.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    /// Returns z coordinate inside region from 0 to 31.
+    pub fn z(&self) -> u8 {
+        self.z
+    }
+
     pub fn from_chunk_position(chunk_x: i32, chunk_z: i32) -> RegionChunkPosition {
         let x = (chunk_x & 31) as u8;
         let z = (chunk_z & 31) as u8;
 
-        RegionChunkPosition::new(x, z)
+        RegionChunkPosition { x, z }
+    }
+
+    /// Returns all 1024 positions inside region in order of region header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::RegionChunkPosition;
+    ///
+    /// let mut positions = RegionChunkPosition::iter_all();
+    ///
+    /// assert_eq!(positions.next(), Some(RegionChunkPosition::new(0, 0)));
+    /// assert_eq!(positions.next(), Some(RegionChunkPosition::new(1, 0)));
+    /// assert_eq!(positions.last(), Some(RegionChunkPosition::new(31, 31)));
+    /// ```
+    pub fn iter_all() -> impl Iterator<Item = RegionChunkPosition> + Clone {
+        (0..1024).map(RegionChunkPosition::from_metadata_index)
+    }
+
+    /// Returns chunk position moved by offset with region which contains it,
+    /// offset can cross bounds of region where chunk are located.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::{RegionChunkPosition, RegionPosition};
+    ///
+    /// let region_position = RegionPosition::new(0, 0);
+    /// let position = RegionChunkPosition::new(0, 31);
+    ///
+    /// assert_eq!(
+    ///     position.offset(region_position, -1, 1),
+    ///     (RegionPosition::new(-1, 1), RegionChunkPosition::new(31, 0))
+    /// );
+    /// ```
+    pub fn offset(
+        &self,
+        region: RegionPosition,
+        dx: i32,
+        dz: i32,
+    ) -> (RegionPosition, RegionChunkPosition) {
+        let position = ChunkPosition::from_region_chunk(region, *self).offset(dx, dz);
+
+        (position.region(), position.region_chunk())
+    }
+
+    /// Returns global position of chunk which are located at this position inside region.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+    ///
+    /// let position = RegionChunkPosition::new(4, 2);
+    ///
+    /// assert_eq!(
+    ///     position.to_chunk_position(RegionPosition::new(-1, 1)),
+    ///     ChunkPosition::new(-28, 34)
+    /// );
+    /// ```
+    pub fn to_chunk_position(&self, region: RegionPosition) -> ChunkPosition {
+        ChunkPosition::from_region_chunk(region, *self)
+    }
+
+    pub(crate) fn from_metadata_index(index: usize) -> RegionChunkPosition {
+        debug_assert!(1024 > index, "Region chunk index out of bounds");
+
+        RegionChunkPosition {
+            x: (index % 32) as u8,
+            z: (index / 32) as u8,
+        }
     }
 
     pub(crate) fn metadata_index(&self) -> usize {
         self.x as usize + self.z as usize * 32
     }
 }
+
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkPosition {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPosition {
+    pub fn new(x: i32, z: i32) -> ChunkPosition {
+        ChunkPosition { x, z }
+    }
+
+    /// Returns position of chunk from position of region and chunk position inside it.
+    ///
+    /// Coordinates wrap around on overflow for regions outside of chunk coordinates range.
+    pub fn from_region_chunk(region: RegionPosition, region_chunk: RegionChunkPosition) -> Self {
+        ChunkPosition::new(
+            region
+                .x
+                .wrapping_mul(32)
+                .wrapping_add(region_chunk.x as i32),
+            region
+                .z
+                .wrapping_mul(32)
+                .wrapping_add(region_chunk.z as i32),
+        )
+    }
+
+    /// Returns position of region which contains chunk.
+    pub fn region(&self) -> RegionPosition {
+        RegionPosition::from_chunk_position(self.x, self.z)
+    }
+
+    /// Returns chunk position inside region.
+    pub fn region_chunk(&self) -> RegionChunkPosition {
+        RegionChunkPosition::from_chunk_position(self.x, self.z)
+    }
+
+    /// Returns index of chunk on Z-order (Morton) curve.
+    ///
+    /// Chunks which are close on curve are close in world as well, every aligned
+    /// square of chunks including region are continuous part of curve.
+    pub fn morton_index(&self) -> u64 {
+        // Sign bit are flipped, so negative coordinates are ordered before positive.
+        let x = (self.x as u32 ^ 0x8000_0000) as u64;
+        let z = (self.z as u32 ^ 0x8000_0000) as u64;
+
+        spread_bits(x) | spread_bits(z) << 1
+    }
+
+    /// Returns chunk position moved by offset.
+    ///
+    /// Coordinates wrap around on overflow.
+    pub fn offset(&self, dx: i32, dz: i32) -> ChunkPosition {
+        ChunkPosition::new(self.x.wrapping_add(dx), self.z.wrapping_add(dz))
+    }
+
+    /// Returns 8 surrounding chunks ordered by z and then by x coordinate.
+    ///
+    /// Coordinates wrap around on overflow, see `offset`.
+    pub fn neighbors(&self) -> [ChunkPosition; 8] {
+        NEIGHBOR_OFFSETS.map(|(dx, dz)| self.offset(dx, dz))
+    }
+
+    /// Returns positions of chunks which are not further than radius from center
+    /// on any axis, see `ChunkBounds::iter` for order of positions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::ChunkPosition;
+    ///
+    /// let positions: Vec<_> = ChunkPosition::iter_square(ChunkPosition::new(0, 0), 1).collect();
+    ///
+    /// assert_eq!(positions.len(), 9);
+    /// assert_eq!(positions[0], ChunkPosition::new(-1, -1));
+    /// assert_eq!(positions[8], ChunkPosition::new(1, 1));
+    /// ```
+    pub fn iter_square(center: ChunkPosition, radius: u32) -> impl Iterator<Item = ChunkPosition> {
+        ChunkBounds::square(center, radius).iter()
+    }
+
+    /// Returns positions of chunks between two corners, both corners are included.
+    pub fn iter_box(min: ChunkPosition, max: ChunkPosition) -> impl Iterator<Item = ChunkPosition> {
+        ChunkBounds::new(min, max).iter()
+    }
+}
+
+impl TryFrom<(u8, u8)> for RegionChunkPosition {
+    type Error = PositionOutOfBoundsError;
+
+    fn try_from((x, z): (u8, u8)) -> Result<Self, Self::Error> {
+        RegionChunkPosition::try_new(x, z)
+    }
+}
+
+/// Deserialized region chunk position which bounds are not checked yet.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedRegionChunkPosition {
+    x: u8,
+    z: u8,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedRegionChunkPosition> for RegionChunkPosition {
+    type Error = PositionOutOfBoundsError;
+
+    fn try_from(position: UncheckedRegionChunkPosition) -> Result<Self, Self::Error> {
+        RegionChunkPosition::try_new(position.x, position.z)
+    }
+}
+
+impl From<(i32, i32)> for ChunkPosition {
+    fn from((x, z): (i32, i32)) -> Self {
+        ChunkPosition::new(x, z)
+    }
+}
+
+impl From<ChunkPosition> for (i32, i32) {
+    fn from(position: ChunkPosition) -> Self {
+        (position.x, position.z)
+    }
+}
+
+impl From<ChunkPosition> for RegionPosition {
+    fn from(position: ChunkPosition) -> Self {
+        position.region()
+    }
+}
+
+impl From<ChunkPosition> for RegionChunkPosition {
+    fn from(position: ChunkPosition) -> Self {
+        position.region_chunk()
+    }
+}
+
+/// Position of block in world.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPosition {
+    pub fn new(x: i32, y: i32, z: i32) -> BlockPosition {
+        BlockPosition { x, y, z }
+    }
+
+    /// Returns position of chunk which contains block.
+    pub fn chunk(&self) -> ChunkPosition {
+        ChunkPosition::new(self.x >> 4, self.z >> 4)
+    }
+
+    /// Returns position of region which contains block.
+    pub fn region(&self) -> RegionPosition {
+        self.chunk().region()
+    }
+
+    /// Returns block position inside chunk.
+    pub fn chunk_block(&self) -> ChunkBlockPosition {
+        ChunkBlockPosition::new((self.x & 15) as u8, self.y, (self.z & 15) as u8)
+    }
+
+    /// Returns position of block from position of chunk and block position inside it.
+    pub fn from_chunk_block(chunk: ChunkPosition, chunk_block: ChunkBlockPosition) -> Self {
+        BlockPosition::new(
+            chunk.x * 16 + chunk_block.x as i32,
+            chunk_block.y,
+            chunk.z * 16 + chunk_block.z as i32,
+        )
+    }
+}
+
+impl From<BlockPosition> for ChunkPosition {
+    fn from(position: BlockPosition) -> Self {
+        position.chunk()
+    }
+}
+
+impl From<BlockPosition> for RegionPosition {
+    fn from(position: BlockPosition) -> Self {
+        position.region()
+    }
+}
+
+/// Position of block inside chunk, height are kept as is.
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct ChunkBlockPosition {
+    pub x: u8,
+    pub y: i32,
+    pub z: u8,
+}
+
+impl ChunkBlockPosition {
+    pub fn new(x: u8, y: i32, z: u8) -> ChunkBlockPosition {
+        debug_assert!(16 > x, "Chunk block x coordinate out of bounds");
+        debug_assert!(16 > z, "Chunk block z coordinate out of bounds");
+
+        ChunkBlockPosition { x, y, z }
+    }
+
+    /// Returns index of section which contains block.
+    pub fn section_y(&self) -> i32 {
+        self.y >> 4
+    }
+}
+
+/// Rectangular area of chunks, both corners are included.
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct ChunkBounds {
+    pub min: ChunkPosition,
+    pub max: ChunkPosition,
+}
+
+impl ChunkBounds {
+    /// Creates bounds from any two opposite corners.
+    pub fn new(first: ChunkPosition, second: ChunkPosition) -> ChunkBounds {
+        let min = ChunkPosition::new(first.x.min(second.x), first.z.min(second.z));
+        let max = ChunkPosition::new(first.x.max(second.x), first.z.max(second.z));
+
+        ChunkBounds { min, max }
+    }
+
+    /// Creates bounds of chunks which are not further than radius from center on any axis.
+    pub fn square(center: ChunkPosition, radius: u32) -> ChunkBounds {
+        let radius = radius.min(i32::MAX as u32) as i32;
+
+        let min = ChunkPosition::new(
+            center.x.saturating_sub(radius),
+            center.z.saturating_sub(radius),
+        );
+        let max = ChunkPosition::new(
+            center.x.saturating_add(radius),
+            center.z.saturating_add(radius),
+        );
+
+        ChunkBounds { min, max }
+    }
+
+    /// Returns `true` if chunk are inside bounds.
+    pub fn contains(&self, position: ChunkPosition) -> bool {
+        (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.z..=self.max.z).contains(&position.z)
+    }
+
+    /// Returns bounds of chunks which are inside both bounds or `None` if bounds
+    /// doesn't intersect.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::{ChunkBounds, ChunkPosition, RegionPosition};
+    ///
+    /// let selection = ChunkBounds::new(ChunkPosition::new(-10, 5), ChunkPosition::new(10, 40));
+    /// let region_bounds = RegionPosition::new(0, 1).chunk_bounds();
+    ///
+    /// assert_eq!(
+    ///     selection.intersection(&region_bounds),
+    ///     Some(ChunkBounds::new(ChunkPosition::new(0, 32), ChunkPosition::new(10, 40)))
+    /// );
+    /// ```
+    pub fn intersection(&self, other: &ChunkBounds) -> Option<ChunkBounds> {
+        let min = ChunkPosition::new(self.min.x.max(other.min.x), self.min.z.max(other.min.z));
+        let max = ChunkPosition::new(self.max.x.min(other.max.x), self.max.z.min(other.max.z));
+
+        if min.x > max.x || min.z > max.z {
+            return None;
+        }
+
+        Some(ChunkBounds { min, max })
+    }
+
+    /// Returns regions which intersect bounds with part of bounds inside every region.
+    ///
+    /// Regions are ordered by z and then by x coordinate.
+    pub fn regions(&self) -> impl Iterator<Item = (RegionPosition, ChunkBounds)> {
+        let bounds = *self;
+        let min_region_position = bounds.min.region();
+        let max_region_position = bounds.max.region();
+
+        (min_region_position.z..=max_region_position.z).flat_map(move |region_z| {
+            (min_region_position.x..=max_region_position.x).map(move |region_x| {
+                let region_position = RegionPosition::new(region_x, region_z);
+                let region_bounds = region_position.chunk_bounds();
+
+                // Region are between regions of bounds corners, so they always intersect.
+                (
+                    region_position,
+                    bounds.intersection(&region_bounds).unwrap(),
+                )
+            })
+        })
+    }
+
+    /// Returns positions of chunks inside bounds grouped by region, so every region
+    /// file needs to be opened once. Inside region chunks are in order of region header.
+    pub fn iter(&self) -> impl Iterator<Item = ChunkPosition> {
+        self.regions().flat_map(|(_, bounds)| {
+            (bounds.min.z..=bounds.max.z).flat_map(move |z| {
+                (bounds.min.x..=bounds.max.x).map(move |x| ChunkPosition::new(x, z))
+            })
+        })
+    }
+
+    /// Returns positions of chunks inside bounds ordered by Z-order curve.
+    ///
+    /// Every region inside bounds are visited once and neighbor chunks are mostly
+    /// processed close in time, which improves locality of caches when area are large.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anvil_region::position::{ChunkBounds, ChunkPosition};
+    ///
+    /// let bounds = ChunkBounds::new(ChunkPosition::new(0, 0), ChunkPosition::new(1, 1));
+    /// let positions: Vec<_> = bounds.iter_morton().collect();
+    ///
+    /// assert_eq!(
+    ///     positions,
+    ///     vec![
+    ///         ChunkPosition::new(0, 0),
+    ///         ChunkPosition::new(1, 0),
+    ///         ChunkPosition::new(0, 1),
+    ///         ChunkPosition::new(1, 1),
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter_morton(&self) -> impl Iterator<Item = ChunkPosition> {
+        let mut positions: Vec<_> = self.iter().collect();
+        positions.sort_unstable_by_key(ChunkPosition::morton_index);
+
+        positions.into_iter()
+    }
+}
+
+impl fmt::Display for RegionPosition {
+    /// Formats position as region filename without extension, e.g. `r.-1.1`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r.{}.{}", self.x, self.z)
+    }
+}
+
+impl FromStr for RegionPosition {
+    type Err = ParsePositionError;
+
+    /// Parses position formatted as `r.x.z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.strip_prefix("r.").map(|s| split_coordinates(s, '.'));
+
+        match parts.as_deref() {
+            Some([x, z]) => Ok(RegionPosition::new(parse(s, x)?, parse(s, z)?)),
+            _ => Err(ParsePositionError::new(s)),
+        }
+    }
+}
+
+impl fmt::Display for RegionChunkPosition {
+    /// Formats position as `region chunk x,z`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "region chunk {},{}", self.x, self.z)
+    }
+}
+
+impl FromStr for RegionChunkPosition {
+    type Err = ParsePositionError;
+
+    /// Parses position formatted as `region chunk x,z` or `x,z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = split_coordinates(s.strip_prefix("region chunk ").unwrap_or(s), ',');
+
+        match parts.as_slice() {
+            [x, z] => RegionChunkPosition::try_new(parse(s, x)?, parse(s, z)?)
+                .map_err(|_| ParsePositionError::new(s)),
+            _ => Err(ParsePositionError::new(s)),
+        }
+    }
+}
+
+impl fmt::Display for ChunkPosition {
+    /// Formats position as `chunk x,z`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chunk {},{}", self.x, self.z)
+    }
+}
+
+impl FromStr for ChunkPosition {
+    type Err = ParsePositionError;
+
+    /// Parses position formatted as `chunk x,z` or `x,z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = split_coordinates(s.strip_prefix("chunk ").unwrap_or(s), ',');
+
+        match parts.as_slice() {
+            [x, z] => Ok(ChunkPosition::new(parse(s, x)?, parse(s, z)?)),
+            _ => Err(ParsePositionError::new(s)),
+        }
+    }
+}
+
+impl fmt::Display for BlockPosition {
+    /// Formats position as `block x,y,z`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {},{},{}", self.x, self.y, self.z)
+    }
+}
+
+impl FromStr for BlockPosition {
+    type Err = ParsePositionError;
+
+    /// Parses position formatted as `block x,y,z` or `x,y,z`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = split_coordinates(s.strip_prefix("block ").unwrap_or(s), ',');
+
+        match parts.as_slice() {
+            [x, y, z] => Ok(BlockPosition::new(parse(s, x)?, parse(s, y)?, parse(s, z)?)),
+            _ => Err(ParsePositionError::new(s)),
+        }
+    }
+}
+
+fn split_coordinates(s: &str, separator: char) -> Vec<&str> {
+    s.split(separator).map(str::trim).collect()
+}
+
+fn parse<T: FromStr>(s: &str, coordinate: &str) -> Result<T, ParsePositionError> {
+    coordinate.parse().map_err(|_| ParsePositionError::new(s))
+}
+
+/// Sorts chunk positions by Z-order curve, see `ChunkPosition::morton_index`.
+pub fn sort_morton(positions: &mut [ChunkPosition]) {
+    positions.sort_unstable_by_key(ChunkPosition::morton_index);
+}
+
+/// Spreads lower 32 bits of value to even bits.
+fn spread_bits(value: u64) -> u64 {
+    let mut value = value & 0xFFFF_FFFF;
+    value = (value | value << 16) & 0x0000_FFFF_0000_FFFF;
+    value = (value | value << 8) & 0x00FF_00FF_00FF_00FF;
+    value = (value | value << 4) & 0x0F0F_0F0F_0F0F_0F0F;
+    value = (value | value << 2) & 0x3333_3333_3333_3333;
+    value = (value | value << 1) & 0x5555_5555_5555_5555;
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ParsePositionError, PositionOutOfBoundsError};
+    use crate::position::{
+        sort_morton, BlockPosition, ChunkBlockPosition, ChunkBounds, ChunkPosition,
+        RegionChunkPosition, RegionPosition,
+    };
+    use std::collections::HashSet;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_chunk_position_conversions() {
+        let position = ChunkPosition::from((-33, 70));
+
+        assert_eq!(RegionPosition::from(position), RegionPosition::new(-2, 2));
+        assert_eq!(
+            RegionChunkPosition::from(position),
+            RegionChunkPosition::new(31, 6)
+        );
+        assert_eq!(<(i32, i32)>::from(position), (-33, 70));
+
+        let region_position = RegionPosition::new(-2, 2);
+        let region_chunk_position = RegionChunkPosition::new(31, 6);
+
+        assert_eq!(
+            ChunkPosition::from_region_chunk(region_position, region_chunk_position),
+            position
+        );
+    }
+
+    #[test]
+    fn test_block_position_conversions() {
+        let position = BlockPosition::new(-1, -64, 513);
+
+        assert_eq!(position.chunk(), ChunkPosition::new(-1, 32));
+        assert_eq!(position.region(), RegionPosition::new(-1, 1));
+        assert_eq!(position.chunk_block(), ChunkBlockPosition::new(15, -64, 1));
+        assert_eq!(position.chunk_block().section_y(), -4);
+
+        assert_eq!(
+            BlockPosition::from_chunk_block(position.chunk(), position.chunk_block()),
+            position
+        );
+
+        let position = BlockPosition::new(-16, 70, -17);
+
+        assert_eq!(ChunkPosition::from(position), ChunkPosition::new(-1, -2));
+        assert_eq!(RegionPosition::from(position), RegionPosition::new(-1, -1));
+        assert_eq!(position.chunk_block(), ChunkBlockPosition::new(0, 70, 15));
+    }
+
+    #[test]
+    fn test_iter_area() {
+        let positions: Vec<_> =
+            ChunkPosition::iter_box(ChunkPosition::new(32, 1), ChunkPosition::new(30, 0)).collect();
+
+        // Chunks of region 0, 0 are returned before chunks of region 1, 0.
+        assert_eq!(
+            positions,
+            vec![
+                ChunkPosition::new(30, 0),
+                ChunkPosition::new(31, 0),
+                ChunkPosition::new(30, 1),
+                ChunkPosition::new(31, 1),
+                ChunkPosition::new(32, 0),
+                ChunkPosition::new(32, 1),
+            ]
+        );
+
+        let center = ChunkPosition::new(-1, 31);
+        let positions: Vec<_> = ChunkPosition::iter_square(center, 2).collect();
+        let bounds = ChunkBounds::square(center, 2);
+
+        assert_eq!(positions.len(), 25);
+        assert!(positions.iter().all(|&position| bounds.contains(position)));
+        assert_eq!(bounds.regions().count(), 4);
+
+        let mut regions: Vec<_> = positions.iter().map(|position| position.region()).collect();
+        regions.dedup();
+        assert_eq!(regions.len(), 4);
+
+        // Bounds are clamped to coordinate range.
+        assert_eq!(
+            ChunkPosition::iter_square(ChunkPosition::new(-1, -1), u32::MAX).next(),
+            Some(ChunkPosition::new(i32::MIN, i32::MIN))
+        );
+    }
+
+    #[test]
+    fn test_region_chunk_position_bounds() {
+        assert_eq!(
+            RegionChunkPosition::try_new(31, 0),
+            Ok(RegionChunkPosition::new(31, 0))
+        );
+        assert_eq!(
+            RegionChunkPosition::try_new(3, 32),
+            Err(PositionOutOfBoundsError { x: 3, z: 32 })
+        );
+        assert_eq!(
+            RegionChunkPosition::try_from((255, 1)),
+            Err(PositionOutOfBoundsError { x: 255, z: 1 })
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_region_chunk_position_out_of_bounds() {
+        RegionChunkPosition::new(32, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let position = ChunkPosition::new(-3, 40);
+        let json = serde_json::to_string(&position).unwrap();
+
+        assert_eq!(json, r#"{"x":-3,"z":40}"#);
+        assert_eq!(
+            serde_json::from_str::<ChunkPosition>(&json).unwrap(),
+            position
+        );
+
+        let position: RegionChunkPosition = serde_json::from_str(r#"{"x":31,"z":0}"#).unwrap();
+        assert_eq!(position, RegionChunkPosition::new(31, 0));
+
+        let result = serde_json::from_str::<RegionChunkPosition>(r#"{"x":32,"z":0}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_offsets() {
+        let region_position = RegionPosition::new(-1, 0);
+
+        assert_eq!(region_position.offset(2, -3), RegionPosition::new(1, -3));
+        assert_eq!(region_position.neighbors()[0], RegionPosition::new(-2, -1));
+        assert_eq!(region_position.neighbors()[7], RegionPosition::new(0, 1));
+
+        let position = ChunkPosition::new(31, 0);
+        let neighbors = position.neighbors();
+
+        assert!(!neighbors.contains(&position));
+        assert!(neighbors.contains(&ChunkPosition::new(32, -1)));
+        assert_eq!(
+            neighbors
+                .iter()
+                .filter(|chunk| chunk.region() != position.region())
+                .count(),
+            5
+        );
+
+        let region_chunk_position = RegionChunkPosition::new(31, 31);
+
+        assert_eq!(
+            region_chunk_position.offset(region_position, 1, 1),
+            (RegionPosition::new(0, 1), RegionChunkPosition::new(0, 0))
+        );
+        assert_eq!(
+            region_chunk_position.offset(region_position, -64, 0),
+            (RegionPosition::new(-3, 0), RegionChunkPosition::new(31, 31))
+        );
+        assert_eq!(
+            region_chunk_position.offset(region_position, 0, 0),
+            (region_position, region_chunk_position)
+        );
+    }
+
+    #[test]
+    fn test_offsets_wrap_around() {
+        let region_position = RegionPosition::new(i32::MAX, i32::MIN);
+
+        assert_eq!(
+            region_position.offset(1, -1),
+            RegionPosition::new(i32::MIN, i32::MAX)
+        );
+        assert_eq!(
+            region_position.neighbors()[0],
+            RegionPosition::new(i32::MAX - 1, i32::MAX)
+        );
+
+        let position = ChunkPosition::new(i32::MIN, i32::MAX);
+
+        assert_eq!(
+            position.neighbors()[7],
+            ChunkPosition::new(i32::MIN + 1, i32::MIN)
+        );
+        assert_eq!(
+            ChunkPosition::from_region_chunk(
+                RegionPosition::new(i32::MAX, -1),
+                RegionChunkPosition::new(4, 2)
+            ),
+            ChunkPosition::new(-28, -30)
+        );
+    }
+
+    #[test]
+    fn test_morton_order() {
+        assert!(
+            ChunkPosition::new(-1, -1).morton_index() < ChunkPosition::new(0, 0).morton_index()
+        );
+        assert_eq!(
+            ChunkPosition::new(0, 0).morton_index() + 1,
+            ChunkPosition::new(1, 0).morton_index()
+        );
+        assert_eq!(
+            ChunkPosition::new(0, 0).morton_index() + 2,
+            ChunkPosition::new(0, 1).morton_index()
+        );
+
+        let bounds = ChunkBounds::new(ChunkPosition::new(-40, -3), ChunkPosition::new(70, 33));
+        let positions: Vec<_> = bounds.iter_morton().collect();
+
+        assert_eq!(positions.len(), bounds.iter().count());
+
+        // Every region are visited once.
+        let mut regions: Vec<_> = positions.iter().map(|position| position.region()).collect();
+        regions.dedup();
+        assert_eq!(regions.len(), bounds.regions().count());
+
+        let mut sorted_positions: Vec<_> = bounds.iter().collect();
+        sort_morton(&mut sorted_positions);
+        assert_eq!(sorted_positions, positions);
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let region_position = RegionPosition::new(-1, 1);
+        assert_eq!(region_position.to_string(), "r.-1.1");
+        assert_eq!("r.-1.1".parse(), Ok(region_position));
+
+        let region_chunk_position = RegionChunkPosition::new(3, 31);
+        assert_eq!(region_chunk_position.to_string(), "region chunk 3,31");
+        assert_eq!("region chunk 3,31".parse(), Ok(region_chunk_position));
+        assert_eq!("3, 31".parse(), Ok(region_chunk_position));
+        assert!("3,32".parse::<RegionChunkPosition>().is_err());
+
+        let chunk_position = ChunkPosition::new(12, -5);
+        assert_eq!(chunk_position.to_string(), "chunk 12,-5");
+        assert_eq!("chunk 12,-5".parse(), Ok(chunk_position));
+        assert_eq!("12,-5".parse(), Ok(chunk_position));
+
+        let block_position = BlockPosition::new(1, -64, 3);
+        assert_eq!(block_position.to_string(), "block 1,-64,3");
+        assert_eq!(block_position.to_string().parse(), Ok(block_position));
+
+        assert_eq!(
+            "r.1".parse::<RegionPosition>(),
+            Err(ParsePositionError {
+                input: "r.1".to_owned()
+            })
+        );
+        assert!("chunk 1,a".parse::<ChunkPosition>().is_err());
+        assert!("1,2,3".parse::<ChunkPosition>().is_err());
+
+        let mut positions = HashSet::new();
+        positions.insert(chunk_position);
+        assert!(positions.contains(&ChunkPosition::new(12, -5)));
+    }
+
+    #[test]
+    fn test_region_chunk_bounds() {
+        let region_position = RegionPosition::new(-1, 2);
+        let bounds = region_position.chunk_bounds();
+
+        assert_eq!(bounds.min, ChunkPosition::new(-32, 64));
+        assert_eq!(bounds.max, ChunkPosition::new(-1, 95));
+        assert_eq!(bounds.iter().count(), 1024);
+        assert!(bounds
+            .iter()
+            .all(|position| region_position.contains_chunk(position)));
+
+        assert!(region_position.contains_chunk(ChunkPosition::new(-1, 64)));
+        assert!(!region_position.contains_chunk(ChunkPosition::new(0, 64)));
+        assert!(!region_position.contains_chunk(ChunkPosition::new(-32, 63)));
+
+        let other_bounds = RegionPosition::new(0, 2).chunk_bounds();
+        assert_eq!(bounds.intersection(&other_bounds), None);
+
+        let selection = ChunkBounds::new(ChunkPosition::new(-1, 90), ChunkPosition::new(5, 100));
+        assert_eq!(
+            selection.intersection(&bounds),
+            Some(ChunkBounds::new(
+                ChunkPosition::new(-1, 90),
+                ChunkPosition::new(-1, 95)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_to_chunk_position() {
+        for &(x, z) in &[(0, 0), (-1, -1), (-33, 64), (1000, -1000)] {
+            let position = ChunkPosition::new(x, z);
+            let region_chunk_position = position.region_chunk();
+
+            assert_eq!(
+                region_chunk_position.to_chunk_position(position.region()),
+                position
+            );
+        }
+    }
+}
@@ -1,98 +1,1105 @@
-use crate::position::RegionPosition;
-use crate::region::Region;
-use std::fs::{File, OpenOptions, read_dir};
-use std::path::Path;
-use std::{fs, io};
+use crate::cache::CachedRegionProvider;
+use crate::compression::CompressionScheme;
+use crate::error::{ChunkReadError, ChunkWriteError, InvalidRegionFilename};
+use crate::events::{read_events, ChunkEvent, EventLog};
+use crate::format::RegionFormat;
+use crate::lock::WorldLock;
+use crate::position::{ChunkBounds, ChunkPosition, RegionChunkPosition, RegionPosition};
+use crate::region::{Region, RegionIndex, SetLen, SyncAll};
+use crate::sidecar::ChunkSidecar;
+use crate::storage::StorageRegionProvider;
+use crate::tar_gz::read_tar_gz_regions;
+#[cfg(feature = "zip")]
+use crate::zip_provider::ZipRegionProvider;
+use nbt::CompoundTag;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::{read_dir, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::{fs, io};
+
+/// Prefix of region filenames.
+const REGION_FILENAME_PREFIX: &str = "r";
+/// Extension of Anvil region files.
+const REGION_FILENAME_EXTENSION: &str = "mca";
+/// Extension of McRegion region files.
+const MCREGION_FILENAME_EXTENSION: &str = "mcr";
+
+/// Name of chunk events log file in region folder.
+const EVENT_LOG_FILENAME: &str = "events.log";
+
+/// Amount of regions kept open by chunk provider returned from `open_world`.
+const OPEN_WORLD_CACHED_REGIONS: usize = 16;
+
+/// Folder where region files are placed if archive doesn't contain any.
+const DEFAULT_REGION_FOLDER: &str = "region/";
+
+/// Category of world data which are stored in region files.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum RegionKind {
+    /// Chunks with blocks stored in `region` folder.
+    Blocks,
+    /// Entities of chunks stored in `entities` folder since 1.17.
+    Entities,
+    /// Points of interest such as villager workstations and portals stored in `poi` folder.
+    Poi,
+}
+
+impl RegionKind {
+    /// Returns name of world folder where regions of this kind are stored.
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            RegionKind::Blocks => "region",
+            RegionKind::Entities => "entities",
+            RegionKind::Poi => "poi",
+        }
+    }
+}
 
 pub trait RegionProvider<S> {
     fn get_region(&self, region_pos: RegionPosition) -> Result<Region<S>, io::Error>;
+
+    /// Returns `true` if provider contains region, region are not created or loaded.
+    ///
+    /// Providers which can't check it without loading region return `true`,
+    /// so `get_region_if_exists` falls back to `get_region`.
+    fn region_exists(&self, region_pos: RegionPosition) -> bool {
+        let _ = region_pos;
+
+        true
+    }
+
+    /// Returns region or `None` if provider doesn't contain it.
+    ///
+    /// Unlike `get_region` missing region are not created.
+    fn get_region_if_exists(
+        &self,
+        region_pos: RegionPosition,
+    ) -> Result<Option<Region<S>>, io::Error> {
+        if !self.region_exists(region_pos) {
+            return Ok(None);
+        }
+
+        self.get_region(region_pos).map(Some)
+    }
+
+    /// Returns index of region header or `None` if provider doesn't contain region.
+    ///
+    /// Default implementation loads region, providers which can read header alone override it.
+    fn get_region_index(
+        &self,
+        region_pos: RegionPosition,
+    ) -> Result<Option<RegionIndex>, io::Error> {
+        let region = self.get_region_if_exists(region_pos)?;
+
+        Ok(region.map(|region| RegionIndex::from_region(region_pos, &region)))
+    }
+
+    /// Removes region and returns whether it existed.
+    ///
+    /// Providers which can't remove regions return `Unsupported` error.
+    fn remove_region(&self, region_pos: RegionPosition) -> Result<bool, io::Error> {
+        let _ = region_pos;
+
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Provider doesn't support region removal",
+        ))
+    }
+}
+
+/// Provider of chunks addressed by position in the world.
+///
+/// Hides splitting of chunk position into region and region chunk positions.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::cache::CachedRegionProvider;
+/// use anvil_region::position::ChunkPosition;
+/// use anvil_region::provider::{ChunkProvider, FolderRegionProvider};
+///
+/// fn inhabited_time<P: ChunkProvider>(provider: &mut P, position: ChunkPosition) -> i64 {
+///     let chunk_compound_tag = provider.load_chunk(position).unwrap();
+///     let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+///
+///     level_compound_tag.get_i64("InhabitedTime").unwrap()
+/// }
+///
+/// let mut provider = CachedRegionProvider::new(FolderRegionProvider::new("test/region"), 16);
+///
+/// assert_eq!(inhabited_time(&mut provider, ChunkPosition::new(4, 2)), 0);
+/// ```
+pub trait ChunkProvider {
+    /// Reads chunk at position in the world.
+    fn load_chunk(&mut self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError>;
+
+    /// Writes chunk at position in the world.
+    fn save_chunk(
+        &mut self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError>;
 }
 
-pub struct FolderRegionProvider<'a> {
+pub struct FolderRegionProvider {
     /// Folder where region files located.
-    folder_path: &'a Path,
+    folder_path: PathBuf,
+    /// Wasted sectors percent after which regions are compacted on flush.
+    auto_defrag_threshold: Option<u8>,
+    /// Whether regions truncate free sectors at the end of file.
+    truncate_freed_sectors: bool,
+    /// Whether regions overwrite freed sectors with zeros.
+    wipe_freed_sectors: bool,
+    /// Whether regions write chunks crash-safe.
+    safe_writes: bool,
+    /// Whether chunk writes and removals are recorded to event log in folder.
+    event_log: bool,
+    /// Compression scheme and level which are used by regions to write chunks.
+    write_compression: Option<(CompressionScheme, u32)>,
+    /// Whether region files are opened only for reading.
+    pub(crate) read_only: bool,
+    /// Whether missing region files are created.
+    pub(crate) create_missing_regions: bool,
+    /// Whether missing region folder are created.
+    create_missing_dirs: bool,
+    /// Session lock of world which must be held to write regions.
+    world_lock: Option<WorldLock>,
+    /// Naming of region files in folder.
+    pub(crate) filename_scheme: FilenameScheme,
 }
 
-impl<'a> FolderRegionProvider<'a> {
-    pub fn new(folder: &'a str) -> FolderRegionProvider<'a> {
-        let folder_path = Path::new(folder);
+impl FolderRegionProvider {
+    /// Creates provider of regions in folder, path are copied so provider doesn't borrow it.
+    pub fn new<P: AsRef<Path>>(folder: P) -> FolderRegionProvider {
+        FolderRegionProvider {
+            folder_path: folder.as_ref().to_path_buf(),
+            auto_defrag_threshold: None,
+            truncate_freed_sectors: false,
+            wipe_freed_sectors: false,
+            safe_writes: false,
+            event_log: false,
+            write_compression: None,
+            read_only: false,
+            create_missing_regions: true,
+            create_missing_dirs: true,
+            world_lock: None,
+            filename_scheme: FilenameScheme::default(),
+        }
+    }
+
+    /// Creates provider of regions of specified kind in world folder.
+    pub fn world<P: AsRef<Path>>(world_folder: P, kind: RegionKind) -> FolderRegionProvider {
+        FolderRegionProvider::new(world_folder.as_ref().join(kind.folder_name()))
+    }
+
+    /// Creates provider of entities regions in world folder.
+    ///
+    /// Entities regions have the same layout as chunk regions, chunks contains
+    /// `Entities` list and `Position` array of chunk x and z.
+    pub fn entities<P: AsRef<Path>>(world_folder: P) -> FolderRegionProvider {
+        FolderRegionProvider::world(world_folder, RegionKind::Entities)
+    }
+
+    /// Creates provider of points of interest regions in world folder.
+    ///
+    /// Chunks contains `Sections` compound tag with records of every section by its y.
+    pub fn poi<P: AsRef<Path>>(world_folder: P) -> FolderRegionProvider {
+        FolderRegionProvider::world(world_folder, RegionKind::Poi)
+    }
+
+    /// Creates provider which opens region files only for reading.
+    ///
+    /// Folder and region files are not created, missing region are returned
+    /// as `NotFound` error.
+    pub fn read_only<P: AsRef<Path>>(folder: P) -> FolderRegionProvider {
+        let mut provider = FolderRegionProvider::new(folder);
+        provider.read_only = true;
+
+        provider
+    }
+
+    /// Enables compaction of regions on flush when wasted sectors percent exceeds specified.
+    pub fn auto_defrag_when_waste_exceeds(mut self, percent: u8) -> Self {
+        self.auto_defrag_threshold = Some(percent);
+        self
+    }
+
+    /// Enables truncation of region files when sectors at the end become free.
+    ///
+    /// See `Region::set_truncate_freed_sectors`.
+    pub fn truncate_freed_sectors(mut self) -> Self {
+        self.truncate_freed_sectors = true;
+        self
+    }
+
+    /// Enables overwriting of sectors released by moved or removed chunks with zeros.
+    ///
+    /// See `Region::set_wipe_freed_sectors`.
+    pub fn wipe_freed_sectors(mut self) -> Self {
+        self.wipe_freed_sectors = true;
+        self
+    }
+
+    /// Enables crash-safe chunk writes.
+    ///
+    /// See `Region::set_safe_writes`.
+    pub fn safe_writes(mut self) -> Self {
+        self.safe_writes = true;
+        self
+    }
+
+    /// Sets whether `get_region` creates empty region file if it missing, enabled by default.
+    ///
+    /// When disabled missing region are returned as `NotFound` error.
+    pub fn create_missing_regions(mut self, create: bool) -> Self {
+        self.create_missing_regions = create;
+        self
+    }
+
+    /// Sets whether `get_region` creates region folder if it missing, enabled by default.
+    pub fn create_missing_dirs(mut self, create: bool) -> Self {
+        self.create_missing_dirs = create;
+        self
+    }
+
+    /// Sets session lock of world which are checked before region opened for writing
+    /// or removed.
+    ///
+    /// When lock was taken over by another process `PermissionDenied` error are returned,
+    /// already opened regions are not affected.
+    pub fn world_lock(mut self, world_lock: WorldLock) -> Self {
+        self.world_lock = Some(world_lock);
+        self
+    }
+
+    /// Sets naming of region files in folder, `r.x.z.mca` are used by default.
+    pub fn filename_scheme(mut self, filename_scheme: FilenameScheme) -> Self {
+        self.filename_scheme = filename_scheme;
+        self
+    }
+
+    /// Enables recording of chunk writes and removals to event log in folder.
+    ///
+    /// Recorded events can be read with `tail_events`.
+    pub fn event_log(mut self) -> Self {
+        self.event_log = true;
+        self
+    }
 
-        FolderRegionProvider { folder_path }
+    /// Sets compression scheme and level which are used by regions to write chunks.
+    ///
+    /// See `Region::set_write_compression_level` for level meaning.
+    pub fn write_compression(mut self, compression_scheme: CompressionScheme, level: u32) -> Self {
+        self.write_compression = Some((compression_scheme, level));
+        self
+    }
+
+    /// Returns folder where region files located.
+    pub fn folder_path(&self) -> &Path {
+        &self.folder_path
     }
 
     // leave implementing this to the specific provider,
     // makes function declaration bearable for now
-    pub fn iter_positions(&self) -> Result<impl Iterator<Item=RegionPosition>, io::Error> {
-        let positions: Vec<_> = read_dir(self.folder_path)?
+    pub fn iter_positions(&self) -> Result<impl Iterator<Item = RegionPosition>, io::Error> {
+        let positions: Vec<_> = read_dir(&self.folder_path)?
             .filter_map(|dir| dir.ok())
-            .filter_map(|dir| region_pos_from_filename(&dir.path()).ok())
+            .filter_map(|dir| self.filename_scheme.position(&dir.path()).ok())
             .collect();
 
         Ok(positions.into_iter())
     }
+
+    /// Returns positions of regions in folder with errors for every entry
+    /// which filename doesn't match filename scheme, so skipped files can be reported.
+    #[allow(clippy::type_complexity)]
+    pub fn iter_position_entries(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<RegionPosition, InvalidRegionFilename>>, io::Error>
+    {
+        let mut entries = Vec::new();
+
+        for dir in read_dir(&self.folder_path)? {
+            entries.push(self.filename_scheme.position(&dir?.path()));
+        }
+
+        Ok(entries.into_iter())
+    }
+
+    /// Returns iterator over indexes of all regions in folder.
+    ///
+    /// Only headers of region files are read, which allows to scan large amount
+    /// of regions quickly.
+    pub fn iter_indexes(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<RegionIndex, io::Error>> + '_, io::Error> {
+        let indexes = self.iter_positions()?.map(move |position| {
+            let region_path = self.region_path(position);
+            let mut file = File::open(region_path)?;
+
+            RegionIndex::read(position, &mut file)
+        });
+
+        Ok(indexes)
+    }
+
+    /// Returns iterator over all regions in folder.
+    ///
+    /// Regions are opened only for reading when iterator reaches them.
+    pub fn iter_regions(
+        &self,
+    ) -> Result<
+        impl Iterator<Item = Result<(RegionPosition, Region<File>), io::Error>> + '_,
+        io::Error,
+    > {
+        let regions = self.iter_positions()?.map(move |position| {
+            let region = self.open_region(position, true)?;
+
+            Ok((position, region))
+        });
+
+        Ok(regions)
+    }
+
+    /// Returns iterator over chunks of all regions in folder with their positions in the world.
+    ///
+    /// Regions are opened only for reading one by one, errors of opening regions and
+    /// reading present chunks are returned.
+    #[allow(clippy::type_complexity)]
+    pub fn iter_chunks(
+        &self,
+    ) -> Result<
+        impl Iterator<Item = Result<(ChunkPosition, CompoundTag), ChunkReadError>> + '_,
+        io::Error,
+    > {
+        let chunks = WorldChunks {
+            regions: self.iter_regions()?,
+            region: None,
+            current: 0,
+            compressed_buffer: Vec::new(),
+        };
+
+        Ok(chunks)
+    }
+
+    /// Returns iterator over chunks of all regions in folder which skips regions and chunks
+    /// that cannot be read.
+    pub fn iter_readable_chunks(
+        &self,
+    ) -> Result<impl Iterator<Item = (ChunkPosition, CompoundTag)> + '_, io::Error> {
+        Ok(self.iter_chunks()?.filter_map(Result::ok))
+    }
+
+    /// Returns last modified timestamps of chunks present inside bounds.
+    ///
+    /// Only headers of regions which intersects bounds are read, missing region files
+    /// are skipped. Chunks are ordered by region and position inside region.
+    pub fn chunk_timestamps(
+        &self,
+        bounds: ChunkBounds,
+    ) -> Result<impl Iterator<Item = (ChunkPosition, u32)>, io::Error> {
+        let min_region_position = bounds.min.region();
+        let max_region_position = bounds.max.region();
+        let mut timestamps = Vec::new();
+
+        for region_z in min_region_position.z..=max_region_position.z {
+            for region_x in min_region_position.x..=max_region_position.x {
+                let position = RegionPosition::new(region_x, region_z);
+                let region_path = self.region_path(position);
+
+                let mut file = match File::open(region_path) {
+                    Ok(file) => file,
+                    Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => continue,
+                    Err(io_error) => return Err(io_error),
+                };
+
+                let index = RegionIndex::read(position, &mut file)?;
+
+                for region_chunk_position in RegionChunkPosition::iter_all() {
+                    let chunk_position =
+                        ChunkPosition::from_region_chunk(position, region_chunk_position);
+
+                    if !bounds.contains(chunk_position) {
+                        continue;
+                    }
+
+                    let timestamp = index.chunk_last_modified_timestamp(region_chunk_position);
+
+                    if let Some(timestamp) = timestamp {
+                        timestamps.push((chunk_position, timestamp));
+                    }
+                }
+            }
+        }
+
+        Ok(timestamps.into_iter())
+    }
+
+    /// Returns chunk events recorded to event log starting from specified sequence number.
+    ///
+    /// Pass sequence number after the last seen event to receive only new events.
+    pub fn tail_events(&self, since: u64) -> Result<impl Iterator<Item = ChunkEvent>, io::Error> {
+        let events = read_events(self.folder_path.join(EVENT_LOG_FILENAME), since)?;
+
+        Ok(events.into_iter())
+    }
+
+    /// Returns store of user-defined chunks metadata kept next to region files.
+    pub fn sidecar(&self) -> ChunkSidecar {
+        ChunkSidecar::new(&self.folder_path)
+    }
+
+    /// Returns bitmaps of chunks present in every region of folder.
+    ///
+    /// See `Region::presence_bitmap` for bitmap layout. Only headers of region files are read.
+    pub fn presence_bitmaps(&self) -> Result<BTreeMap<RegionPosition, [u64; 16]>, io::Error> {
+        let mut bitmaps = BTreeMap::new();
+
+        for index in self.iter_indexes()? {
+            let index = index?;
+            bitmaps.insert(index.position(), index.presence_bitmap());
+        }
+
+        Ok(bitmaps)
+    }
+
+    /// Returns uniform random sample of `n` chunks present in folder.
+    ///
+    /// Same seed gives same sample, chunks are ordered by region and position inside region.
+    /// If folder contains less than `n` chunks all chunks are returned.
+    /// Regions are opened only for reading.
+    pub fn sample_chunks(
+        &self,
+        n: usize,
+        seed: u64,
+    ) -> Result<Vec<(ChunkPosition, CompoundTag)>, ChunkReadError> {
+        let bitmaps = self.presence_bitmaps()?;
+
+        let mut remaining: u64 = bitmaps
+            .values()
+            .flat_map(|bitmap| bitmap.iter())
+            .map(|word| word.count_ones() as u64)
+            .sum();
+
+        let mut needed = (n as u64).min(remaining);
+        let mut random = SplitMix64(seed);
+        let mut chunks = Vec::with_capacity(needed as usize);
+
+        // Selection sampling: every chunk are selected with probability needed / remaining.
+        for (region_position, bitmap) in bitmaps {
+            let mut region = None;
+
+            for index in 0..1024 {
+                if needed == 0 {
+                    break;
+                }
+
+                if bitmap[index / 64] & (1 << (index % 64)) == 0 {
+                    continue;
+                }
+
+                let selected = random.next() % remaining < needed;
+                remaining -= 1;
+
+                if !selected {
+                    continue;
+                }
+
+                needed -= 1;
+
+                let region = match &mut region {
+                    Some(region) => region,
+                    None => region.insert(self.open_region(region_position, true)?),
+                };
+
+                let region_chunk_position = RegionChunkPosition::from_metadata_index(index);
+                let chunk_position =
+                    ChunkPosition::from_region_chunk(region_position, region_chunk_position);
+
+                chunks.push((chunk_position, region.read_chunk(region_chunk_position)?));
+            }
+        }
+
+        Ok(chunks)
+    }
 }
 
-impl<'a> RegionProvider<File> for FolderRegionProvider<'a> {
+impl RegionProvider<File> for FolderRegionProvider {
     fn get_region(&self, position: RegionPosition) -> Result<Region<File>, io::Error> {
-        if !self.folder_path.exists() {
-            fs::create_dir(self.folder_path)?;
+        self.open_region(position, self.read_only)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        let region_path = self.region_path(position);
+
+        region_path.is_file()
+    }
+
+    /// Reads only region file header, missing file gives `None`.
+    fn get_region_index(&self, position: RegionPosition) -> Result<Option<RegionIndex>, io::Error> {
+        let region_path = self.region_path(position);
+
+        let mut file = match File::open(region_path) {
+            Ok(file) => file,
+            Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(io_error) => return Err(io_error),
+        };
+
+        RegionIndex::read(position, &mut file).map(Some)
+    }
+
+    /// Deletes region file, read only provider returns `PermissionDenied` error.
+    ///
+    /// Region of removed file must not be used, because its writes are lost.
+    fn remove_region(&self, position: RegionPosition) -> Result<bool, io::Error> {
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Regions of read only provider can't be removed",
+            ));
         }
 
-        let region_name = region_position_filename(position);
-        let region_path = self.folder_path.join(region_name);
+        if let Some(world_lock) = &self.world_lock {
+            world_lock.check()?;
+        }
 
-        let file = OpenOptions::new()
-            .write(true)
-            .read(true)
-            .create(true)
-            .open(region_path)?;
+        let region_path = self.region_path(position);
 
-        Region::load(position, file)
+        match fs::remove_file(region_path) {
+            Ok(()) => Ok(true),
+            Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(io_error) => Err(io_error),
+        }
     }
 }
 
-fn region_pos_from_filename(path: &Path) -> Result<RegionPosition, io::Error> {
-    // we can use lossy because of the bound check later
-    let filename = path.file_name().unwrap_or_default().to_string_lossy();
-    let parts: Vec<_> = filename.split('.').collect();
+impl FolderRegionProvider {
+    pub(crate) fn region_path(&self, position: RegionPosition) -> PathBuf {
+        self.folder_path
+            .join(self.filename_scheme.filename(position))
+    }
+
+    /// Opens region file and applies provider settings to region.
+    pub(crate) fn open_region(
+        &self,
+        position: RegionPosition,
+        read_only: bool,
+    ) -> Result<Region<File>, io::Error> {
+        let region_path = self.region_path(position);
+
+        let file = if read_only {
+            File::open(region_path)
+        } else {
+            self.prepare_region_write()?;
+
+            OpenOptions::new()
+                .write(true)
+                .read(true)
+                .create(self.create_missing_regions)
+                .truncate(false)
+                .open(region_path)
+        };
+
+        let file = file.map_err(|io_error| match io_error.kind() {
+            io::ErrorKind::NotFound => region_not_found(position),
+            _ => io_error,
+        })?;
+
+        self.load_region(position, file, read_only)
+    }
+
+    /// Checks world lock and creates missing region folder before region are opened for writing.
+    pub(crate) fn prepare_region_write(&self) -> Result<(), io::Error> {
+        if let Some(world_lock) = &self.world_lock {
+            world_lock.check()?;
+        }
+
+        if self.create_missing_dirs && !self.folder_path.exists() {
+            fs::create_dir(&self.folder_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Loads region from source and applies settings of provider to it.
+    pub(crate) fn load_region<S>(
+        &self,
+        position: RegionPosition,
+        mut source: S,
+        read_only: bool,
+    ) -> Result<Region<S>, io::Error>
+    where
+        S: Read + Write + Seek + SetLen + SyncAll,
+    {
+        let format = RegionFormat::detect(&mut source)?;
+
+        if !format.is_supported() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported region format {:?}", format.layout()),
+            ));
+        }
+
+        source.seek(SeekFrom::Start(0))?;
+
+        let mut region = if format.has_backup_header() {
+            Region::load_with_backup_header(position, source)?
+        } else {
+            Region::load(position, source)?
+        };
+
+        region.set_auto_defrag_threshold(self.auto_defrag_threshold);
+        region.set_truncate_freed_sectors(self.truncate_freed_sectors);
+        region.set_wipe_freed_sectors(self.wipe_freed_sectors);
+        region.set_safe_writes(self.safe_writes);
+
+        if self.event_log && !read_only {
+            region.set_event_log(EventLog::open(self.folder_path.join(EVENT_LOG_FILENAME))?);
+        }
+
+        if let Some((compression_scheme, level)) = self.write_compression {
+            region.set_write_compression_scheme(compression_scheme);
+            region.set_write_compression_level(level);
+        }
+
+        Ok(region)
+    }
+}
+
+/// Returns error of region which doesn't exist.
+pub(crate) fn region_not_found(position: RegionPosition) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("Region x: {}, z: {} not found", position.x, position.z),
+    )
+}
+
+/// Opens world located at the path with the provider suitable for it.
+///
+/// Path can point to:
+/// - world folder which contains `region` folder or to folder with region files itself,
+/// - zip archive with world, requires `zip` feature,
+/// - `.tar.gz` archive with world,
+/// - single `.mca` or `.mcr` region file, which are opened with provider of its folder.
+///
+/// Regions of archives are loaded to memory and opened only for reading,
+/// saving chunk to archived world returns `Unsupported` error.
+pub fn open_world(path: &Path) -> Result<Box<dyn ChunkProvider>, io::Error> {
+    if path.is_dir() {
+        let region_folder_path = path.join(RegionKind::Blocks.folder_name());
+
+        let folder_path = if region_folder_path.is_dir() {
+            region_folder_path.as_path()
+        } else {
+            path
+        };
+
+        let provider = FolderRegionProvider::new(folder_path);
+
+        return Ok(Box::new(CachedRegionProvider::new(
+            provider,
+            OPEN_WORLD_CACHED_REGIONS,
+        )));
+    }
+
+    if !path.is_file() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "World not found"));
+    }
+
+    let filename = path
+        .file_name()
+        .map(|filename| filename.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+        let provider = StorageRegionProvider::new(read_tar_gz_regions(path)?);
+
+        return Ok(Box::new(ArchiveChunkProvider(CachedRegionProvider::new(
+            provider,
+            OPEN_WORLD_CACHED_REGIONS,
+        ))));
+    }
+
+    match path.extension().and_then(OsStr::to_str) {
+        #[cfg(feature = "zip")]
+        Some("zip") => {
+            let provider = ZipRegionProvider::open(path)?;
+
+            Ok(Box::new(ArchiveChunkProvider(CachedRegionProvider::new(
+                provider,
+                OPEN_WORLD_CACHED_REGIONS,
+            ))))
+        }
+        Some(REGION_FILENAME_EXTENSION) => open_region_file(path, FilenameScheme::anvil()),
+        Some(MCREGION_FILENAME_EXTENSION) => open_region_file(path, FilenameScheme::mcregion()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Unsupported world format",
+        )),
+    }
+}
+
+/// Provider of archived world which rejects chunk writes, because they are not saved to archive.
+struct ArchiveChunkProvider<P: ChunkProvider>(P);
+
+impl<P: ChunkProvider> ChunkProvider for ArchiveChunkProvider<P> {
+    fn load_chunk(&mut self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+        self.0.load_chunk(position)
+    }
+
+    fn save_chunk(
+        &mut self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        let _ = (position, chunk_compound_tag);
+
+        Err(ChunkWriteError::from(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Chunks of archived world can't be saved",
+        )))
+    }
+}
+
+/// Opens provider of folder which contains region file, filename must match the scheme.
+fn open_region_file(
+    path: &Path,
+    filename_scheme: FilenameScheme,
+) -> Result<Box<dyn ChunkProvider>, io::Error> {
+    filename_scheme
+        .position(path)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+
+    let folder_path = path.parent().unwrap_or_else(|| Path::new(""));
+    let provider = FolderRegionProvider::new(folder_path).filename_scheme(filename_scheme);
+
+    Ok(Box::new(CachedRegionProvider::new(
+        provider,
+        OPEN_WORLD_CACHED_REGIONS,
+    )))
+}
+
+/// Finds region files of world in archive entries.
+///
+/// The least nested `region` folder are preferred, so regions of other dimensions
+/// are not mixed with overworld. Otherwise the least nested folder with region files are used.
+///
+/// Returns entry name prefix of region folder and region entries.
+pub(crate) fn find_region_entries<'a>(
+    names: impl Iterator<Item = &'a str>,
+) -> (String, BTreeMap<RegionPosition, String>) {
+    let mut folders: BTreeMap<&Path, BTreeMap<RegionPosition, String>> = BTreeMap::new();
+
+    for name in names {
+        let path = Path::new(name);
+
+        if let Ok(position) = FilenameScheme::anvil().position(path) {
+            let folder = path.parent().unwrap_or_else(|| Path::new(""));
+
+            folders
+                .entry(folder)
+                .or_default()
+                .insert(position, name.to_owned());
+        }
+    }
+
+    let depth = |folder: &&Path| folder.components().count();
+
+    let region_folder = folders
+        .keys()
+        .copied()
+        .filter(|folder| folder.file_name().is_some_and(|name| name == "region"))
+        .min_by_key(depth)
+        .or_else(|| folders.keys().copied().min_by_key(depth));
+
+    let region_folder = match region_folder {
+        Some(region_folder) => region_folder,
+        None => return (DEFAULT_REGION_FOLDER.to_owned(), BTreeMap::new()),
+    };
+
+    let region_entries = folders.remove(region_folder).unwrap_or_default();
+
+    if region_folder.as_os_str().is_empty() {
+        (String::new(), region_entries)
+    } else {
+        (
+            format!("{}/", region_folder.to_string_lossy()),
+            region_entries,
+        )
+    }
+}
+
+/// Iterator over chunks of regions which are opened one by one.
+struct WorldChunks<I> {
+    regions: I,
+    /// Region which chunks are currently iterated.
+    region: Option<(RegionPosition, Region<File>)>,
+    /// Index of the next chunk in current region.
+    current: usize,
+    /// Compressed chunk data buffer which are reused between chunks.
+    compressed_buffer: Vec<u8>,
+}
+
+impl<I> Iterator for WorldChunks<I>
+where
+    I: Iterator<Item = Result<(RegionPosition, Region<File>), io::Error>>,
+{
+    type Item = Result<(ChunkPosition, CompoundTag), ChunkReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((region_position, region)) = &mut self.region {
+                while self.current < 1024 {
+                    let index = self.current;
+                    self.current += 1;
+
+                    let region_chunk_position = RegionChunkPosition::from_metadata_index(index);
+
+                    if !region.contains_chunk(region_chunk_position) {
+                        continue;
+                    }
+
+                    let chunk_position =
+                        ChunkPosition::from_region_chunk(*region_position, region_chunk_position);
+
+                    let result = region
+                        .read_chunk_into(region_chunk_position, &mut self.compressed_buffer)
+                        .map(|compound_tag| (chunk_position, compound_tag));
+
+                    return Some(result);
+                }
+            }
+
+            self.region = None;
+            self.current = 0;
+
+            match self.regions.next()? {
+                Ok(region) => self.region = Some(region),
+                Err(io_error) => return Some(Err(io_error.into())),
+            }
+        }
+    }
+}
+
+/// Small deterministic pseudorandom generator used for sampling.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        value ^ (value >> 31)
+    }
+}
+
+/// Orders chunk positions in the way which allows reading them with minimal seeking.
+///
+/// Positions are grouped by region and sorted by chunk data offset inside region file.
+/// Positions of chunks which are not present in region are placed at the end of region group.
+/// Only region headers are read, see `RegionProvider::get_region_index`.
+/// Missing regions are not created, positions in them are kept in the original order.
+pub fn order_chunks_for_io<S, P: RegionProvider<S>>(
+    provider: &P,
+    positions: impl IntoIterator<Item = ChunkPosition>,
+) -> Result<Vec<ChunkPosition>, io::Error> {
+    let mut regions_positions: BTreeMap<RegionPosition, Vec<ChunkPosition>> = BTreeMap::new();
+
+    for position in positions {
+        regions_positions
+            .entry(position.region())
+            .or_default()
+            .push(position);
+    }
+
+    let mut ordered_positions = Vec::new();
+
+    for (region_position, mut positions) in regions_positions {
+        if let Some(index) = provider.get_region_index(region_position)? {
+            positions.sort_by_key(|position| {
+                index
+                    .chunk_start_sector_index(position.region_chunk())
+                    .unwrap_or(u32::MAX)
+            });
+        }
+
+        ordered_positions.extend(positions);
+    }
+
+    Ok(ordered_positions)
+}
+
+/// Naming of region files in form of `prefix.x.z.extension`.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::position::RegionPosition;
+/// use anvil_region::provider::{FilenameScheme, FolderRegionProvider};
+///
+/// let filename_scheme = FilenameScheme::new("r", "mcr");
+/// assert_eq!(filename_scheme.filename(RegionPosition::new(-1, 2)), "r.-1.2.mcr");
+///
+/// let provider = FolderRegionProvider::new("world/region").filename_scheme(filename_scheme);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FilenameScheme {
+    prefix: String,
+    extension: String,
+}
+
+impl FilenameScheme {
+    pub fn new(prefix: &str, extension: &str) -> Self {
+        FilenameScheme {
+            prefix: prefix.to_owned(),
+            extension: extension.to_owned(),
+        }
+    }
+
+    /// Returns scheme of Anvil region files `r.x.z.mca`.
+    pub fn anvil() -> Self {
+        FilenameScheme::new(REGION_FILENAME_PREFIX, REGION_FILENAME_EXTENSION)
+    }
+
+    /// Returns scheme of legacy McRegion files `r.x.z.mcr`.
+    pub fn mcregion() -> Self {
+        FilenameScheme::new(REGION_FILENAME_PREFIX, MCREGION_FILENAME_EXTENSION)
+    }
+
+    /// Returns filename of region file.
+    pub fn filename(&self, position: RegionPosition) -> String {
+        format_filename(&self.prefix, &self.extension, position)
+    }
+
+    /// Returns region position from filename of path or error if filename
+    /// doesn't match scheme.
+    pub fn position(&self, path: &Path) -> Result<RegionPosition, InvalidRegionFilename> {
+        // we can use lossy because of the bound check later
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+
+        match parse_filename(&self.prefix, &self.extension, &filename) {
+            Some(position) => Ok(position),
+            None => Err(InvalidRegionFilename {
+                name: filename.into_owned(),
+            }),
+        }
+    }
+}
+
+impl Default for FilenameScheme {
+    fn default() -> Self {
+        FilenameScheme::anvil()
+    }
+}
 
-    let (x, z) = parse_coords(parts).ok_or_else(|| io::ErrorKind::InvalidInput)?;
+pub(crate) fn region_position_filename(pos: RegionPosition) -> String {
+    format_filename(REGION_FILENAME_PREFIX, REGION_FILENAME_EXTENSION, pos)
+}
 
-    Ok(RegionPosition::new(x, z))
+fn format_filename(prefix: &str, extension: &str, pos: RegionPosition) -> String {
+    format!("{}.{}.{}.{}", prefix, pos.x, pos.z, extension)
 }
 
-fn region_position_filename(pos: RegionPosition) -> String {
-    format!("r.{}.{}.mca", pos.x, pos.z)
+fn parse_filename(prefix: &str, extension: &str, filename: &str) -> Option<RegionPosition> {
+    let filename = filename.strip_prefix(prefix)?;
+    let filename = filename.strip_suffix(extension)?;
+
+    // prefix and extension are removed, so x and z are surrounded by separators
+    let parts: Vec<_> = filename.split('.').collect();
+    let (x, z) = parse_coords(parts)?;
+
+    Some(RegionPosition::new(x, z))
 }
 
 fn parse_coords(parts: Vec<&str>) -> Option<(i32, i32)> {
-    let incorrect_format =
-        parts.len() != 4 ||
-            parts[0] != "r" ||
-            parts[3] != "mca";
+    let incorrect_format = parts.len() != 4 || !parts[0].is_empty() || !parts[3].is_empty();
 
     if incorrect_format {
         return None;
     }
 
-    Some((i32::from_str(parts[1]).ok()?,
-          i32::from_str(parts[2]).ok()?))
+    Some((i32::from_str(parts[1]).ok()?, i32::from_str(parts[2]).ok()?))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-    use crate::position::RegionPosition;
-    use crate::provider::region_pos_from_filename;
+    use crate::error::InvalidRegionFilename;
+    use crate::events::ChunkEventKind;
+    use crate::fixtures::{minimal_chunk, minimal_world, temp_world};
+    use crate::lock::WorldLock;
+    use crate::position::{ChunkBounds, ChunkPosition, RegionChunkPosition, RegionPosition};
+    use crate::provider::{
+        find_region_entries, open_world, order_chunks_for_io, FilenameScheme, FolderRegionProvider,
+        RegionKind, RegionProvider,
+    };
+    use crate::region::{Region, RegionIndex};
+    use crate::temp::TempWorldProvider;
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::io;
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+
+    #[test]
+    fn test_filename_scheme() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(&region_folder)
+            .filename_scheme(FilenameScheme::new("region", "mcc"));
+
+        assert_eq!(provider.iter_positions().unwrap().count(), 0);
+
+        provider.get_region(RegionPosition::new(-3, 1)).unwrap();
+        assert!(region_folder.join("region.-3.1.mcc").is_file());
+        assert!(provider.region_exists(RegionPosition::new(-3, 1)));
+
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(-3, 1)]
+        );
+
+        let filename_scheme = FilenameScheme::mcregion();
+        assert_eq!(
+            filename_scheme.position(Path::new("r.1.2.mcr")),
+            Ok(RegionPosition::new(1, 2))
+        );
+        assert!(filename_scheme.position(Path::new("r.1.2.mca")).is_err());
+        assert!(filename_scheme.position(Path::new("r.1.2.3.mcr")).is_err());
+        assert!(filename_scheme.position(Path::new("rr.1.2.mcr")).is_err());
+
+        // Entries which are not region files are reported.
+        fs::write(region_folder.join("r.0.0.mca"), []).unwrap();
+
+        let mut entries: Vec<_> = provider.iter_position_entries().unwrap().collect();
+        entries.sort_by_key(|entry| entry.is_err());
+
+        assert_eq!(
+            entries,
+            vec![
+                Ok(RegionPosition::new(-3, 1)),
+                Err(InvalidRegionFilename {
+                    name: "r.0.0.mca".to_owned()
+                }),
+            ]
+        );
+    }
 
     #[test]
     fn test_position_parse() {
         let mut path = PathBuf::new();
         path.set_file_name("r.-1.1.mca");
 
-        let pos = region_pos_from_filename(&path).unwrap();
-        assert_eq!(RegionPosition{ x: -1, z: 1}, pos)
+        let pos = FilenameScheme::anvil().position(&path).unwrap();
+        assert_eq!(RegionPosition { x: -1, z: 1 }, pos)
     }
 
     #[test]
@@ -101,6 +1108,609 @@ mod tests {
         let mut path = PathBuf::new();
         path.set_file_name("this is not a valid region.filename");
 
-        region_pos_from_filename(&path).unwrap();
+        FilenameScheme::anvil().position(&path).unwrap();
+    }
+
+    #[test]
+    fn test_find_region_entries() {
+        let names = [
+            "World/level.dat",
+            "World/DIM-1/region/r.0.0.mca",
+            "World/region/r.0.0.mca",
+            "World/region/r.-1.2.mca",
+            "World/region/r.0.0.mca.bak",
+        ];
+
+        let (region_folder, entries) = find_region_entries(names.iter().copied());
+
+        assert_eq!(region_folder, "World/region/");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[&RegionPosition::new(0, 0)],
+            "World/region/r.0.0.mca"
+        );
+        assert_eq!(
+            entries[&RegionPosition::new(-1, 2)],
+            "World/region/r.-1.2.mca"
+        );
+
+        let (region_folder, entries) =
+            find_region_entries(["r.3.4.mca", "backup/r.0.0.mca"].iter().copied());
+
+        assert_eq!(region_folder, "");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[&RegionPosition::new(3, 4)], "r.3.4.mca");
+
+        let (region_folder, entries) = find_region_entries(["level.dat"].iter().copied());
+
+        assert_eq!(region_folder, "region/");
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_order_chunks_for_io() {
+        let world = TempWorldProvider::new().unwrap();
+        let region_folder = world.path().join("region");
+        fs::copy("test/region/r.0.0.mca", region_folder.join("r.0.0.mca")).unwrap();
+
+        let provider = FolderRegionProvider::new(&region_folder);
+
+        let positions = vec![
+            ChunkPosition::new(40, 2),
+            ChunkPosition::new(4, 8),
+            ChunkPosition::new(28, 1),
+            ChunkPosition::new(1, 8),
+            ChunkPosition::new(0, 8),
+            ChunkPosition::new(3, 8),
+            ChunkPosition::new(2, 8),
+        ];
+
+        let ordered_positions = order_chunks_for_io(&provider, positions).unwrap();
+
+        // Sorted by offsets from region header, missing chunk are last.
+        assert_eq!(
+            ordered_positions,
+            vec![
+                ChunkPosition::new(4, 8),
+                ChunkPosition::new(0, 8),
+                ChunkPosition::new(1, 8),
+                ChunkPosition::new(2, 8),
+                ChunkPosition::new(3, 8),
+                ChunkPosition::new(28, 1),
+                ChunkPosition::new(40, 2),
+            ]
+        );
+
+        // Missing region are not created.
+        assert!(!provider.region_exists(RegionPosition::new(1, 0)));
+        assert!(provider
+            .get_region_index(RegionPosition::new(1, 0))
+            .unwrap()
+            .is_none());
+
+        // Header read alone matches index of loaded region.
+        let region_position = RegionPosition::new(0, 0);
+        let index = provider.get_region_index(region_position).unwrap().unwrap();
+        let region = provider.get_region(region_position).unwrap();
+        let region_index = RegionIndex::from_region(region_position, &region);
+
+        assert_eq!(index.changed_chunks(&region_index).count(), 0);
+        assert_eq!(
+            index.chunk_start_sector_index(RegionChunkPosition::new(4, 8)),
+            region.chunk_start_sector_index(&RegionChunkPosition::new(4, 8))
+        );
+    }
+
+    #[test]
+    fn test_open_world() {
+        for path in &["test", "test/region", "test/region/r.0.0.mca"] {
+            let mut provider = open_world(Path::new(path)).unwrap();
+
+            let compound_tag = provider.load_chunk(ChunkPosition::new(15, 3)).unwrap();
+            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+            assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn test_open_world_unsupported_format() {
+        for path in &["Cargo.toml", "test/empty_region.mca", "test/missing"] {
+            assert!(open_world(Path::new(path)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_presence_bitmaps() {
+        let provider = FolderRegionProvider::new("test/region");
+        let bitmaps = provider.presence_bitmaps().unwrap();
+
+        let bitmap = bitmaps.get(&RegionPosition::new(0, 0)).unwrap();
+        let index = RegionChunkPosition::new(15, 3).metadata_index();
+
+        assert_ne!(bitmap[index / 64] & (1 << (index % 64)), 0);
+    }
+
+    #[test]
+    fn test_sample_chunks() {
+        let chunks: Vec<_> = (0..10).map(|index| (index * 7, -index * 3)).collect();
+
+        let world = temp_world(&chunks);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let sample = provider.sample_chunks(4, 42).unwrap();
+        let positions: Vec<_> = sample.iter().map(|(position, _)| *position).collect();
+
+        assert_eq!(sample.len(), 4);
+
+        for (position, compound_tag) in &sample {
+            let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            assert!(chunks.contains(&(position.x, position.z)));
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), position.x);
+        }
+
+        let same_seed_positions: Vec<_> = provider
+            .sample_chunks(4, 42)
+            .unwrap()
+            .into_iter()
+            .map(|(position, _)| position)
+            .collect();
+
+        assert_eq!(positions, same_seed_positions);
+        assert_eq!(provider.sample_chunks(100, 1).unwrap().len(), 10);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_iter_regions() {
+        let world = temp_world(&[(0, 0), (-1, 40), (-2, 41)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut regions: Vec<_> = provider
+            .iter_regions()
+            .unwrap()
+            .map(|result| result.unwrap())
+            .collect();
+        regions.sort_by_key(|(position, _)| *position);
+
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].0, RegionPosition::new(-1, 1));
+        assert_eq!(regions[0].1.positions().count(), 2);
+
+        // Regions are opened read only.
+        assert!(regions[1]
+            .1
+            .write_chunk(RegionChunkPosition::new(1, 1), minimal_chunk(1, 1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_region_exists() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(&region_folder);
+
+        assert!(provider.region_exists(RegionPosition::new(0, 0)));
+        assert!(!provider.region_exists(RegionPosition::new(1, 0)));
+
+        let region = provider
+            .get_region_if_exists(RegionPosition::new(0, 0))
+            .unwrap();
+        assert!(region
+            .unwrap()
+            .contains_chunk(RegionChunkPosition::new(0, 0)));
+
+        let region = provider
+            .get_region_if_exists(RegionPosition::new(1, 0))
+            .unwrap();
+        assert!(region.is_none());
+        assert!(!region_folder.join("r.1.0.mca").exists());
+    }
+
+    #[test]
+    fn test_world_lock() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let world_lock = WorldLock::acquire(dir).unwrap();
+        let provider = FolderRegionProvider::new(&region_folder).world_lock(world_lock);
+
+        provider.get_region(RegionPosition::new(0, 0)).unwrap();
+
+        // Another process opens world.
+        WorldLock::acquire(dir).unwrap();
+
+        let io_error = provider
+            .get_region(RegionPosition::new(0, 0))
+            .err()
+            .unwrap();
+        assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+
+        let io_error = provider
+            .remove_region(RegionPosition::new(0, 0))
+            .unwrap_err();
+        assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(region_folder.join("r.0.0.mca").exists());
+    }
+
+    #[test]
+    fn test_remove_region() {
+        let world = temp_world(&[(0, 0), (40, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(&region_folder);
+
+        assert!(provider.remove_region(RegionPosition::new(1, 0)).unwrap());
+        assert!(!provider.remove_region(RegionPosition::new(1, 0)).unwrap());
+        assert!(!region_folder.join("r.1.0.mca").exists());
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(0, 0)]
+        );
+
+        let provider = FolderRegionProvider::read_only(&region_folder);
+        let io_error = provider
+            .remove_region(RegionPosition::new(0, 0))
+            .unwrap_err();
+
+        assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(region_folder.join("r.0.0.mca").exists());
+    }
+
+    #[test]
+    fn test_owned_folder_path() {
+        let provider = {
+            let folder_path = PathBuf::from("test").join("region");
+            FolderRegionProvider::read_only(&folder_path)
+        };
+
+        let handle = thread::spawn(move || {
+            let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+            region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap()
+        });
+
+        let chunk_compound_tag = handle.join().unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+    }
+
+    #[test]
+    fn test_iter_chunks() {
+        let world = temp_world(&[(0, 0), (-1, 40), (-2, 41), (70, 3)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut chunks: Vec<_> = provider
+            .iter_chunks()
+            .unwrap()
+            .map(|result| result.unwrap())
+            .map(|(position, compound_tag)| {
+                let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+                let x = level_compound_tag.get_i32("xPos").unwrap();
+                let z = level_compound_tag.get_i32("zPos").unwrap();
+
+                assert_eq!(position, ChunkPosition::new(x, z));
+                position
+            })
+            .collect();
+        chunks.sort();
+
+        assert_eq!(
+            chunks,
+            vec![
+                ChunkPosition::new(-2, 41),
+                ChunkPosition::new(-1, 40),
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(70, 3),
+            ]
+        );
+
+        // Region with chunk which compression scheme are unknown.
+        let mut data = vec![0; 12288];
+        data[..4].copy_from_slice(&[0, 0, 2, 1]);
+        data[8192..8197].copy_from_slice(&[0, 0, 0, 1, 42]);
+        fs::write(region_folder.join("r.5.5.mca"), data).unwrap();
+
+        assert_eq!(
+            provider
+                .iter_chunks()
+                .unwrap()
+                .filter(Result::is_err)
+                .count(),
+            1
+        );
+        assert_eq!(provider.iter_readable_chunks().unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_iter_indexes() {
+        let world = temp_world(&[(0, 0), (1, 0), (40, -3)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut indexes: Vec<_> = provider
+            .iter_indexes()
+            .unwrap()
+            .map(|index| index.unwrap())
+            .collect();
+        indexes.sort_by_key(|index| index.position());
+
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(indexes[0].position(), RegionPosition::new(0, 0));
+        assert_eq!(indexes[0].chunks_count(), 2);
+        assert!(indexes[1].contains_chunk(ChunkPosition::new(40, -3).region_chunk()));
+    }
+
+    #[test]
+    fn test_chunk_timestamps() {
+        let world = temp_world(&[(0, 0), (1, 0), (40, -3), (-5, 2), (100, 100)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let bounds = ChunkBounds::new(ChunkPosition::new(40, 0), ChunkPosition::new(-5, -3));
+        let timestamps: Vec<_> = provider.chunk_timestamps(bounds).unwrap().collect();
+        let positions: Vec<_> = timestamps.iter().map(|(position, _)| *position).collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                ChunkPosition::new(40, -3),
+                ChunkPosition::new(0, 0),
+                ChunkPosition::new(1, 0),
+            ]
+        );
+        assert!(timestamps.iter().all(|(_, timestamp)| *timestamp > 0));
+    }
+
+    #[test]
+    fn test_get_region_format() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap());
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        region.set_backup_header(true);
+        region.flush().unwrap();
+
+        // Chunk offset points outside of file, header are recovered from backup header.
+        let region_path = region_folder.join("r.0.0.mca");
+        let mut data = fs::read(&region_path).unwrap();
+        data[..4].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0x01]);
+        fs::write(&region_path, data).unwrap();
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(region.read_chunk(RegionChunkPosition::new(0, 0)).is_ok());
+
+        // Linear region in place of Anvil.
+        let linear_path = region_folder.join("r.1.0.mca");
+        fs::write(
+            &linear_path,
+            [0xC3, 0xFF, 0x13, 0x18, 0x3C, 0xCA, 0x9D, 0x9A, 1],
+        )
+        .unwrap();
+
+        let error = provider
+            .get_region(RegionPosition::new(1, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tail_events() {
+        let world = temp_world(&[(0, 0), (33, -1)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap()).event_log();
+
+        assert_eq!(provider.tail_events(0).unwrap().count(), 0);
+
+        let chunk_compound_tag = minimal_chunk(33, -1);
+        let mut region = provider.get_region(RegionPosition::new(1, -1)).unwrap();
+        region
+            .write_chunk(
+                RegionChunkPosition::from_chunk_position(33, -1),
+                chunk_compound_tag,
+            )
+            .unwrap();
+        region
+            .remove_chunk(RegionChunkPosition::from_chunk_position(33, -1))
+            .unwrap();
+
+        let events: Vec<_> = provider.tail_events(0).unwrap().collect();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].position, ChunkPosition::new(33, -1));
+        assert_eq!(events[0].kind, ChunkEventKind::Write);
+        assert_eq!(events[1].kind, ChunkEventKind::Remove);
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        region.remove_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+
+        let events: Vec<_> = provider
+            .tail_events(events[1].sequence + 1)
+            .unwrap()
+            .collect();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].sequence, 2);
+        assert_eq!(events[0].position, ChunkPosition::new(0, 0));
+
+        // Event log doesn't break iteration over regions.
+        assert_eq!(provider.iter_positions().unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("read-only");
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::read_only(region_folder.to_str().unwrap());
+
+        let error = provider
+            .get_region(RegionPosition::new(0, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(!region_folder.exists());
+
+        minimal_world(&dir, &[(0, 0)]).unwrap();
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(region.read_chunk(RegionChunkPosition::new(0, 0)).is_ok());
+        assert!(region
+            .write_chunk(RegionChunkPosition::new(1, 1), minimal_chunk(1, 1))
+            .is_err());
+
+        let error = provider
+            .get_region(RegionPosition::new(1, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(!region_folder.join("r.1.0.mca").exists());
+    }
+
+    #[test]
+    fn test_create_missing() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("create-missing");
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap())
+            .create_missing_dirs(false)
+            .create_missing_regions(false);
+
+        let error = provider
+            .get_region(RegionPosition::new(0, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(!region_folder.exists());
+
+        fs::create_dir_all(&dir).unwrap();
+        let provider = FolderRegionProvider::new(region_folder.to_str().unwrap())
+            .create_missing_regions(false);
+
+        let error = provider
+            .get_region(RegionPosition::new(0, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(region_folder.is_dir());
+        assert!(!region_folder.join("r.0.0.mca").exists());
+
+        minimal_world(&dir, &[(0, 0)]).unwrap();
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(1, 1), minimal_chunk(1, 1))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_entities() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let provider = FolderRegionProvider::entities(dir.to_str().unwrap());
+
+        let mut entities_compound_tag = CompoundTag::new();
+        entities_compound_tag.insert_i32("DataVersion", 3465);
+        entities_compound_tag.insert_i32_vec("Position", vec![33, -1]);
+        entities_compound_tag.insert_compound_tag_vec("Entities", Vec::new());
+
+        let mut region = provider.get_region(RegionPosition::new(1, -1)).unwrap();
+        region.set_verify_write_positions(true);
+        region
+            .write_chunk(
+                RegionChunkPosition::from_chunk_position(33, -1),
+                entities_compound_tag,
+            )
+            .unwrap();
+
+        assert!(dir.join("entities").join("r.1.-1.mca").is_file());
+
+        let provider = FolderRegionProvider::world(dir.to_str().unwrap(), RegionKind::Blocks);
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_poi() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let provider = FolderRegionProvider::poi(dir.to_str().unwrap());
+        let position = RegionChunkPosition::from_chunk_position(-5, 7);
+
+        let mut record_compound_tag = CompoundTag::new();
+        record_compound_tag.insert_str("type", "minecraft:nether_portal");
+        record_compound_tag.insert_i32_vec("pos", vec![-80, 64, 112]);
+        record_compound_tag.insert_i32("free_tickets", 0);
+
+        let mut section_compound_tag = CompoundTag::new();
+        section_compound_tag.insert_bool("Valid", true);
+        section_compound_tag.insert_compound_tag_vec("Records", vec![record_compound_tag]);
+
+        let mut sections_compound_tag = CompoundTag::new();
+        sections_compound_tag.insert_compound_tag("4", section_compound_tag);
+
+        let mut poi_compound_tag = CompoundTag::new();
+        poi_compound_tag.insert_i32("DataVersion", 3465);
+        poi_compound_tag.insert_compound_tag("Sections", sections_compound_tag);
+
+        let mut region = provider.get_region(RegionPosition::new(-1, 0)).unwrap();
+        region.write_chunk(position, poi_compound_tag).unwrap();
+
+        let poi_compound_tag = region.read_chunk(position).unwrap();
+        let sections_compound_tag = poi_compound_tag.get_compound_tag("Sections").unwrap();
+
+        assert!(sections_compound_tag.get_compound_tag("4").is_ok());
+        assert!(dir.join("poi").join("r.-1.0.mca").is_file());
+    }
+
+    #[test]
+    fn test_default_region_exists() {
+        let provider = MemoryRegionProvider;
+        let position = RegionPosition::new(0, 0);
+
+        assert!(provider.region_exists(position));
+        assert!(provider.get_region_if_exists(position).unwrap().is_some());
+    }
+
+    /// Provider which implements only required method.
+    struct MemoryRegionProvider;
+
+    impl RegionProvider<Cursor<Vec<u8>>> for MemoryRegionProvider {
+        fn get_region(
+            &self,
+            region_pos: RegionPosition,
+        ) -> Result<Region<Cursor<Vec<u8>>>, io::Error> {
+            Region::load(region_pos, Cursor::new(Vec::new()))
+        }
+    }
+}
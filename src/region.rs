@@ -1,41 +1,129 @@
-use crate::error::{ChunkReadError, ChunkWriteError};
-use crate::position::{RegionChunkPosition, RegionPosition};
+use crate::compression::CompressionScheme;
+use crate::error::{ChunkCopyError, ChunkReadError, ChunkWriteError};
+use crate::events::{ChunkEventKind, EventLog};
+use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+use crate::scratch::ChunkEncoder;
+use crate::source::Window;
+use crate::stats::RegionStats;
+#[cfg(feature = "allocation-trace")]
+use crate::trace::{AllocationEvent, AllocationTrace};
+use crate::validation::{ValidationIssue, ValidationReport};
 use bitvec::prelude::*;
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use log::debug;
-use nbt::decode::{read_gzip_compound_tag, read_zlib_compound_tag};
-use nbt::encode::write_zlib_compound_tag;
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use log::{debug, warn};
+use nbt::decode::{read_compound_tag, read_gzip_compound_tag, read_zlib_compound_tag};
 use nbt::CompoundTag;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs::File;
 use std::io;
 use std::io::{Cursor, Error, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::ops::{Deref, DerefMut};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Amount of chunks in region.
-const REGION_CHUNKS: usize = 1024;
+pub(crate) const REGION_CHUNKS: usize = 1024;
 /// Length of chunks metadata in region.
 const REGION_CHUNKS_METADATA_LENGTH: usize = 2 * REGION_CHUNKS;
 /// Region header length in bytes.
-const REGION_HEADER_BYTES_LENGTH: u64 = 8 * REGION_CHUNKS as u64;
+pub(crate) const REGION_HEADER_BYTES_LENGTH: u64 = 8 * REGION_CHUNKS as u64;
 /// Region sector length in bytes.
-const REGION_SECTOR_BYTES_LENGTH: u16 = 4096;
+pub(crate) const REGION_SECTOR_BYTES_LENGTH: u16 = 4096;
 /// Maximum chunk length in bytes.
-const CHUNK_MAXIMUM_BYTES_LENGTH: u32 = REGION_SECTOR_BYTES_LENGTH as u32 * 256;
+pub(crate) const CHUNK_MAXIMUM_BYTES_LENGTH: u32 = REGION_SECTOR_BYTES_LENGTH as u32 * 256;
+
+/// Magic which starts sector preceding backup header at the end of source.
+///
+/// Backup header are the crate own format extension which are ignored by other readers.
+pub(crate) const BACKUP_HEADER_MAGIC: &[u8] = b"anvil-region backup header";
+/// Amount of sectors used by backup header including magic sector.
+const BACKUP_HEADER_SECTORS: u32 = 3;
+
+/// Compression level which are used by default to write chunks.
+pub(crate) const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+
+/// Region operation which requires more source capabilities than available in the caller.
+type RegionFn<S> = fn(&mut Region<S>) -> Result<(), io::Error>;
+/// Reads compression scheme of chunk, requires source to be readable.
+type ChunkCompressionFn<S> =
+    fn(&mut Region<S>, RegionChunkPosition) -> Result<Option<CompressionScheme>, ChunkReadError>;
+
+/// Source owned by region which can be moved out while region are dropped.
+///
+/// Source are always present until `take_source` are called by `Region::into_inner`.
+struct RegionSource<S>(Option<S>);
+
+impl<S> RegionSource<S> {
+    fn new(source: S) -> Self {
+        RegionSource(Some(source))
+    }
+
+    fn take_source(&mut self) -> S {
+        self.0.take().expect("region source already taken")
+    }
+}
+
+impl<S> Deref for RegionSource<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.0.as_ref().expect("region source already taken")
+    }
+}
 
-/// Gzip compression type value.
-const GZIP_COMPRESSION_TYPE: u8 = 1;
-/// Zlib compression type value.
-const ZLIB_COMPRESSION_TYPE: u8 = 2;
+impl<S> DerefMut for RegionSource<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.0.as_mut().expect("region source already taken")
+    }
+}
 
 /// Region represents a 32x32 group of chunks.
 pub struct Region<S> {
     /// Region position in the world.
     position: RegionPosition,
     /// Source in which region are stored.
-    source: S,
+    source: RegionSource<S>,
     /// Array of chunks metadata.
     chunks_metadata: [ChunkMetadata; REGION_CHUNKS],
     /// Used sectors for chunks data.
     used_sectors: BitVec,
+    /// Whether used sectors are computed, region opened with header only computes them lazily.
+    sector_map_loaded: bool,
+    /// Wasted sectors percent after which region are compacted on flush.
+    auto_defrag: Option<(u8, RegionFn<S>)>,
+    /// Flushes region on drop, present if region was changed since the last flush.
+    pending_flush: Option<RegionFn<S>>,
+    /// Synchronizes source with storage between chunk data and header writes.
+    safe_writes: Option<RegionFn<S>>,
+    /// Truncates free sectors at the end of source after chunks are written or removed.
+    truncate_freed_sectors: Option<RegionFn<S>>,
+    /// Whether chunk header entry are checked for changes after reading chunk data.
+    verify_reads: bool,
+    /// Whether chunk coordinates tags are checked to match position on write.
+    verify_write_positions: bool,
+    /// Whether backup header are written at the end of source on flush.
+    backup_header: bool,
+    /// Sector index of backup header magic sector if backup header present.
+    backup_header_sector_index: Option<u32>,
+    /// Log where chunk writes and removals are recorded.
+    event_log: Option<EventLog>,
+    /// Whether rewritten chunks keep their last modified timestamp.
+    preserve_timestamps: bool,
+    /// Whether sectors released by moved or removed chunks are overwritten with zeros.
+    wipe_freed_sectors: bool,
+    /// Compression scheme which are used to write chunks.
+    write_compression_scheme: CompressionScheme,
+    /// Reads compression scheme of chunk being rewritten to reuse it if preservation are enabled.
+    preserve_compression_scheme: Option<ChunkCompressionFn<S>>,
+    /// Compression level from 0 to 9 which are used to write Gzip and Zlib compressed chunks.
+    write_compression_level: u32,
+    /// Buffers which are reused to encode written chunks.
+    chunk_encoder: ChunkEncoder,
+    /// Recorded sector allocation events if tracing are started.
+    #[cfg(feature = "allocation-trace")]
+    allocation_trace: Option<AllocationTrace>,
 }
 
 impl<S> Region<S> {
@@ -43,443 +131,3755 @@ impl<S> Region<S> {
     fn get_metadata(&self, position: &RegionChunkPosition) -> ChunkMetadata {
         self.chunks_metadata[position.metadata_index()]
     }
-}
-
-/// Calculates used sectors.
-fn used_sectors(total_sectors: usize, chunks_metadata: &[ChunkMetadata]) -> BitVec {
-    // First two sectors are used to store metadata.
-    let mut used_sectors = bitvec![0; total_sectors];
 
-    used_sectors.set(0, true);
-    used_sectors.set(1, true);
+    /// Returns sector index from which starts chunk data or `None` if chunk not present.
+    pub(crate) fn chunk_start_sector_index(&self, position: &RegionChunkPosition) -> Option<u32> {
+        let metadata = self.get_metadata(position);
 
-    for metadata in chunks_metadata {
         if metadata.is_empty() {
-            continue;
+            return None;
         }
 
-        let start_index = metadata.start_sector_index as usize;
-        let end_index = start_index + metadata.sectors as usize;
-
-        for index in start_index..end_index {
-            used_sectors.set(index, true);
-        }
+        Some(metadata.start_sector_index)
     }
 
-    used_sectors
-}
-
-/// First 8KB of source are header of 1024 offsets and 1024 timestamps.
-fn read_header<S: Read>(
-    source: &mut S,
-    source_len: u64,
-) -> Result<[ChunkMetadata; REGION_CHUNKS], io::Error> {
-    let mut chunks_metadata = [Default::default(); REGION_CHUNKS];
-
-    if REGION_HEADER_BYTES_LENGTH > source_len {
-        return Ok(chunks_metadata);
+    /// Sets whether chunk header entry are re-read after reading chunk data.
+    ///
+    /// If entry was changed by another process while chunk data were read,
+    /// `ChunkReadError::ConcurrentModification` are returned. Chunks which sectors are
+    /// used by another chunk are also rejected with `ChunkReadError::OverlappingSectors`.
+    pub fn set_verify_reads(&mut self, verify_reads: bool) {
+        self.verify_reads = verify_reads;
     }
 
-    let mut values = [0u32; REGION_CHUNKS_METADATA_LENGTH];
-
-    for index in 0..REGION_CHUNKS_METADATA_LENGTH {
-        values[index] = source.read_u32::<BigEndian>()?;
+    /// Sets whether `write_chunk` and `write_chunks` check that `xPos` and `zPos` tags
+    /// of chunk match position where chunk are written.
+    ///
+    /// The game discards chunks which coordinates doesn't match their place in region,
+    /// on mismatch `ChunkWriteError::PositionMismatch` are returned and chunk are not written.
+    pub fn set_verify_write_positions(&mut self, verify_write_positions: bool) {
+        self.verify_write_positions = verify_write_positions;
     }
 
-    for index in 0..REGION_CHUNKS {
-        let last_modified_timestamp = values[REGION_CHUNKS + index];
-        let offset = values[index];
-
-        let start_sector_index = offset >> 8;
-        let sectors = (offset & 0xFF) as u8;
-
-        let metadata = ChunkMetadata::new(start_sector_index, sectors, last_modified_timestamp);
-        chunks_metadata[index] = metadata;
-    }
+    /// Checks chunk coordinates tags if write positions verification are enabled.
+    fn verify_write_position(
+        &self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: &CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        if !self.verify_write_positions {
+            return Ok(());
+        }
 
-    return Ok(chunks_metadata);
-}
+        let expected = ChunkPosition::from_region_chunk(self.position, position);
+        let found = chunk_tag_coordinates(chunk_compound_tag);
 
-impl<S: Read + Seek> Region<S> {
-    pub fn load(position: RegionPosition, mut source: S) -> Result<Self, io::Error> {
-        let source_len = source.len()?;
-        let chunks_metadata = read_header(&mut source, source_len)?;
+        if found != Some(expected) {
+            return Err(ChunkWriteError::PositionMismatch { expected, found });
+        }
 
-        let total_sectors = if source_len > REGION_HEADER_BYTES_LENGTH {
-            (source_len as usize + (REGION_SECTOR_BYTES_LENGTH as usize - 1))
-                / REGION_SECTOR_BYTES_LENGTH as usize
-        } else {
-            2
-        };
+        Ok(())
+    }
 
-        let used_sectors = used_sectors(total_sectors, &chunks_metadata);
+    /// Returns region position in the world.
+    pub fn position(&self) -> RegionPosition {
+        self.position
+    }
 
-        let region = Region {
-            position,
-            source,
-            chunks_metadata,
-            used_sectors,
-        };
+    /// Returns `true` if chunk present in region.
+    ///
+    /// Only loaded header are checked, chunk data are not read.
+    pub fn contains_chunk(&self, position: RegionChunkPosition) -> bool {
+        !self.get_metadata(&position).is_empty()
+    }
 
-        Ok(region)
+    /// Returns iterator over positions of chunks present in region.
+    ///
+    /// Only loaded header are checked, chunk data are not read.
+    pub fn positions(&self) -> impl Iterator<Item = RegionChunkPosition> + '_ {
+        self.chunks_metadata
+            .iter()
+            .enumerate()
+            .filter(|(_, metadata)| !metadata.is_empty())
+            .map(|(index, _)| RegionChunkPosition::from_metadata_index(index))
     }
 
-    pub fn read_chunk(
-        &mut self,
-        position: RegionChunkPosition,
-    ) -> Result<CompoundTag, ChunkReadError> {
+    /// Returns last time in seconds when chunk was modified or `None` if chunk not present.
+    pub fn chunk_last_modified_timestamp(&self, position: RegionChunkPosition) -> Option<u32> {
         let metadata = self.get_metadata(&position);
 
         if metadata.is_empty() {
-            return Err(ChunkReadError::ChunkNotFound { position });
+            return None;
         }
 
-        let seek_offset = metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
-        let maximum_length = (metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32)
-            .min(CHUNK_MAXIMUM_BYTES_LENGTH);
+        Some(metadata.last_modified_timestamp)
+    }
 
-        self.source.seek(SeekFrom::Start(seek_offset))?;
-        let length = self.source.read_u32::<BigEndian>()?;
+    /// Returns reference to the source.
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
 
-        if length > maximum_length {
-            return Err(ChunkReadError::LengthExceedsMaximum {
-                length,
-                maximum_length,
-            });
+    /// Returns mutable reference to the source.
+    ///
+    /// Loaded header are not updated if source are modified directly,
+    /// so region should be loaded again after such changes.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Flushes changes which was not flushed and returns the source.
+    pub fn into_inner(mut self) -> Result<S, io::Error> {
+        if let Some(flush) = self.pending_flush.take() {
+            flush(&mut self)?;
         }
 
-        let compression_scheme = self.source.read_u8()?;
-        let mut compressed_buffer = vec![0u8; (length - 1) as usize];
-        self.source.read_exact(&mut compressed_buffer)?;
+        // Pending flush are taken, so drop of region without source don't touch it.
+        Ok(self.source.take_source())
+    }
 
-        let mut cursor = Cursor::new(&compressed_buffer);
+    /// Returns `true` if region doesn't contain any chunk.
+    pub fn is_empty(&self) -> bool {
+        self.chunks_metadata.iter().all(ChunkMetadata::is_empty)
+    }
 
-        match compression_scheme {
-            GZIP_COMPRESSION_TYPE => Ok(read_gzip_compound_tag(&mut cursor)?),
-            ZLIB_COMPRESSION_TYPE => Ok(read_zlib_compound_tag(&mut cursor)?),
-            _ => Err(ChunkReadError::UnsupportedCompressionScheme { compression_scheme }),
-        }
+    /// Sets whether backup header are written at the end of source on flush.
+    pub fn set_backup_header(&mut self, backup_header: bool) {
+        self.backup_header = backup_header;
     }
-}
 
-impl<S: Write + Seek> Region<S> {
-    pub fn write_chunk(
+    /// Sets whether `write_chunk` and `write_chunks` keep last modified timestamp
+    /// of chunks which are already present instead of setting current time.
+    pub fn set_preserve_timestamps(&mut self, preserve_timestamps: bool) {
+        self.preserve_timestamps = preserve_timestamps;
+    }
+
+    /// Sets whether sectors released by moved or removed chunks are overwritten with zeros,
+    /// so data of old chunk versions don't remain in source.
+    pub fn set_wipe_freed_sectors(&mut self, wipe_freed_sectors: bool) {
+        self.wipe_freed_sectors = wipe_freed_sectors;
+    }
+
+    /// Returns compression scheme which are used to write chunk.
+    ///
+    /// If preservation are enabled and compression scheme of present chunk can be read,
+    /// it are used instead of region write compression scheme.
+    fn chunk_write_compression_scheme(
         &mut self,
         position: RegionChunkPosition,
-        chunk_compound_tag: CompoundTag,
-    ) -> Result<(), ChunkWriteError> {
-        let mut buffer = Vec::new();
+    ) -> CompressionScheme {
+        let compression_scheme = match self.preserve_compression_scheme {
+            Some(chunk_compression) => chunk_compression(self, position).ok().flatten(),
+            None => None,
+        };
 
-        // If necessary, extend the source length to the length of the header.
-        if REGION_HEADER_BYTES_LENGTH > self.source.len()? {
-            debug!(target: "anvil-region", "Extending source to header length");
-            self.source.extend_len(REGION_HEADER_BYTES_LENGTH)?;
-        }
+        compression_scheme.unwrap_or(self.write_compression_scheme)
+    }
 
-        buffer.write_u8(ZLIB_COMPRESSION_TYPE)?;
-        write_zlib_compound_tag(&mut buffer, &chunk_compound_tag)?;
+    /// Returns last modified timestamp which are written with chunk.
+    fn write_timestamp(&self, position: RegionChunkPosition) -> u32 {
+        let last_modified_timestamp = if self.preserve_timestamps {
+            self.chunk_last_modified_timestamp(position)
+        } else {
+            None
+        };
 
-        // 4 bytes for data length.
-        let length = (buffer.len() + 4) as u32;
+        last_modified_timestamp.unwrap_or_else(current_timestamp)
+    }
 
-        if length > CHUNK_MAXIMUM_BYTES_LENGTH {
-            return Err(ChunkWriteError::LengthExceedsMaximum { length });
-        }
+    /// Sets log where chunk writes and removals are recorded.
+    pub fn set_event_log(&mut self, event_log: EventLog) {
+        self.event_log = Some(event_log);
+    }
 
-        let mut metadata = self.find_place(&position, length)?;
-        let seek_offset = metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+    /// Records chunk change to event log if it set.
+    fn record_event(
+        &mut self,
+        position: RegionChunkPosition,
+        kind: ChunkEventKind,
+    ) -> Result<(), io::Error> {
+        let event_log = match &mut self.event_log {
+            Some(event_log) => event_log,
+            None => return Ok(()),
+        };
 
-        self.source.seek(SeekFrom::Start(seek_offset))?;
-        self.source.write_u32::<BigEndian>(buffer.len() as u32)?;
-        self.source.write_all(&buffer)?;
+        let chunk_position = ChunkPosition::from_region_chunk(self.position, position);
 
-        // Padding to align sector.
-        let padding_len = REGION_SECTOR_BYTES_LENGTH - length as u16 % REGION_SECTOR_BYTES_LENGTH;
+        event_log.record(chunk_position, kind)
+    }
 
-        if padding_len > 0 {
-            self.source.write_all(&vec![0; padding_len as usize])?;
-        }
+    /// Sets compression scheme which are used to write chunks.
+    ///
+    /// By default chunks are written with Zlib compression.
+    pub fn set_write_compression_scheme(&mut self, compression_scheme: CompressionScheme) {
+        self.write_compression_scheme = compression_scheme;
+    }
 
-        metadata.update_last_modified_timestamp();
-        self.update_metadata(&position, metadata)?;
+    /// Sets compression level from 0 to 9 which are used to write Gzip and Zlib
+    /// compressed chunks, higher level are slower but gives smaller data.
+    ///
+    /// By default level 6 are used, levels above 9 are treated as 9.
+    pub fn set_write_compression_level(&mut self, level: u32) {
+        self.write_compression_level = level.min(9);
+    }
 
-        Ok(())
+    /// Starts recording of sector allocation events, previously recorded events are discarded.
+    #[cfg(feature = "allocation-trace")]
+    pub fn start_allocation_trace(&mut self) {
+        self.allocation_trace = Some(AllocationTrace::new());
     }
 
-    /// Finds a place where chunk data of a given length can be put.
+    /// Stops recording of sector allocation events and returns recorded events.
+    #[cfg(feature = "allocation-trace")]
+    pub fn take_allocation_trace(&mut self) -> Option<AllocationTrace> {
+        self.allocation_trace.take()
+    }
+
+    /// Records sector allocation event if tracing are started.
+    #[cfg(feature = "allocation-trace")]
+    fn trace(&mut self, event: AllocationEvent) {
+        if let Some(allocation_trace) = &mut self.allocation_trace {
+            allocation_trace.record(event);
+        }
+    }
+
+    /// Returns `true` if some chunks in header are pointing to overlapping sectors.
     ///
-    /// If cannot find a place to put chunk data will extend source.
-    fn find_place(
-        &mut self,
-        position: &RegionChunkPosition,
-        chunk_length: u32,
-    ) -> Result<ChunkMetadata, io::Error> {
-        let sectors_required = (chunk_length / REGION_SECTOR_BYTES_LENGTH as u32) as u8 + 1;
-        let metadata = self.get_metadata(position);
+    /// Header are checked only, use `validate` to find affected chunks. Overlapping sectors
+    /// are never reused for other chunks, since writing to them would corrupt another chunk.
+    pub fn has_overlapping_sectors(&self) -> bool {
+        sectors_overlap(&self.chunks_metadata)
+    }
 
-        // Chunk still fits in the old place.
-        if metadata.sectors == sectors_required {
-            debug!(
+    /// Logs warning if header contains chunks with overlapping sectors.
+    fn warn_overlapping_sectors(&self) {
+        if self.has_overlapping_sectors() {
+            warn!(
                 target: "anvil-region",
-                "Region x: {}, z: {} chunk x: {}, z: {} with length {} still fits in the old place",
-                self.position.x, self.position.z, position.x, position.z, chunk_length
+                "Region x: {}, z: {} header contains chunks with overlapping sectors",
+                self.position.x, self.position.z
             );
-
-            return Ok(metadata);
         }
+    }
 
-        // Release previously used sectors.
-        for i in 0..metadata.sectors {
-            let sector_index = metadata.start_sector_index as usize + i as usize;
-            self.used_sectors.set(sector_index, false);
-        }
+    /// Returns `true` if chunk sectors overlap sectors of another chunk.
+    fn overlaps_other_chunk(
+        &self,
+        position: &RegionChunkPosition,
+        metadata: ChunkMetadata,
+    ) -> bool {
+        self.overlapping_chunk(position, metadata).is_some()
+    }
 
-        let source_len = self.source.len()?;
-        let total_sectors = source_len / REGION_SECTOR_BYTES_LENGTH as u64;
+    /// Returns position of the first another chunk which sectors overlap chunk sectors.
+    fn overlapping_chunk(
+        &self,
+        position: &RegionChunkPosition,
+        metadata: ChunkMetadata,
+    ) -> Option<RegionChunkPosition> {
+        let start_sector_index = metadata.start_sector_index;
+        let end_sector_index = start_sector_index + metadata.sectors as u32;
+
+        self.chunks_metadata
+            .iter()
+            .enumerate()
+            .filter(|(index, other_metadata)| {
+                *index != position.metadata_index() && !other_metadata.is_empty()
+            })
+            .find(|(_, other_metadata)| {
+                let other_end_sector_index =
+                    other_metadata.start_sector_index + other_metadata.sectors as u32;
+
+                other_metadata.start_sector_index < end_sector_index
+                    && start_sector_index < other_end_sector_index
+            })
+            .map(|(index, _)| RegionChunkPosition::from_metadata_index(index))
+    }
 
-        // Trying to find enough big gap between sectors to put chunk.
-        let mut sectors_free = 0;
+    /// Returns bitmap of chunks present in region.
+    ///
+    /// Bit `x + z * 32` are set when chunk at region chunk position `x`, `z` present,
+    /// bits are counted from the least significant bit of the first word.
+    pub fn presence_bitmap(&self) -> [u64; 16] {
+        presence_bitmap(&self.chunks_metadata)
+    }
 
-        for sector_index in 0..total_sectors {
-            // Sector occupied and we can't place chunk.
-            if self.used_sectors[sector_index as usize] {
-                sectors_free = 0;
-                continue;
-            }
+    /// Returns percent of sectors after header which are not used by chunks.
+    fn wasted_sectors_percent(&self) -> u8 {
+        let data_sectors = self.used_sectors.len().saturating_sub(2);
 
-            debug!(target: "anvil-region", "Sector {} is free", sector_index);
-            sectors_free += 1;
+        if data_sectors == 0 {
+            return 0;
+        }
 
-            // Can put chunk in gap.
-            if sectors_free == sectors_required {
-                let put_sector_index = sector_index as u32 - sectors_free as u32 + 1;
+        (self.used_sectors.count_zeros() * 100 / data_sectors) as u8
+    }
+}
 
-                // Marking new sectors as used.
-                for i in 0..sectors_free {
-                    let sector_index = put_sector_index as usize + i as usize;
-                    self.used_sectors.set(sector_index, true);
-                }
+/// Calculates used sectors.
+pub(crate) fn used_sectors(total_sectors: usize, chunks_metadata: &[ChunkMetadata]) -> BitVec {
+    // First two sectors are used to store metadata.
+    let mut used_sectors = bitvec![0; total_sectors];
 
-                debug!(
-                    target: "anvil-region",
-                    "Region x: {}, z: {} chunk x: {}, z: {} with {} required sectors \
-                    can be placed in free sectors gap between from {} to {}",
-                    self.position.x,
-                    self.position.z,
-                    position.x,
-                    position.z,
-                    sectors_required,
-                    put_sector_index,
-                    sector_index
-                );
+    used_sectors.set(0, true);
+    used_sectors.set(1, true);
 
-                return Ok(ChunkMetadata::new(put_sector_index, sectors_required, 0));
-            }
+    for metadata in chunks_metadata {
+        if metadata.is_empty() {
+            continue;
         }
 
-        // Extending source because cannot find a place to put chunk data.
-        let extend_sectors = sectors_required - sectors_free;
-        let extend_len = (REGION_SECTOR_BYTES_LENGTH * extend_sectors as u16) as u64;
+        let start_index = metadata.start_sector_index as usize;
+        let end_index = start_index + metadata.sectors as usize;
 
-        debug!(
-            target: "anvil-region",
-            "Extending region x: {}, z: {} source for {} bytes to place chunk data",
-            self.position.x,
-            self.position.z,
-            extend_len
-        );
+        for index in start_index..end_index {
+            used_sectors.set(index, true);
+        }
+    }
+
+    used_sectors
+}
 
-        self.source.extend_len(source_len + extend_len)?;
+/// Returns amount of sectors required to store chunk data of a given length with padding.
+pub(crate) fn sectors_required(chunk_length: u32) -> Result<u8, io::Error> {
+    u8::try_from(chunk_length / REGION_SECTOR_BYTES_LENGTH as u32 + 1).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("chunk length {} doesn't fit into 255 sectors", chunk_length),
+        )
+    })
+}
 
-        // Mark new sectors as used.
-        for _ in 0..extend_sectors {
-            self.used_sectors.push(true);
+/// Finds the first gap of free sectors which fits chunk and marks it as used.
+///
+/// If there is no such gap chunk are placed at the end, reusing free sectors before it.
+/// Returns start sector index of chunk and amount of sectors appended to the bitmap,
+/// caller must extend source to the end of chunk.
+pub(crate) fn allocate_sectors(used_sectors: &mut BitVec, sectors_required: u8) -> (u32, u8) {
+    let mut sectors_free = 0;
+    let mut gap_start_sector_index = None;
+
+    for sector_index in 0..used_sectors.len() {
+        // Sector occupied and we can't place chunk.
+        if used_sectors[sector_index] {
+            sectors_free = 0;
+            continue;
         }
 
-        return Ok(ChunkMetadata::new(
-            total_sectors as u32 - sectors_free as u32,
-            sectors_required,
-            0,
-        ));
-    }
+        sectors_free += 1;
 
-    /// Updates chunk metadata.
-    fn update_metadata(
-        &mut self,
-        position: &RegionChunkPosition,
-        metadata: ChunkMetadata,
-    ) -> Result<(), io::Error> {
-        let metadata_index = position.metadata_index();
-        self.chunks_metadata[metadata_index] = metadata;
+        // Can put chunk in gap.
+        if sectors_free == sectors_required {
+            gap_start_sector_index = Some(sector_index + 1 - sectors_free as usize);
+            break;
+        }
+    }
 
-        let start_seek_offset = SeekFrom::Start((metadata_index * 4) as u64);
-        let offset = (metadata.start_sector_index << 8) | metadata.sectors as u32;
+    let start_sector_index =
+        gap_start_sector_index.unwrap_or(used_sectors.len() - sectors_free as usize);
+    let end_sector_index = start_sector_index + sectors_required as usize;
+    let extend_sectors = end_sector_index.saturating_sub(used_sectors.len());
 
-        self.source.seek(start_seek_offset)?;
-        self.source.write_u32::<BigEndian>(offset)?;
+    if extend_sectors > 0 {
+        used_sectors.resize(end_sector_index, false);
+    }
 
-        let next_seek_offset = SeekFrom::Current(REGION_SECTOR_BYTES_LENGTH as i64 - 4);
-        let last_modified_timestamp = metadata.last_modified_timestamp;
+    for sector_index in start_sector_index..end_sector_index {
+        used_sectors.set(sector_index, true);
+    }
 
-        self.source.seek(next_seek_offset)?;
-        self.source
-            .write_u32::<BigEndian>(last_modified_timestamp)?;
+    (start_sector_index as u32, extend_sectors as u8)
+}
 
-        Ok(())
+/// Marks chunk sectors as free.
+///
+/// Sectors which are also used by another chunk because of corrupted header are kept used.
+pub(crate) fn free_sectors(
+    used_sectors: &mut BitVec,
+    chunks_metadata: &[ChunkMetadata],
+    metadata_index: usize,
+    metadata: ChunkMetadata,
+) {
+    let start_sector_index = metadata.start_sector_index as usize;
+    let end_sector_index = start_sector_index + metadata.sectors as usize;
+
+    for sector_index in start_sector_index..end_sector_index.min(used_sectors.len()) {
+        used_sectors.set(sector_index, false);
     }
-}
 
-impl<S: Read + Seek> IntoIterator for Region<S> {
-    type Item = <RegionIterator<S> as Iterator>::Item;
-    type IntoIter = RegionIterator<S>;
+    for (index, other_metadata) in chunks_metadata.iter().enumerate() {
+        if index == metadata_index || other_metadata.is_empty() {
+            continue;
+        }
 
-    fn into_iter(self) -> Self::IntoIter {
-        RegionIterator {
-            inner: self,
-            current: 0,
+        let other_start_sector_index = other_metadata.start_sector_index as usize;
+        let other_end_sector_index = other_start_sector_index + other_metadata.sectors as usize;
+        let shared_start_sector_index = start_sector_index.max(other_start_sector_index);
+        let shared_end_sector_index = end_sector_index
+            .min(other_end_sector_index)
+            .min(used_sectors.len());
+
+        for sector_index in shared_start_sector_index..shared_end_sector_index {
+            used_sectors.set(sector_index, true);
         }
     }
 }
 
-pub struct RegionIterator<S: Read + Seek> {
-    inner: Region<S>,
-    current: usize,
-}
-
-impl<S: Read + Seek> Iterator for RegionIterator<S> {
-    type Item = CompoundTag;
+/// Calculates bitmap of present chunks, see `Region::presence_bitmap` for layout.
+fn presence_bitmap(chunks_metadata: &[ChunkMetadata]) -> [u64; 16] {
+    let mut bitmap = [0u64; 16];
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current == REGION_CHUNKS {
-            return None;
+    for (index, metadata) in chunks_metadata.iter().enumerate() {
+        if !metadata.is_empty() {
+            bitmap[index / 64] |= 1 << (index % 64);
         }
+    }
 
-        let x = self.current % 32;
-        let z = self.current / 32;
-
-        self.current += 1;
+    bitmap
+}
 
-        let pos = RegionChunkPosition::new(x as u8, z as u8);
-        match self.inner.read_chunk(pos) {
-            Ok(chunk) => Some(chunk),
-            Err(_) => self.next(),
-        }
+/// Calculates total sectors of source with specified length.
+pub(crate) fn total_sectors(source_len: u64) -> usize {
+    if source_len > REGION_HEADER_BYTES_LENGTH {
+        (source_len as usize).div_ceil(REGION_SECTOR_BYTES_LENGTH as usize)
+    } else {
+        2
     }
 }
 
-/// Chunk metadata are stored in header.
-#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
-struct ChunkMetadata {
-    /// Sector index from which starts chunk data.
-    start_sector_index: u32,
-    /// Amount of sectors used to store chunk.
-    sectors: u8,
-    /// Last time in seconds when chunk was modified.
-    last_modified_timestamp: u32,
+/// First 8KB of source are header of 1024 offsets and 1024 timestamps.
+pub(crate) fn read_header<S: Read>(
+    source: &mut S,
+    source_len: u64,
+) -> Result<[ChunkMetadata; REGION_CHUNKS], io::Error> {
+    let mut chunks_metadata = [Default::default(); REGION_CHUNKS];
+
+    if REGION_HEADER_BYTES_LENGTH > source_len {
+        return Ok(chunks_metadata);
+    }
+
+    // Header are read with a single call instead of a call for every value,
+    // which matters for unbuffered sources such as `File`.
+    let mut buffer = [0u8; REGION_HEADER_BYTES_LENGTH as usize];
+    source.read_exact(&mut buffer)?;
+
+    let mut values = [0u32; REGION_CHUNKS_METADATA_LENGTH];
+    BigEndian::read_u32_into(&buffer, &mut values);
+
+    for index in 0..REGION_CHUNKS {
+        let last_modified_timestamp = values[REGION_CHUNKS + index];
+        let offset = values[index];
+
+        let start_sector_index = offset >> 8;
+        let sectors = (offset & 0xFF) as u8;
+
+        let metadata = ChunkMetadata::new(start_sector_index, sectors, last_modified_timestamp);
+        chunks_metadata[index] = metadata;
+    }
+
+    Ok(chunks_metadata)
 }
 
-impl ChunkMetadata {
-    fn new(start_sector_index: u32, sectors: u8, last_modified_timestamp: u32) -> Self {
-        ChunkMetadata {
-            start_sector_index,
-            sectors,
-            last_modified_timestamp,
+/// Returns `true` if some chunks metadata are pointing to overlapping sectors.
+fn sectors_overlap(chunks_metadata: &[ChunkMetadata]) -> bool {
+    let mut sector_ranges: Vec<(u32, u32)> = chunks_metadata
+        .iter()
+        .filter(|metadata| !metadata.is_empty())
+        .map(|metadata| {
+            let start_sector_index = metadata.start_sector_index;
+            (
+                start_sector_index,
+                start_sector_index + metadata.sectors as u32,
+            )
+        })
+        .collect();
+
+    sector_ranges.sort_unstable();
+
+    // Sorted by start, so any overlap includes overlap of adjacent ranges.
+    sector_ranges
+        .windows(2)
+        .any(|ranges| ranges[1].0 < ranges[0].1)
+}
+
+/// Serializes chunks metadata to header bytes.
+pub(crate) fn header_bytes(chunks_metadata: &[ChunkMetadata]) -> Result<Vec<u8>, io::Error> {
+    let mut buffer = Vec::with_capacity(REGION_HEADER_BYTES_LENGTH as usize);
+
+    for metadata in chunks_metadata {
+        let offset = (metadata.start_sector_index << 8) | metadata.sectors as u32;
+        buffer.write_u32::<BigEndian>(offset)?;
+    }
+
+    for metadata in chunks_metadata {
+        buffer.write_u32::<BigEndian>(metadata.last_modified_timestamp)?;
+    }
+
+    Ok(buffer)
+}
+
+/// Returns magic sector index of backup header if it present at the end of source.
+pub(crate) fn find_backup_header<S: Read + Seek>(
+    source: &mut S,
+    source_len: u64,
+) -> Result<Option<u32>, io::Error> {
+    let sector_index = match backup_header_sector_index(source_len) {
+        Some(sector_index) => sector_index,
+        None => return Ok(None),
+    };
+
+    let mut magic = vec![0; BACKUP_HEADER_MAGIC.len()];
+
+    source.seek(SeekFrom::Start(
+        sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64,
+    ))?;
+    source.read_exact(&mut magic)?;
+
+    if magic != BACKUP_HEADER_MAGIC {
+        return Ok(None);
+    }
+
+    Ok(Some(sector_index))
+}
+
+/// Returns sector index where backup header starts if source of this length can contain it.
+pub(crate) fn backup_header_sector_index(source_len: u64) -> Option<u32> {
+    let sector_len = REGION_SECTOR_BYTES_LENGTH as u64;
+    let backup_len = BACKUP_HEADER_SECTORS as u64 * sector_len;
+
+    if !source_len.is_multiple_of(sector_len)
+        || REGION_HEADER_BYTES_LENGTH + backup_len > source_len
+    {
+        return None;
+    }
+
+    Some(((source_len - backup_len) / sector_len) as u32)
+}
+
+/// Checks that all chunks are placed after header inside data sectors and don't overlap.
+fn is_valid_header(chunks_metadata: &[ChunkMetadata], data_sectors: usize) -> bool {
+    let mut used_sectors = bitvec![0; data_sectors];
+
+    for metadata in chunks_metadata
+        .iter()
+        .filter(|metadata| !metadata.is_empty())
+    {
+        let start_index = metadata.start_sector_index as usize;
+        let end_index = start_index + metadata.sectors as usize;
+
+        if start_index < 2 || end_index > data_sectors {
+            return false;
+        }
+
+        for index in start_index..end_index {
+            if used_sectors[index] {
+                return false;
+            }
+
+            used_sectors.set(index, true);
+        }
+    }
+
+    true
+}
+
+/// Returns chunk position from `xPos` and `zPos` tags, which are stored in `Level`
+/// compound tag before 1.18.
+///
+/// Entities chunks stores position in `Position` array of x and z instead.
+fn chunk_tag_coordinates(compound_tag: &CompoundTag) -> Option<ChunkPosition> {
+    if let Ok([x, z]) = compound_tag.get_i32_vec("Position").map(Vec::as_slice) {
+        return Some(ChunkPosition::new(*x, *z));
+    }
+
+    let data_compound_tag = compound_tag
+        .get_compound_tag("Level")
+        .unwrap_or(compound_tag);
+
+    let x = data_compound_tag.get_i32("xPos").ok()?;
+    let z = data_compound_tag.get_i32("zPos").ok()?;
+
+    Some(ChunkPosition::new(x, z))
+}
+
+/// Decodes compressed chunk data to compound tag.
+pub(crate) fn decode_chunk(
+    compression_scheme: u8,
+    compressed_buffer: &[u8],
+) -> Result<CompoundTag, ChunkReadError> {
+    let mut cursor = Cursor::new(compressed_buffer);
+
+    match CompressionScheme::from_id(compression_scheme) {
+        Some(CompressionScheme::Gzip) => Ok(read_gzip_compound_tag(&mut cursor)?),
+        Some(CompressionScheme::Zlib) => Ok(read_zlib_compound_tag(&mut cursor)?),
+        Some(CompressionScheme::Uncompressed) => Ok(read_compound_tag(&mut cursor)?),
+        #[cfg(feature = "lz4")]
+        Some(CompressionScheme::Lz4) => {
+            let buffer = crate::lz4::decompress(compressed_buffer)?;
+            Ok(read_compound_tag(&mut Cursor::new(buffer))?)
+        }
+        None => Err(ChunkReadError::UnsupportedCompressionScheme {
+            compression_scheme,
+            region: None,
+            position: None,
+        }),
+    }
+}
+
+impl<S: Read + Seek> Region<S> {
+    pub fn load(position: RegionPosition, mut source: S) -> Result<Self, io::Error> {
+        let source_len = source.len()?;
+        let chunks_metadata = read_header(&mut source, source_len)?;
+        let used_sectors = used_sectors(total_sectors(source_len), &chunks_metadata);
+        let backup_header_sector_index = find_backup_header(&mut source, source_len)?;
+
+        let region = Region {
+            position,
+            source: RegionSource::new(source),
+            chunks_metadata,
+            used_sectors,
+            sector_map_loaded: true,
+            auto_defrag: None,
+            pending_flush: None,
+            safe_writes: None,
+            truncate_freed_sectors: None,
+            verify_reads: false,
+            verify_write_positions: false,
+            backup_header: false,
+            backup_header_sector_index,
+            event_log: None,
+            preserve_timestamps: false,
+            wipe_freed_sectors: false,
+            write_compression_scheme: CompressionScheme::Zlib,
+            preserve_compression_scheme: None,
+            write_compression_level: DEFAULT_COMPRESSION_LEVEL,
+            chunk_encoder: ChunkEncoder::default(),
+            #[cfg(feature = "allocation-trace")]
+            allocation_trace: None,
+        };
+
+        region.warn_overlapping_sectors();
+
+        Ok(region)
+    }
+
+    /// Opens region reading only header.
+    ///
+    /// Unlike `load` source length are not queried and backup header are not searched,
+    /// used sectors are computed before the first chunk write or remove.
+    /// Useful for tools which peeks at few chunks of many regions.
+    pub fn open_header_only(position: RegionPosition, mut source: S) -> Result<Self, io::Error> {
+        let mut buffer = Vec::with_capacity(REGION_HEADER_BYTES_LENGTH as usize);
+        (&mut source)
+            .take(REGION_HEADER_BYTES_LENGTH)
+            .read_to_end(&mut buffer)?;
+
+        let chunks_metadata = read_header(&mut buffer.as_slice(), buffer.len() as u64)?;
+
+        let region = Region {
+            position,
+            source: RegionSource::new(source),
+            chunks_metadata,
+            used_sectors: BitVec::new(),
+            sector_map_loaded: false,
+            auto_defrag: None,
+            pending_flush: None,
+            safe_writes: None,
+            truncate_freed_sectors: None,
+            verify_reads: false,
+            verify_write_positions: false,
+            backup_header: false,
+            backup_header_sector_index: None,
+            event_log: None,
+            preserve_timestamps: false,
+            wipe_freed_sectors: false,
+            write_compression_scheme: CompressionScheme::Zlib,
+            preserve_compression_scheme: None,
+            write_compression_level: DEFAULT_COMPRESSION_LEVEL,
+            chunk_encoder: ChunkEncoder::default(),
+            #[cfg(feature = "allocation-trace")]
+            allocation_trace: None,
+        };
+
+        Ok(region)
+    }
+
+    /// Loads region with enabled backup header.
+    ///
+    /// If primary header are corrupted and valid backup header present at the end
+    /// of source, chunks metadata are recovered from backup header.
+    pub fn load_with_backup_header(
+        position: RegionPosition,
+        mut source: S,
+    ) -> Result<Self, io::Error> {
+        let source_len = source.len()?;
+        let mut chunks_metadata = read_header(&mut source, source_len)?;
+        let backup_header_sector_index = find_backup_header(&mut source, source_len)?;
+
+        if let Some(sector_index) = backup_header_sector_index {
+            let data_sectors = sector_index as usize;
+
+            if !is_valid_header(&chunks_metadata, data_sectors) {
+                let seek_offset = (sector_index as u64 + 1) * REGION_SECTOR_BYTES_LENGTH as u64;
+
+                source.seek(SeekFrom::Start(seek_offset))?;
+                let backup_chunks_metadata = read_header(&mut source, REGION_HEADER_BYTES_LENGTH)?;
+
+                if is_valid_header(&backup_chunks_metadata, data_sectors) {
+                    debug!(
+                        target: "anvil-region",
+                        "Region x: {}, z: {} header are corrupted, recovering from backup header",
+                        position.x, position.z
+                    );
+
+                    chunks_metadata = backup_chunks_metadata;
+                }
+            }
+        }
+
+        let used_sectors = used_sectors(total_sectors(source_len), &chunks_metadata);
+
+        let region = Region {
+            position,
+            source: RegionSource::new(source),
+            chunks_metadata,
+            used_sectors,
+            sector_map_loaded: true,
+            auto_defrag: None,
+            pending_flush: None,
+            safe_writes: None,
+            truncate_freed_sectors: None,
+            verify_reads: false,
+            verify_write_positions: false,
+            backup_header: true,
+            backup_header_sector_index,
+            event_log: None,
+            preserve_timestamps: false,
+            wipe_freed_sectors: false,
+            write_compression_scheme: CompressionScheme::Zlib,
+            preserve_compression_scheme: None,
+            write_compression_level: DEFAULT_COMPRESSION_LEVEL,
+            chunk_encoder: ChunkEncoder::default(),
+            #[cfg(feature = "allocation-trace")]
+            allocation_trace: None,
+        };
+
+        region.warn_overlapping_sectors();
+
+        Ok(region)
+    }
+
+    pub fn read_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<CompoundTag, ChunkReadError> {
+        self.read_chunk_into(position, &mut Vec::new())
+    }
+
+    /// Reads chunk using buffer for compressed chunk data.
+    ///
+    /// Buffer content are replaced, but its capacity are reused, so reading many chunks
+    /// with the same buffer avoids allocation of compressed data for every chunk.
+    pub fn read_chunk_into(
+        &mut self,
+        position: RegionChunkPosition,
+        compressed_buffer: &mut Vec<u8>,
+    ) -> Result<CompoundTag, ChunkReadError> {
+        let compression_scheme = self.read_compressed_chunk_into(position, compressed_buffer)?;
+
+        decode_chunk(compression_scheme, compressed_buffer)
+            .map_err(|read_error| read_error.with_context(self.position, Some(position)))
+    }
+
+    /// Reads multiple chunks.
+    ///
+    /// Chunks are read in the order of their place in source to minimize seeking,
+    /// results are returned in the order of positions.
+    pub fn read_chunks(
+        &mut self,
+        positions: &[RegionChunkPosition],
+    ) -> Vec<Result<CompoundTag, ChunkReadError>> {
+        let mut indexes: Vec<usize> = (0..positions.len()).collect();
+        indexes.sort_by_key(|index| self.get_metadata(&positions[*index]).start_sector_index);
+
+        let mut results: Vec<_> = positions.iter().map(|_| None).collect();
+        let mut compressed_buffer = Vec::new();
+
+        for index in indexes {
+            let result = self.read_chunk_into(positions[index], &mut compressed_buffer);
+            results[index] = Some(result);
+        }
+
+        results.into_iter().flatten().collect()
+    }
+
+    /// Reads compression scheme and compressed chunk data without decoding.
+    ///
+    /// Data can be written back with `write_chunk_raw` or decoded with another NBT library.
+    pub fn read_chunk_raw(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<(CompressionScheme, Vec<u8>), ChunkReadError> {
+        let (compression_scheme, compressed_buffer) = self.read_compressed_chunk(position)?;
+
+        match CompressionScheme::from_id(compression_scheme) {
+            Some(compression_scheme) => Ok((compression_scheme, compressed_buffer)),
+            None => Err(ChunkReadError::UnsupportedCompressionScheme {
+                compression_scheme,
+                region: Some(self.position),
+                position: Some(position),
+            }),
+        }
+    }
+
+    /// Reads compression scheme type id and compressed chunk data without decoding.
+    pub(crate) fn read_compressed_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<(u8, Vec<u8>), ChunkReadError> {
+        let mut compressed_buffer = Vec::new();
+        let compression_scheme =
+            self.read_compressed_chunk_into(position, &mut compressed_buffer)?;
+
+        Ok((compression_scheme, compressed_buffer))
+    }
+
+    /// Reads compressed chunk data into buffer and returns compression scheme type id.
+    ///
+    /// Errors contain position of region and chunk.
+    fn read_compressed_chunk_into(
+        &mut self,
+        position: RegionChunkPosition,
+        compressed_buffer: &mut Vec<u8>,
+    ) -> Result<u8, ChunkReadError> {
+        self.read_compressed_chunk_data_into(position, compressed_buffer)
+            .map_err(|read_error| read_error.with_context(self.position, Some(position)))
+    }
+
+    fn read_compressed_chunk_data_into(
+        &mut self,
+        position: RegionChunkPosition,
+        compressed_buffer: &mut Vec<u8>,
+    ) -> Result<u8, ChunkReadError> {
+        let (length, compression_scheme) = self.read_chunk_prefix(position)?;
+
+        compressed_buffer.clear();
+        compressed_buffer.resize((length - 1) as usize, 0);
+        self.source.read_exact(compressed_buffer)?;
+
+        if self.verify_reads {
+            self.verify_metadata(position)?;
+        }
+
+        Ok(compression_scheme)
+    }
+
+    /// Returns iterator over chunks present in region.
+    ///
+    /// Chunks which are not present are skipped, errors of reading present chunks are returned.
+    pub fn chunks(&mut self) -> Chunks<'_, S> {
+        Chunks {
+            region: self,
+            current: 0,
+            compressed_buffer: Vec::new(),
+        }
+    }
+
+    /// Returns iterator over chunks present in region which skips chunks that cannot be read.
+    pub fn readable_chunks(
+        &mut self,
+    ) -> impl Iterator<Item = (RegionChunkPosition, CompoundTag)> + '_ {
+        self.chunks().filter_map(Result::ok)
+    }
+
+    /// Returns compression scheme of chunk or `None` if chunk not present.
+    ///
+    /// Only chunk data prefix are read, chunk data are not decompressed.
+    pub fn chunk_compression(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<Option<CompressionScheme>, ChunkReadError> {
+        let compression_scheme = match self.read_chunk_prefix(position) {
+            Ok((_, compression_scheme)) => compression_scheme,
+            Err(ChunkReadError::ChunkNotFound { .. }) => return Ok(None),
+            Err(error) => return Err(error.with_context(self.position, Some(position))),
+        };
+
+        match CompressionScheme::from_id(compression_scheme) {
+            Some(compression_scheme) => Ok(Some(compression_scheme)),
+            None => Err(ChunkReadError::UnsupportedCompressionScheme {
+                compression_scheme,
+                region: Some(self.position),
+                position: Some(position),
+            }),
+        }
+    }
+
+    /// Checks that chunk header entry in source matches loaded chunk metadata.
+    fn verify_metadata(&mut self, position: RegionChunkPosition) -> Result<(), ChunkReadError> {
+        let metadata_index = position.metadata_index();
+        let metadata = self.chunks_metadata[metadata_index];
+
+        self.source
+            .seek(SeekFrom::Start((metadata_index * 4) as u64))?;
+        let offset = self.source.read_u32::<BigEndian>()?;
+
+        self.source
+            .seek(SeekFrom::Current(REGION_SECTOR_BYTES_LENGTH as i64 - 4))?;
+        let last_modified_timestamp = self.source.read_u32::<BigEndian>()?;
+
+        let source_metadata =
+            ChunkMetadata::new(offset >> 8, (offset & 0xFF) as u8, last_modified_timestamp);
+
+        if source_metadata != metadata {
+            return Err(ChunkReadError::ConcurrentModification {
+                position,
+                region: Some(self.position),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Validates region integrity.
+    ///
+    /// Checks that chunks are placed after header and inside source, chunks sectors
+    /// don't overlap, chunks declared length are not zero and fits into their sectors
+    /// and chunks compression scheme are known.
+    pub fn validate(&mut self) -> Result<ValidationReport, io::Error> {
+        let mut report = ValidationReport::default();
+
+        let total_sectors = total_sectors(self.source.len()?);
+        let mut sectors_owners: Vec<Option<usize>> = vec![None; total_sectors];
+
+        for index in 0..REGION_CHUNKS {
+            let metadata = self.chunks_metadata[index];
+            let position = RegionChunkPosition::from_metadata_index(index);
+
+            if metadata.is_empty() {
+                continue;
+            }
+
+            let start_sector_index = metadata.start_sector_index;
+            let end_sector_index = start_sector_index + metadata.sectors as u32;
+
+            if start_sector_index < 2 {
+                report.push(ValidationIssue::OffsetInsideHeader {
+                    position,
+                    start_sector_index,
+                });
+
+                continue;
+            }
+
+            if end_sector_index as usize > total_sectors {
+                report.push(ValidationIssue::SectorsBeyondEndOfSource {
+                    position,
+                    end_sector_index,
+                    total_sectors: total_sectors as u32,
+                });
+
+                continue;
+            }
+
+            for sector_index in start_sector_index..end_sector_index {
+                let owner = &mut sectors_owners[sector_index as usize];
+
+                if let Some(other_index) = *owner {
+                    let other_position = RegionChunkPosition::from_metadata_index(other_index);
+
+                    report.push(ValidationIssue::OverlappingSectors {
+                        position,
+                        other_position,
+                    });
+
+                    break;
+                }
+
+                *owner = Some(index);
+            }
+
+            let seek_offset = start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+
+            // 4 bytes for data length.
+            let length = self.source.read_u32::<BigEndian>()?;
+            let maximum_length = metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32 - 4;
+
+            if length == 0 {
+                report.push(ValidationIssue::ZeroLength { position });
+
+                continue;
+            }
+
+            if length > maximum_length {
+                report.push(ValidationIssue::LengthExceedsSectors {
+                    position,
+                    length,
+                    maximum_length,
+                });
+
+                continue;
+            }
+
+            let compression_scheme = self.source.read_u8()?;
+
+            if CompressionScheme::from_id(compression_scheme).is_none() {
+                report.push(ValidationIssue::UnsupportedCompressionScheme {
+                    position,
+                    compression_scheme,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Returns usage statistics of region.
+    ///
+    /// Length prefix of every chunk are read to calculate padding.
+    pub fn stats(&mut self) -> Result<RegionStats, io::Error> {
+        let file_size = self.source.len()?;
+        let used_sectors = used_sectors(total_sectors(file_size), &self.chunks_metadata);
+
+        let mut chunks = 0;
+        let mut wasted_bytes = 0;
+
+        for metadata in self.chunks_metadata.iter() {
+            if metadata.is_empty() {
+                continue;
+            }
+
+            let seek_offset =
+                metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+
+            // 4 bytes for data length.
+            let length = self.source.read_u32::<BigEndian>()? as u64 + 4;
+            let sectors_length = metadata.sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+            chunks += 1;
+            wasted_bytes += sectors_length.saturating_sub(length);
+        }
+
+        // First two sectors are used to store metadata.
+        let data_sectors = &used_sectors[2.min(used_sectors.len())..];
+        let used_count = data_sectors.count_ones();
+        let free_count = data_sectors.count_zeros();
+
+        let fragmentation = match data_sectors.iter().rposition(|used| *used) {
+            Some(last_index) => {
+                let gaps = data_sectors[..last_index].count_zeros();
+                gaps as f64 / (last_index + 1) as f64
+            }
+            None => 0.0,
+        };
+
+        Ok(RegionStats {
+            chunks,
+            used_sectors: used_count as u32,
+            free_sectors: free_count as u32,
+            file_size,
+            wasted_bytes,
+            fragmentation,
+        })
+    }
+
+    /// Scans all sectors of source and extracts every chunk which can be decoded.
+    ///
+    /// Chunks referenced by header are preferred, data found in unreferenced sectors
+    /// are used for chunks which header entry are missing or points to damaged data.
+    /// Position of unreferenced chunk are taken from `xPos` and `zPos` tags.
+    ///
+    /// Chunks are returned in header order, chunk which has header entry but cannot be
+    /// recovered are returned as error.
+    pub fn salvage(
+        &mut self,
+    ) -> Result<
+        impl Iterator<Item = Result<(RegionChunkPosition, CompoundTag), ChunkReadError>>,
+        io::Error,
+    > {
+        let source_len = self.source.len()?;
+        let total_sectors = total_sectors(source_len) as u32;
+
+        let mut referenced_positions = BTreeMap::new();
+
+        for index in 0..REGION_CHUNKS {
+            let metadata = self.chunks_metadata[index];
+
+            if !metadata.is_empty() {
+                let position = RegionChunkPosition::from_metadata_index(index);
+                referenced_positions.insert(metadata.start_sector_index, position);
+            }
+        }
+
+        let mut referenced_chunks = BTreeMap::new();
+        let mut unreferenced_chunks = BTreeMap::new();
+        let mut sector_index = 2;
+
+        while sector_index < total_sectors {
+            let result = self.read_chunk_at(sector_index, source_len);
+
+            let sectors = match &result {
+                Ok((sectors, _)) => *sectors,
+                Err(_) => 1,
+            };
+
+            match referenced_positions.get(&sector_index) {
+                Some(position) => {
+                    let result =
+                        result
+                            .map(|(_, compound_tag)| compound_tag)
+                            .map_err(|read_error| {
+                                read_error.with_context(self.position, Some(*position))
+                            });
+                    referenced_chunks.insert(*position, result);
+                }
+                None => {
+                    if let Ok((_, compound_tag)) = result {
+                        if let Some(position) = self.chunk_tag_position(&compound_tag) {
+                            unreferenced_chunks.entry(position).or_insert(compound_tag);
+                        }
+                    }
+                }
+            }
+
+            sector_index += sectors;
+        }
+
+        let mut chunks = Vec::new();
+
+        for index in 0..REGION_CHUNKS {
+            let position = RegionChunkPosition::from_metadata_index(index);
+
+            let result = match referenced_chunks.remove(&position) {
+                Some(Ok(compound_tag)) => Ok(compound_tag),
+                Some(Err(error)) => unreferenced_chunks.remove(&position).ok_or(error),
+                // Header entry points inside header or beyond the end of source.
+                None if self.contains_chunk(position) => unreferenced_chunks
+                    .remove(&position)
+                    .map_or_else(|| self.read_chunk(position), Ok),
+                None => match unreferenced_chunks.remove(&position) {
+                    Some(compound_tag) => Ok(compound_tag),
+                    None => continue,
+                },
+            };
+
+            chunks.push(result.map(|compound_tag| (position, compound_tag)));
+        }
+
+        Ok(chunks.into_iter())
+    }
+
+    /// Reads and decodes chunk which starts at specified sector without using header.
+    ///
+    /// Returns amount of sectors used by chunk and chunk compound tag.
+    fn read_chunk_at(
+        &mut self,
+        sector_index: u32,
+        source_len: u64,
+    ) -> Result<(u32, CompoundTag), ChunkReadError> {
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        let maximum_length =
+            (source_len.saturating_sub(seek_offset + 4) as u32).min(CHUNK_MAXIMUM_BYTES_LENGTH);
+
+        self.source.seek(SeekFrom::Start(seek_offset))?;
+        let length = self.source.read_u32::<BigEndian>()?;
+
+        if length == 0 {
+            return Err(ChunkReadError::ZeroLength {
+                region: Some(self.position),
+                position: None,
+            });
+        }
+
+        if length > maximum_length {
+            return Err(ChunkReadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+                region: Some(self.position),
+                position: None,
+            });
+        }
+
+        let compression_scheme = self.source.read_u8()?;
+
+        let mut compressed_buffer = vec![0u8; (length - 1) as usize];
+        self.source.read_exact(&mut compressed_buffer)?;
+
+        let compound_tag = decode_chunk(compression_scheme, &compressed_buffer)
+            .map_err(|read_error| read_error.with_context(self.position, None))?;
+        let sectors = (length + 4).div_ceil(REGION_SECTOR_BYTES_LENGTH as u32);
+
+        Ok((sectors, compound_tag))
+    }
+
+    /// Returns chunk position inside region from chunk coordinates tags.
+    ///
+    /// Chunks which coordinates belongs to another region are ignored.
+    fn chunk_tag_position(&self, compound_tag: &CompoundTag) -> Option<RegionChunkPosition> {
+        let chunk_position = chunk_tag_coordinates(compound_tag)?;
+
+        if chunk_position.region() != self.position {
+            return None;
+        }
+
+        Some(chunk_position.region_chunk())
+    }
+
+    /// Reads chunk data length and compression scheme type id.
+    ///
+    /// Source are left positioned at the start of compressed chunk data.
+    fn read_chunk_prefix(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<(u32, u8), ChunkReadError> {
+        let metadata = self.get_metadata(&position);
+        let start_sector_index = metadata.start_sector_index;
+
+        if metadata.is_empty() && start_sector_index != 0 {
+            return Err(ChunkReadError::ZeroSectorsCount {
+                position,
+                start_sector_index,
+                region: Some(self.position),
+            });
+        }
+
+        if metadata.is_empty() {
+            return Err(ChunkReadError::ChunkNotFound {
+                position,
+                region: Some(self.position),
+            });
+        }
+
+        // Scanning every header entry are too expensive for every read, `validate` reports
+        // overlapping sectors of all chunks at once.
+        if self.verify_reads {
+            if let Some(other_position) = self.overlapping_chunk(&position, metadata) {
+                return Err(ChunkReadError::OverlappingSectors {
+                    position,
+                    other_position,
+                    region: Some(self.position),
+                });
+            }
+        }
+
+        let seek_offset = start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        let maximum_length = (metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32)
+            .min(CHUNK_MAXIMUM_BYTES_LENGTH);
+
+        self.source.seek(SeekFrom::Start(seek_offset))?;
+
+        let length = match self.source.read_u32::<BigEndian>() {
+            Ok(length) => length,
+            Err(io_error) if io_error.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(ChunkReadError::OffsetPastEnd {
+                    position,
+                    start_sector_index,
+                    region: Some(self.position),
+                });
+            }
+            Err(io_error) => return Err(io_error.into()),
+        };
+
+        if length == 0 {
+            return Err(ChunkReadError::ZeroLength {
+                region: Some(self.position),
+                position: Some(position),
+            });
+        }
+
+        if length > maximum_length {
+            return Err(ChunkReadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+                region: Some(self.position),
+                position: Some(position),
+            });
+        }
+
+        let compression_scheme = self.source.read_u8()?;
+
+        Ok((length, compression_scheme))
+    }
+}
+
+impl<S: Write + Seek> Region<S> {
+    pub fn write_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        let last_modified_timestamp = self.write_timestamp(position);
+
+        self.write_chunk_with_timestamp(position, chunk_compound_tag, last_modified_timestamp)
+    }
+
+    /// Writes chunk with specified last modified timestamp in seconds.
+    pub fn write_chunk_with_timestamp(
+        &mut self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: CompoundTag,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkWriteError> {
+        self.encode_chunk(position, chunk_compound_tag, last_modified_timestamp)
+            .map_err(|write_error| write_error.with_context(self.position, Some(position)))
+    }
+
+    fn encode_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: CompoundTag,
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkWriteError> {
+        self.verify_write_position(position, &chunk_compound_tag)?;
+        self.extend_to_header_len()?;
+
+        let compression_scheme = self.chunk_write_compression_scheme(position);
+        let length = self.chunk_encoder.encode(
+            &chunk_compound_tag,
+            compression_scheme,
+            self.write_compression_level,
+        )?;
+
+        self.place_encoded_chunk(position, length, last_modified_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Writes chunk data from encoder, encoder buffers are kept for the next chunks.
+    fn place_encoded_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        length: u32,
+        last_modified_timestamp: u32,
+    ) -> Result<(), io::Error> {
+        let chunk_encoder = mem::take(&mut self.chunk_encoder);
+        let result = self.place_chunk(
+            position,
+            length,
+            chunk_encoder.data(),
+            last_modified_timestamp,
+        );
+        self.chunk_encoder = chunk_encoder;
+
+        result
+    }
+
+    /// Writes framed chunk data to found place and updates chunk metadata.
+    ///
+    /// In safe writes mode previous chunk sectors are released only after header
+    /// pointing to the new data reach the storage.
+    fn place_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        length: u32,
+        data: &[u8],
+        last_modified_timestamp: u32,
+    ) -> Result<(), io::Error> {
+        let previous_metadata = self.get_metadata(&position);
+
+        let mut metadata = self.find_place(&position, length)?;
+        let seek_offset = metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.source.seek(SeekFrom::Start(seek_offset))?;
+        self.source.write_all(data)?;
+        self.sync_safe_write()?;
+
+        metadata.last_modified_timestamp = last_modified_timestamp;
+        self.update_metadata(&position, metadata)?;
+
+        if self.safe_writes.is_some() {
+            self.sync_safe_write()?;
+            self.release_sectors(&position, previous_metadata)?;
+        }
+
+        self.truncate_free_tail()?;
+        self.record_event(position, ChunkEventKind::Write)
+    }
+
+    /// Writes multiple chunks.
+    ///
+    /// Places for all chunks are found first, then data of chunks which are placed in adjacent
+    /// sectors are written with a single write call and header are written once at the end.
+    /// Writing 32 small chunks to an empty region with `write_chunks` takes 35 write calls
+    /// to the source instead of 129 with `write_chunk` for every chunk.
+    ///
+    /// If same position occurs multiple times, only the last chunk are written.
+    ///
+    /// If any chunk cannot be placed or written, header are left pointing to previous chunks.
+    /// Without safe writes sectors of replaced chunks can be already overwritten.
+    pub fn write_chunks<I>(&mut self, chunks: I) -> Result<(), ChunkWriteError>
+    where
+        I: IntoIterator<Item = (RegionChunkPosition, CompoundTag)>,
+    {
+        let chunks: Vec<_> = chunks.into_iter().collect();
+
+        // Positions are verified before any chunk are placed.
+        for (position, chunk_compound_tag) in &chunks {
+            self.verify_write_position(*position, chunk_compound_tag)?;
+        }
+
+        self.extend_to_header_len()?;
+
+        // Metadata and sector map are restored if any chunk cannot be placed or written,
+        // so failed write doesn't change region header.
+        let chunks_metadata = self.chunks_metadata;
+        let used_sectors = self.used_sectors.clone();
+        let sector_map_loaded = self.sector_map_loaded;
+
+        let (positions, previous_metadata) = match self.place_and_write_chunks(chunks) {
+            Ok(placed) => placed,
+            Err(error) => {
+                self.chunks_metadata = chunks_metadata;
+                self.used_sectors = used_sectors;
+                self.sector_map_loaded = sector_map_loaded;
+
+                return Err(error);
+            }
+        };
+
+        if self.safe_writes.is_some() {
+            self.sync_safe_write()?;
+
+            for (index, metadata) in previous_metadata {
+                let position = RegionChunkPosition::from_metadata_index(index);
+                self.release_sectors(&position, metadata)?;
+            }
+        }
+
+        self.truncate_free_tail()?;
+
+        for position in positions {
+            self.record_event(position, ChunkEventKind::Write)?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds places for chunks, writes their data and header.
+    ///
+    /// Returns written positions and metadata of replaced chunks which sectors
+    /// are released after header in safe writes mode.
+    #[allow(clippy::type_complexity)]
+    fn place_and_write_chunks(
+        &mut self,
+        chunks: Vec<(RegionChunkPosition, CompoundTag)>,
+    ) -> Result<(Vec<RegionChunkPosition>, BTreeMap<usize, ChunkMetadata>), ChunkWriteError> {
+        // Chunks data by start sector index, keyed by metadata index to drop replaced chunks.
+        let mut placed_chunks: BTreeMap<usize, (u32, Vec<u8>)> = BTreeMap::new();
+        // Metadata of replaced chunks which sectors are released after header in safe writes mode.
+        let mut previous_metadata: BTreeMap<usize, ChunkMetadata> = BTreeMap::new();
+
+        for (position, chunk_compound_tag) in chunks {
+            let compression_scheme = self.chunk_write_compression_scheme(position);
+            let length = self.chunk_encoder.encode(
+                &chunk_compound_tag,
+                compression_scheme,
+                self.write_compression_level,
+            )?;
+            let data = self.chunk_encoder.data().to_vec();
+            let last_modified_timestamp = self.write_timestamp(position);
+
+            if self.safe_writes.is_some() {
+                let metadata = self.get_metadata(&position);
+
+                match previous_metadata.entry(position.metadata_index()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(metadata);
+                    }
+                    // Chunk placed earlier in this call are not referenced from storage yet.
+                    Entry::Occupied(_) => self.release_sectors(&position, metadata)?,
+                }
+            }
+
+            let mut metadata = self.find_place(&position, length)?;
+            metadata.last_modified_timestamp = last_modified_timestamp;
+
+            self.chunks_metadata[position.metadata_index()] = metadata;
+            placed_chunks.insert(
+                position.metadata_index(),
+                (metadata.start_sector_index, data),
+            );
+        }
+
+        let positions: Vec<_> = placed_chunks
+            .keys()
+            .map(|&index| RegionChunkPosition::from_metadata_index(index))
+            .collect();
+
+        let mut placed_chunks: Vec<_> = placed_chunks.into_values().collect();
+        placed_chunks.sort_by_key(|(start_sector_index, _)| *start_sector_index);
+
+        let mut placed_chunks = placed_chunks.into_iter().peekable();
+
+        while let Some((start_sector_index, mut buffer)) = placed_chunks.next() {
+            let mut end_sector_index =
+                start_sector_index + (buffer.len() / REGION_SECTOR_BYTES_LENGTH as usize) as u32;
+
+            // Coalescing data of chunks placed right after previous one.
+            while let Some((next_start_sector_index, _)) = placed_chunks.peek() {
+                if *next_start_sector_index != end_sector_index {
+                    break;
+                }
+
+                let (_, data) = placed_chunks.next().unwrap();
+                end_sector_index += (data.len() / REGION_SECTOR_BYTES_LENGTH as usize) as u32;
+                buffer.extend_from_slice(&data);
+            }
+
+            let seek_offset = start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+            self.source.write_all(&buffer)?;
+        }
+
+        self.sync_safe_write()?;
+        self.write_header()?;
+
+        Ok((positions, previous_metadata))
+    }
+
+    /// Extends the source length to the length of the header if necessary.
+    fn extend_to_header_len(&mut self) -> Result<(), io::Error> {
+        if REGION_HEADER_BYTES_LENGTH > self.source.len()? {
+            debug!(target: "anvil-region", "Extending source to header length");
+            self.source.extend_len(REGION_HEADER_BYTES_LENGTH)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes already compressed chunk data without encoding.
+    ///
+    /// Same as `write_compressed_chunk` with timestamp chosen as for `write_chunk`.
+    pub fn write_chunk_raw(
+        &mut self,
+        position: RegionChunkPosition,
+        compression_scheme: CompressionScheme,
+        data: &[u8],
+    ) -> Result<(), ChunkWriteError> {
+        let last_modified_timestamp = self.write_timestamp(position);
+
+        self.write_compressed_chunk(position, compression_scheme, data, last_modified_timestamp)
+    }
+
+    /// Writes already compressed chunk data with specified last modified timestamp.
+    ///
+    /// Data are not checked to be valid chunk data compressed with specified compression scheme.
+    pub fn write_compressed_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        compression_scheme: CompressionScheme,
+        data: &[u8],
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkWriteError> {
+        self.write_raw_chunk(
+            position,
+            compression_scheme.id(),
+            data,
+            last_modified_timestamp,
+        )
+    }
+
+    /// Writes compressed chunk data with compression scheme type id as is.
+    pub(crate) fn write_raw_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        compression_scheme: u8,
+        data: &[u8],
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkWriteError> {
+        self.frame_chunk(position, compression_scheme, data, last_modified_timestamp)
+            .map_err(|write_error| write_error.with_context(self.position, Some(position)))
+    }
+
+    fn frame_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        compression_scheme: u8,
+        data: &[u8],
+        last_modified_timestamp: u32,
+    ) -> Result<(), ChunkWriteError> {
+        self.extend_to_header_len()?;
+
+        let length = self.chunk_encoder.frame(compression_scheme, data)?;
+
+        self.place_encoded_chunk(position, length, last_modified_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Computes used sectors if region was opened with header only.
+    fn load_sector_map(&mut self) -> Result<(), io::Error> {
+        if !self.sector_map_loaded {
+            let total_sectors = total_sectors(self.source.len()?);
+
+            self.used_sectors = used_sectors(total_sectors, &self.chunks_metadata);
+            self.sector_map_loaded = true;
+        }
+
+        Ok(())
+    }
+
+    /// Truncates free sectors at the end of source if enabled.
+    fn truncate_free_tail(&mut self) -> Result<(), io::Error> {
+        match self.truncate_freed_sectors {
+            Some(truncate_freed_sectors) => truncate_freed_sectors(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes chunk from region and releases sectors used by it.
+    ///
+    /// Returns `true` if chunk was present in region.
+    pub fn remove_chunk(&mut self, position: RegionChunkPosition) -> Result<bool, io::Error> {
+        let metadata = self.get_metadata(&position);
+
+        if metadata.is_empty() {
+            return Ok(false);
+        }
+
+        self.load_sector_map()?;
+        self.release_sectors(&position, metadata)?;
+        self.update_metadata(&position, ChunkMetadata::default())?;
+        self.truncate_free_tail()?;
+        self.record_event(position, ChunkEventKind::Remove)?;
+
+        Ok(true)
+    }
+
+    /// Finds a place where chunk data of a given length can be put.
+    ///
+    /// If cannot find a place to put chunk data will extend source.
+    fn find_place(
+        &mut self,
+        position: &RegionChunkPosition,
+        chunk_length: u32,
+    ) -> Result<ChunkMetadata, io::Error> {
+        self.load_sector_map()?;
+
+        let sectors_required = sectors_required(chunk_length)?;
+        let metadata = self.get_metadata(position);
+
+        // In safe writes mode old place are kept until new data reach the storage.
+        if self.safe_writes.is_some() {
+            return self.find_free_place(position, sectors_required);
+        }
+
+        // Chunk still fits in the old place which is not shared with another chunk.
+        if metadata.sectors == sectors_required && !self.overlaps_other_chunk(position, metadata) {
+            debug!(
+                target: "anvil-region",
+                "Region x: {}, z: {} chunk x: {}, z: {} with length {} still fits in the old place",
+                self.position.x, self.position.z, position.x(), position.z(), chunk_length
+            );
+
+            #[cfg(feature = "allocation-trace")]
+            self.trace(AllocationEvent::Reuse {
+                position: *position,
+                start_sector_index: metadata.start_sector_index,
+                sectors: metadata.sectors,
+            });
+
+            return Ok(metadata);
+        }
+
+        // Release previously used sectors.
+        self.release_sectors(position, metadata)?;
+        self.find_free_place(position, sectors_required)
+    }
+
+    /// Finds free sectors gap where chunk data can be put and marks it as used.
+    ///
+    /// If cannot find a gap will extend source.
+    fn find_free_place(
+        &mut self,
+        position: &RegionChunkPosition,
+        sectors_required: u8,
+    ) -> Result<ChunkMetadata, io::Error> {
+        let source_len = self.source.len()?;
+        let (start_sector_index, extend_sectors) =
+            allocate_sectors(&mut self.used_sectors, sectors_required);
+
+        let end_sector_index = start_sector_index + sectors_required as u32;
+        let end_len = end_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        if end_len > source_len {
+            // Extending source because cannot find a place to put chunk data.
+            debug!(
+                target: "anvil-region",
+                "Extending region x: {}, z: {} source for {} bytes to place chunk data, \
+                {} sectors added",
+                self.position.x,
+                self.position.z,
+                end_len - source_len,
+                extend_sectors
+            );
+
+            self.source.extend_len(end_len)?;
+
+            #[cfg(feature = "allocation-trace")]
+            self.trace(AllocationEvent::Extend {
+                position: *position,
+                start_sector_index,
+                sectors: sectors_required,
+                extend_sectors,
+            });
+        } else {
+            debug!(
+                target: "anvil-region",
+                "Region x: {}, z: {} chunk x: {}, z: {} with {} required sectors \
+                can be placed in free sectors gap between from {} to {}",
+                self.position.x,
+                self.position.z,
+                position.x(),
+                position.z(),
+                sectors_required,
+                start_sector_index,
+                end_sector_index - 1
+            );
+
+            #[cfg(feature = "allocation-trace")]
+            self.trace(AllocationEvent::Allocate {
+                position: *position,
+                start_sector_index,
+                sectors: sectors_required,
+            });
+        }
+
+        Ok(ChunkMetadata::new(start_sector_index, sectors_required, 0))
+    }
+
+    /// Marks chunk sectors as free and wipes them if enabled.
+    ///
+    /// Sectors which are also used by another chunk because of corrupted header are kept used.
+    fn release_sectors(
+        &mut self,
+        position: &RegionChunkPosition,
+        metadata: ChunkMetadata,
+    ) -> Result<(), io::Error> {
+        let start_sector_index = metadata.start_sector_index;
+        let end_sector_index = start_sector_index + metadata.sectors as u32;
+
+        free_sectors(
+            &mut self.used_sectors,
+            &self.chunks_metadata,
+            position.metadata_index(),
+            metadata,
+        );
+
+        #[cfg(feature = "allocation-trace")]
+        if !metadata.is_empty() {
+            self.trace(AllocationEvent::Free {
+                position: *position,
+                start_sector_index: metadata.start_sector_index,
+                sectors: metadata.sectors,
+            });
+        }
+
+        if self.wipe_freed_sectors {
+            self.wipe_sectors(start_sector_index, end_sector_index)?;
+        }
+
+        Ok(())
+    }
+
+    /// Overwrites free sectors in range with zeros.
+    fn wipe_sectors(
+        &mut self,
+        start_sector_index: u32,
+        end_sector_index: u32,
+    ) -> Result<(), io::Error> {
+        let mut sector_index = start_sector_index;
+
+        while sector_index < end_sector_index {
+            if self.used_sectors[sector_index as usize] {
+                sector_index += 1;
+                continue;
+            }
+
+            // Free sectors in a row are wiped with a single write.
+            let free_sectors = (sector_index..end_sector_index)
+                .take_while(|sector_index| !self.used_sectors[*sector_index as usize])
+                .count();
+
+            let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            let wipe_len = free_sectors * REGION_SECTOR_BYTES_LENGTH as usize;
+
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+            self.source.write_all(&vec![0; wipe_len])?;
+
+            sector_index += free_sectors as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Synchronizes source with storage if safe writes are enabled.
+    fn sync_safe_write(&mut self) -> Result<(), io::Error> {
+        match self.safe_writes {
+            Some(sync_all) => sync_all(self),
+            None => Ok(()),
+        }
+    }
+
+    /// Updates chunk metadata.
+    fn update_metadata(
+        &mut self,
+        position: &RegionChunkPosition,
+        metadata: ChunkMetadata,
+    ) -> Result<(), io::Error> {
+        let metadata_index = position.metadata_index();
+        self.chunks_metadata[metadata_index] = metadata;
+        self.mark_unflushed();
+
+        let start_seek_offset = SeekFrom::Start((metadata_index * 4) as u64);
+        let offset = (metadata.start_sector_index << 8) | metadata.sectors as u32;
+
+        self.source.seek(start_seek_offset)?;
+        self.source.write_u32::<BigEndian>(offset)?;
+
+        let next_seek_offset = SeekFrom::Current(REGION_SECTOR_BYTES_LENGTH as i64 - 4);
+        let last_modified_timestamp = metadata.last_modified_timestamp;
+
+        self.source.seek(next_seek_offset)?;
+        self.source
+            .write_u32::<BigEndian>(last_modified_timestamp)?;
+
+        Ok(())
+    }
+
+    /// Marks region as changed, so it are flushed on drop.
+    fn mark_unflushed(&mut self) {
+        self.pending_flush = Some(Self::flush as RegionFn<S>);
+    }
+
+    /// Writes all chunks metadata to header.
+    fn write_header(&mut self) -> Result<(), io::Error> {
+        let buffer = header_bytes(&self.chunks_metadata)?;
+        self.mark_unflushed();
+
+        self.source.seek(SeekFrom::Start(0))?;
+        self.source.write_all(&buffer)
+    }
+
+    /// Writes copy of header after the last used sector.
+    ///
+    /// Previous backup header are overwritten in place when no chunk data was placed after it.
+    fn write_backup_header(&mut self) -> Result<(), io::Error> {
+        let data_sectors = self
+            .used_sectors
+            .iter()
+            .rposition(|used| *used)
+            .map_or(2, |index| index + 1) as u32;
+        let source_sectors = total_sectors(self.source.len()?) as u32;
+
+        let sector_index = match self.backup_header_sector_index {
+            Some(sector_index)
+                if sector_index >= data_sectors
+                    && sector_index + BACKUP_HEADER_SECTORS == source_sectors =>
+            {
+                sector_index
+            }
+            _ => data_sectors.max(source_sectors),
+        };
+
+        let mut buffer = vec![0; REGION_SECTOR_BYTES_LENGTH as usize];
+        buffer[..BACKUP_HEADER_MAGIC.len()].copy_from_slice(BACKUP_HEADER_MAGIC);
+        buffer.extend_from_slice(&header_bytes(&self.chunks_metadata)?);
+
+        let seek_offset = sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.source.seek(SeekFrom::Start(seek_offset))?;
+        self.source.write_all(&buffer)?;
+
+        // Backup header sectors are free for chunks data.
+        let total_sectors = (sector_index + BACKUP_HEADER_SECTORS) as usize;
+
+        if total_sectors > self.used_sectors.len() {
+            self.used_sectors.resize(total_sectors, false);
+        }
+
+        self.backup_header_sector_index = Some(sector_index);
+
+        Ok(())
+    }
+
+    /// Flushes source.
+    ///
+    /// If auto defragmentation are enabled and wasted sectors percent exceeds threshold
+    /// region will be compacted before flush.
+    ///
+    /// If backup header are enabled header copy are written at the end of source.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.load_sector_map()?;
+
+        if let Some((threshold, compact)) = self.auto_defrag {
+            let wasted_sectors_percent = self.wasted_sectors_percent();
+
+            if wasted_sectors_percent > threshold {
+                debug!(
+                    target: "anvil-region",
+                    "Region x: {}, z: {} wasted sectors {}% exceeds {}%, compacting",
+                    self.position.x, self.position.z, wasted_sectors_percent, threshold
+                );
+
+                compact(self)?;
+            }
+        }
+
+        if self.backup_header {
+            // Primary header also rewritten in case it was recovered from backup.
+            self.write_header()?;
+            self.write_backup_header()?;
+        }
+
+        self.source.flush()?;
+        self.pending_flush = None;
+
+        Ok(())
+    }
+
+    /// Flushes and closes region.
+    ///
+    /// Region are also flushed on drop, but flush errors are only logged there.
+    pub fn close(mut self) -> Result<(), io::Error> {
+        self.flush()
+    }
+}
+
+impl<S: Write + Seek + SyncAll> Region<S> {
+    /// Flushes region and waits until all data reach the storage.
+    pub fn sync_all(&mut self) -> Result<(), io::Error> {
+        self.flush()?;
+        self.source.sync_all()
+    }
+
+    /// Sets whether chunks are written crash-safe.
+    ///
+    /// Chunk data are always written to free sectors and synchronized with storage before
+    /// header are updated, so crash during write can't corrupt chunk which was already present.
+    /// Writes are slower since every write waits for storage twice.
+    pub fn set_safe_writes(&mut self, safe: bool) {
+        let sync_all = Self::sync_source as RegionFn<S>;
+        self.safe_writes = safe.then_some(sync_all);
+    }
+
+    fn sync_source(&mut self) -> Result<(), io::Error> {
+        self.source.sync_all()
+    }
+}
+
+impl<S> Drop for Region<S> {
+    fn drop(&mut self) {
+        if let Some(flush) = self.pending_flush.take() {
+            if let Err(io_error) = flush(self) {
+                warn!(
+                    target: "anvil-region",
+                    "Region x: {}, z: {} flush on drop failed: {}",
+                    self.position.x, self.position.z, io_error
+                );
+            }
+        }
+    }
+}
+
+impl<S: Read + Seek> Region<S> {
+    /// Copies whole region source byte-for-byte to the writer.
+    ///
+    /// Returns amount of copied bytes.
+    pub fn copy_raw_to<W: Write>(&mut self, writer: &mut W) -> Result<u64, io::Error> {
+        let source_len = self.source.len()?;
+        self.source.seek(SeekFrom::Start(0))?;
+
+        let copied_len = io::copy(&mut (&mut *self.source).take(source_len), writer)?;
+
+        if copied_len != source_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Region source ended before expected length",
+            ));
+        }
+
+        Ok(copied_len)
+    }
+
+    /// Writes compacted region to the writer without changing source.
+    ///
+    /// Chunks are written in the order of their sectors without gaps between them,
+    /// same as after `compact`. Only one chunk data are kept in memory at a time.
+    /// Returns amount of written bytes.
+    pub fn write_to<W: Write>(&mut self, writer: &mut W) -> Result<u64, io::Error> {
+        let mut indexes: Vec<usize> = (0..REGION_CHUNKS)
+            .filter(|index| !self.chunks_metadata[*index].is_empty())
+            .collect();
+
+        indexes.sort_by_key(|index| self.chunks_metadata[*index].start_sector_index);
+
+        let mut chunks_metadata = self.chunks_metadata;
+        // First two sectors are used to store metadata.
+        let mut sector_index = 2;
+
+        for index in &indexes {
+            chunks_metadata[*index].start_sector_index = sector_index;
+            sector_index += chunks_metadata[*index].sectors as u32;
+        }
+
+        writer.write_all(&header_bytes(&chunks_metadata)?)?;
+
+        let mut data = Vec::new();
+
+        for index in indexes {
+            let metadata = self.chunks_metadata[index];
+            let seek_offset =
+                metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            let data_len = metadata.sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+            data.clear();
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+            (&mut *self.source).take(data_len).read_to_end(&mut data)?;
+            data.resize(data_len as usize, 0);
+
+            writer.write_all(&data)?;
+        }
+
+        writer.flush()?;
+
+        Ok(sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64)
+    }
+
+    /// Copies compressed chunk data to another region without decoding.
+    ///
+    /// Compression scheme and last modified timestamp of chunk are preserved,
+    /// coordinates inside chunk data are not changed. Returns `false` if chunk not present.
+    pub fn copy_chunk_to<D: Write + Seek>(
+        &mut self,
+        destination: &mut Region<D>,
+        from: RegionChunkPosition,
+        to: RegionChunkPosition,
+    ) -> Result<bool, ChunkCopyError> {
+        let last_modified_timestamp = match self.chunk_last_modified_timestamp(from) {
+            Some(last_modified_timestamp) => last_modified_timestamp,
+            None => return Ok(false),
+        };
+
+        let (compression_scheme, data) = self.read_compressed_chunk(from)?;
+        destination.write_raw_chunk(to, compression_scheme, &data, last_modified_timestamp)?;
+
+        Ok(true)
+    }
+}
+
+impl<S: Read + Write + Seek> Region<S> {
+    /// Sets whether `write_chunk` and `write_chunks` reuse compression scheme of chunks
+    /// which are already present, so rewritten chunks keep their original compression.
+    ///
+    /// Chunks which compression scheme can't be read are written with region write
+    /// compression scheme.
+    pub fn set_preserve_compression_scheme(&mut self, preserve: bool) {
+        let chunk_compression = Self::chunk_compression as ChunkCompressionFn<S>;
+        self.preserve_compression_scheme = preserve.then_some(chunk_compression);
+    }
+
+    /// Rebuilds header by scanning sectors for chunks data.
+    ///
+    /// Every sector after header which starts decodable chunk are treated as chunk start,
+    /// chunk position are taken from chunk coordinates tags. If several chunks with the same
+    /// position found, chunk referenced by current header are preferred, otherwise the last one.
+    /// Timestamps are kept for chunks which stay at the same place, otherwise set to zero.
+    ///
+    /// Returns amount of chunks in rebuilt header.
+    pub fn rebuild_header(&mut self) -> Result<usize, io::Error> {
+        let source_len = self.source.len()?;
+        let total_sectors = total_sectors(source_len) as u32;
+
+        let mut chunks_metadata = [ChunkMetadata::default(); REGION_CHUNKS];
+        let mut sector_index = 2;
+
+        while sector_index < total_sectors {
+            let (sectors, compound_tag) = match self.read_chunk_at(sector_index, source_len) {
+                Ok(chunk) => chunk,
+                Err(_) => {
+                    sector_index += 1;
+                    continue;
+                }
+            };
+
+            // Sectors count are stored in one byte, such chunk can't be referenced by header.
+            let chunk_sectors = match u8::try_from(sectors) {
+                Ok(chunk_sectors) => chunk_sectors,
+                Err(_) => {
+                    debug!(
+                        target: "anvil-region",
+                        "Region x: {}, z: {} chunk at sector {} takes {} sectors and are skipped",
+                        self.position.x, self.position.z, sector_index, sectors
+                    );
+
+                    sector_index += sectors;
+                    continue;
+                }
+            };
+
+            if let Some(position) = self.chunk_tag_position(&compound_tag) {
+                let metadata_index = position.metadata_index();
+                let current_metadata = self.chunks_metadata[metadata_index];
+                let found_metadata = &mut chunks_metadata[metadata_index];
+                let found_referenced = !found_metadata.is_empty()
+                    && found_metadata.start_sector_index == current_metadata.start_sector_index;
+
+                if current_metadata.start_sector_index == sector_index {
+                    *found_metadata = current_metadata;
+                    found_metadata.sectors = chunk_sectors;
+                } else if !found_referenced {
+                    *found_metadata = ChunkMetadata::new(sector_index, chunk_sectors, 0);
+                }
+            }
+
+            sector_index += sectors;
+        }
+
+        self.used_sectors = used_sectors(total_sectors as usize, &chunks_metadata);
+        self.sector_map_loaded = true;
+        self.chunks_metadata = chunks_metadata;
+        self.write_header()?;
+
+        let chunks = chunks_metadata
+            .iter()
+            .filter(|metadata| !metadata.is_empty())
+            .count();
+
+        Ok(chunks)
+    }
+}
+
+impl<S: Read + Write + Seek + SetLen> Region<S> {
+    /// Replaces whole region source with region data from the reader.
+    ///
+    /// Data are validated before replacing: header must be complete, all chunks must
+    /// point to the sectors inside data and chunks sectors must not overlap.
+    /// Source are truncated to the length of new data.
+    pub fn replace_from<R: Read>(&mut self, reader: &mut R) -> Result<(), io::Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let data_len = data.len() as u64;
+
+        if data_len != 0 && REGION_HEADER_BYTES_LENGTH > data_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Region data is shorter than header",
+            ));
+        }
+
+        let chunks_metadata = read_header(&mut Cursor::new(&data), data_len)?;
+        let data_sectors = total_sectors(data_len);
+
+        for metadata in chunks_metadata
+            .iter()
+            .filter(|metadata| !metadata.is_empty())
+        {
+            let end_sector_index = metadata.start_sector_index as usize + metadata.sectors as usize;
+
+            if metadata.start_sector_index < 2 || end_sector_index > data_sectors {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Region data contains chunk outside of data",
+                ));
+            }
+        }
+
+        // Region with such header are only warned about on load, but writing into
+        // overlapping sectors would corrupt another chunk.
+        if sectors_overlap(&chunks_metadata) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Region data contains chunks with overlapping sectors",
+            ));
+        }
+
+        let backup_header_sector_index = find_backup_header(&mut Cursor::new(&data), data_len)?;
+
+        self.source.seek(SeekFrom::Start(0))?;
+        self.source.write_all(&data)?;
+        self.source.set_len(data_len)?;
+
+        self.used_sectors = used_sectors(total_sectors(data_len), &chunks_metadata);
+        self.sector_map_loaded = true;
+        // Backup header of previous data are truncated or overwritten.
+        self.backup_header_sector_index = backup_header_sector_index;
+        self.chunks_metadata = chunks_metadata;
+        self.mark_unflushed();
+
+        Ok(())
+    }
+
+    /// Moves all chunks data to the beginning of source removing gaps between them
+    /// and truncates unused sectors at the end.
+    pub fn compact(&mut self) -> Result<(), io::Error> {
+        let mut indexes: Vec<usize> = (0..REGION_CHUNKS)
+            .filter(|index| !self.chunks_metadata[*index].is_empty())
+            .collect();
+
+        indexes.sort_by_key(|index| self.chunks_metadata[*index].start_sector_index);
+
+        let mut chunks_data = Vec::with_capacity(indexes.len());
+
+        for index in &indexes {
+            let metadata = self.chunks_metadata[*index];
+            let seek_offset =
+                metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+            let data_len = metadata.sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+            let mut data = Vec::with_capacity(data_len as usize);
+            self.source.seek(SeekFrom::Start(seek_offset))?;
+            (&mut *self.source).take(data_len).read_to_end(&mut data)?;
+            data.resize(data_len as usize, 0);
+
+            chunks_data.push(data);
+        }
+
+        // First two sectors are used to store metadata.
+        let mut sector_index = 2;
+        self.source
+            .seek(SeekFrom::Start(REGION_HEADER_BYTES_LENGTH))?;
+
+        for (index, data) in indexes.into_iter().zip(chunks_data) {
+            self.source.write_all(&data)?;
+            self.chunks_metadata[index].start_sector_index = sector_index;
+
+            sector_index += self.chunks_metadata[index].sectors as u32;
+        }
+
+        self.write_header()?;
+        self.source
+            .set_len(sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64)?;
+
+        self.used_sectors = used_sectors(sector_index as usize, &self.chunks_metadata);
+        self.sector_map_loaded = true;
+
+        Ok(())
+    }
+
+    /// Truncates source after the last sector used by chunks.
+    ///
+    /// Header are validated first, all chunks must be placed inside source and don't overlap.
+    /// Trailing data such as half-written sectors and backup header are removed.
+    /// Returns amount of removed bytes.
+    pub fn truncate_after_last_valid_chunk(&mut self) -> Result<u64, io::Error> {
+        let source_len = self.source.len()?;
+
+        if !is_valid_header(&self.chunks_metadata, total_sectors(source_len)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Region header contains chunk outside of source or overlapping chunks",
+            ));
+        }
+
+        // First two sectors are used to store metadata.
+        let data_sectors = self
+            .chunks_metadata
+            .iter()
+            .filter(|metadata| !metadata.is_empty())
+            .map(|metadata| metadata.start_sector_index as usize + metadata.sectors as usize)
+            .max()
+            .unwrap_or(2);
+
+        let data_len = data_sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        if data_len >= source_len {
+            return Ok(0);
+        }
+
+        self.source.set_len(data_len)?;
+        self.used_sectors = used_sectors(data_sectors, &self.chunks_metadata);
+        self.sector_map_loaded = true;
+        self.backup_header_sector_index = None;
+
+        Ok(source_len - data_len)
+    }
+
+    /// Disables backup header and removes it from the end of source.
+    pub fn strip_backup_header(&mut self) -> Result<(), io::Error> {
+        self.backup_header = false;
+
+        let sector_index = match self.backup_header_sector_index.take() {
+            Some(sector_index) => sector_index,
+            None => return Ok(()),
+        };
+
+        let source_sectors = total_sectors(self.source.len()?) as u32;
+        let data_sectors = self
+            .used_sectors
+            .iter()
+            .rposition(|used| *used)
+            .map_or(2, |index| index + 1) as u32;
+
+        // Backup header was overwritten by chunks data or not at the end anymore.
+        if sector_index < data_sectors || sector_index + BACKUP_HEADER_SECTORS != source_sectors {
+            return Ok(());
+        }
+
+        self.source
+            .set_len(sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64)?;
+        self.used_sectors.truncate(sector_index as usize);
+
+        Ok(())
+    }
+
+    /// Sets wasted sectors percent after which region are compacted on flush.
+    ///
+    /// `None` disables auto defragmentation.
+    pub fn set_auto_defrag_threshold(&mut self, percent: Option<u8>) {
+        self.auto_defrag = percent.map(|percent| (percent, Self::compact as RegionFn<S>));
+    }
+
+    /// Sets whether source are truncated when sectors at the end become free
+    /// after chunk are written or removed.
+    ///
+    /// Backup header at the end of source are removed too and written again on flush.
+    pub fn set_truncate_freed_sectors(&mut self, truncate: bool) {
+        let truncate_freed_sectors = Self::truncate_freed_sectors as RegionFn<S>;
+        self.truncate_freed_sectors = truncate.then_some(truncate_freed_sectors);
+    }
+
+    /// Truncates free sectors at the end of source.
+    fn truncate_freed_sectors(&mut self) -> Result<(), io::Error> {
+        // First two sectors are used to store metadata.
+        let data_sectors = self
+            .used_sectors
+            .iter()
+            .rposition(|used| *used)
+            .map_or(2, |index| index + 1);
+
+        let data_len = data_sectors as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        if data_len >= self.source.len()? {
+            return Ok(());
+        }
+
+        self.source.set_len(data_len)?;
+        self.used_sectors.truncate(data_sectors);
+        self.backup_header_sector_index = None;
+
+        Ok(())
+    }
+}
+
+impl<S: Read + Seek> Region<Window<S>> {
+    /// Loads region which are embedded in source at specified offset.
+    ///
+    /// Source window of specified length starting from offset are treated as region source.
+    pub fn load_at(
+        position: RegionPosition,
+        source: S,
+        byte_offset: u64,
+        len: u64,
+    ) -> Result<Self, io::Error> {
+        let window = Window::new(source, byte_offset, len)?;
+
+        Region::load(position, window)
+    }
+}
+
+impl Region<Cursor<Vec<u8>>> {
+    /// Loads region from bytes of region file.
+    ///
+    /// Empty bytes are treated as empty region.
+    pub fn from_bytes(x: i32, z: i32, bytes: Vec<u8>) -> Result<Self, io::Error> {
+        Region::load(RegionPosition::new(x, z), Cursor::new(bytes))
+    }
+
+    /// Flushes region and returns bytes of region file.
+    pub fn into_bytes(self) -> Result<Vec<u8>, io::Error> {
+        Ok(self.into_inner()?.into_inner())
+    }
+}
+
+impl<S: Read + Seek> IntoIterator for Region<S> {
+    type Item = <RegionIterator<S> as Iterator>::Item;
+    type IntoIter = RegionIterator<S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        RegionIterator {
+            inner: self,
+            current: 0,
+        }
+    }
+}
+
+pub struct RegionIterator<S: Read + Seek> {
+    inner: Region<S>,
+    current: usize,
+}
+
+impl<S: Read + Seek> Iterator for RegionIterator<S> {
+    type Item = CompoundTag;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current == REGION_CHUNKS {
+            return None;
+        }
+
+        let pos = RegionChunkPosition::from_metadata_index(self.current);
+        self.current += 1;
+
+        match self.inner.read_chunk(pos) {
+            Ok(chunk) => Some(chunk),
+            Err(_) => self.next(),
+        }
+    }
+}
+
+/// Iterator over chunks present in region with their positions.
+pub struct Chunks<'a, S> {
+    region: &'a mut Region<S>,
+    current: usize,
+    /// Compressed chunk data buffer which are reused between chunks.
+    compressed_buffer: Vec<u8>,
+}
+
+impl<S: Read + Seek> Iterator for Chunks<'_, S> {
+    type Item = Result<(RegionChunkPosition, CompoundTag), ChunkReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.current < REGION_CHUNKS {
+            let position = RegionChunkPosition::from_metadata_index(self.current);
+            self.current += 1;
+
+            if !self.region.contains_chunk(position) {
+                continue;
+            }
+
+            let result = self
+                .region
+                .read_chunk_into(position, &mut self.compressed_buffer)
+                .map(|compound_tag| (position, compound_tag));
+
+            return Some(result);
+        }
+
+        None
+    }
+}
+
+/// Summary of region header without access to chunks data.
+pub struct RegionIndex {
+    /// Region position in the world.
+    position: RegionPosition,
+    /// Array of chunks metadata.
+    chunks_metadata: [ChunkMetadata; REGION_CHUNKS],
+}
+
+impl RegionIndex {
+    /// Reads region index from the header at the start of reader.
+    ///
+    /// Reader which is shorter than header are treated as empty region.
+    pub fn read<R: Read>(position: RegionPosition, reader: &mut R) -> Result<Self, io::Error> {
+        let mut buffer = Vec::with_capacity(REGION_HEADER_BYTES_LENGTH as usize);
+        reader
+            .take(REGION_HEADER_BYTES_LENGTH)
+            .read_to_end(&mut buffer)?;
+
+        let chunks_metadata = read_header(&mut buffer.as_slice(), buffer.len() as u64)?;
+
+        Ok(RegionIndex {
+            position,
+            chunks_metadata,
+        })
+    }
+
+    /// Creates region index from the header of loaded region.
+    pub(crate) fn from_region<S>(position: RegionPosition, region: &Region<S>) -> Self {
+        RegionIndex {
+            position,
+            chunks_metadata: region.chunks_metadata,
+        }
+    }
+
+    /// Returns region position in the world.
+    pub fn position(&self) -> RegionPosition {
+        self.position
+    }
+
+    /// Returns `true` if chunk present in region.
+    pub fn contains_chunk(&self, position: RegionChunkPosition) -> bool {
+        !self.chunks_metadata[position.metadata_index()].is_empty()
+    }
+
+    /// Returns sector index from which starts chunk data or `None` if chunk not present.
+    pub fn chunk_start_sector_index(&self, position: RegionChunkPosition) -> Option<u32> {
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        if metadata.is_empty() {
+            return None;
+        }
+
+        Some(metadata.start_sector_index)
+    }
+
+    /// Returns bitmap of chunks present in region, see `Region::presence_bitmap` for layout.
+    pub fn presence_bitmap(&self) -> [u64; 16] {
+        presence_bitmap(&self.chunks_metadata)
+    }
+
+    /// Returns amount of chunks present in region.
+    pub fn chunks_count(&self) -> usize {
+        self.chunks_metadata
+            .iter()
+            .filter(|metadata| !metadata.is_empty())
+            .count()
+    }
+
+    /// Returns last time in seconds when chunk was modified or `None` if chunk not present.
+    pub fn chunk_last_modified_timestamp(&self, position: RegionChunkPosition) -> Option<u32> {
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        if metadata.is_empty() {
+            return None;
+        }
+
+        Some(metadata.last_modified_timestamp)
+    }
+
+    /// Returns positions of chunks which were written, moved or removed since previous index.
+    pub fn changed_chunks<'a>(
+        &'a self,
+        previous: &'a RegionIndex,
+    ) -> impl Iterator<Item = RegionChunkPosition> + 'a {
+        self.chunks_metadata
+            .iter()
+            .zip(previous.chunks_metadata.iter())
+            .enumerate()
+            .filter(|(_, (metadata, previous_metadata))| metadata != previous_metadata)
+            .map(|(index, _)| RegionChunkPosition::from_metadata_index(index))
+    }
+}
+
+/// Chunk metadata are stored in header.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub(crate) struct ChunkMetadata {
+    /// Sector index from which starts chunk data.
+    pub(crate) start_sector_index: u32,
+    /// Amount of sectors used to store chunk.
+    pub(crate) sectors: u8,
+    /// Last time in seconds when chunk was modified.
+    pub(crate) last_modified_timestamp: u32,
+}
+
+impl ChunkMetadata {
+    pub(crate) fn new(start_sector_index: u32, sectors: u8, last_modified_timestamp: u32) -> Self {
+        ChunkMetadata {
+            start_sector_index,
+            sectors,
+            last_modified_timestamp,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sectors == 0
+    }
+}
+
+/// Returns current time in seconds.
+pub(crate) fn current_timestamp() -> u32 {
+    let system_time = SystemTime::now();
+    let time = system_time.duration_since(UNIX_EPOCH).unwrap();
+
+    time.as_secs() as u32
+}
+
+/// Source which length can be changed.
+pub trait SetLen {
+    /// Truncates or extends source to the specified length.
+    fn set_len(&mut self, len: u64) -> Result<(), io::Error>;
+}
+
+impl SetLen for File {
+    fn set_len(&mut self, len: u64) -> Result<(), Error> {
+        File::set_len(self, len)
+    }
+}
+
+impl SetLen for Cursor<Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> Result<(), Error> {
+        self.get_mut().resize(len as usize, 0);
+
+        Ok(())
+    }
+}
+
+/// Source which data can be synchronized with the storage.
+pub trait SyncAll {
+    /// Waits until all written data and metadata reach the storage.
+    fn sync_all(&mut self) -> Result<(), io::Error>;
+}
+
+impl SyncAll for File {
+    fn sync_all(&mut self) -> Result<(), Error> {
+        File::sync_all(self)
+    }
+}
+
+impl SyncAll for Cursor<Vec<u8>> {
+    fn sync_all(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Trait adds additional helper methods for `Seek`.
+trait SeekExt {
+    fn len(&mut self) -> Result<u64, io::Error>;
+}
+
+impl<S: Seek> SeekExt for S {
+    fn len(&mut self) -> Result<u64, Error> {
+        let old_pos = self.stream_position()?;
+        self.seek(SeekFrom::Start(0))?;
+        let len = self.seek(SeekFrom::End(0))?;
+
+        if old_pos != len {
+            self.seek(SeekFrom::Start(old_pos))?;
+        }
+
+        Ok(len)
+    }
+}
+
+/// Trait adds additional helper methods for `Seek+Write`.
+trait SeekWriteExt {
+    fn extend_len(&mut self, new_len: u64) -> Result<(), io::Error>;
+}
+
+impl<S: Seek + Write> SeekWriteExt for S {
+    fn extend_len(&mut self, new_len: u64) -> Result<(), Error> {
+        let old_pos = self.stream_position()?;
+        self.seek(SeekFrom::Start(0))?;
+        let len = self.seek(SeekFrom::End(0))?;
+
+        if new_len > len {
+            let padding_len = new_len - len;
+            self.write_all(&vec![0; padding_len as usize])?;
+        }
+
+        if old_pos != len {
+            self.seek(SeekFrom::Start(old_pos))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkTagBuilder;
+    use crate::compression::CompressionScheme;
+    use crate::error::{ChunkReadError, ChunkWriteError};
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+    use crate::region;
+    use crate::region::{
+        read_header, ChunkMetadata, Region, RegionIndex, SeekExt, SeekWriteExt,
+        BACKUP_HEADER_SECTORS, REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+    };
+    use crate::validation::ValidationIssue;
+    use nbt::CompoundTag;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_header_read() {
+        let expected_data = [
+            ChunkMetadata::new(61, 2, 1570215508),
+            ChunkMetadata::new(102, 2, 1570215511),
+            ChunkMetadata::new(177, 2, 1570215515),
+            ChunkMetadata::new(265, 2, 1570215519),
+            ChunkMetadata::new(56, 2, 1570215508),
+        ];
+
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        for (index, expected_chunk_metadata) in expected_data.iter().enumerate() {
+            let chunk_metadata = region.chunks_metadata[256 + index];
+
+            assert_eq!(&chunk_metadata, expected_chunk_metadata);
+        }
+    }
+
+    #[test]
+    fn test_read_chunk() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let compound_tag = region.read_chunk(RegionChunkPosition::new(15, 3)).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        region.set_backup_header(true);
+        region
+            .write_chunk(RegionChunkPosition::new(4, 4), CompoundTag::new())
+            .unwrap();
+
+        let data_len = region.get_ref().get_ref().len();
+        assert_eq!(
+            region.get_mut().seek(SeekFrom::End(0)).unwrap(),
+            data_len as u64
+        );
+
+        // Backup header are written by flush before source are returned.
+        let data = region.into_inner().unwrap().into_inner();
+        let mut region =
+            Region::load_with_backup_header(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+
+        assert!(region.backup_header_sector_index.is_some());
+        assert!(region.read_chunk(RegionChunkPosition::new(4, 4)).is_ok());
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut region = region_with_gap();
+        let source_data = region.source.get_ref().clone();
+
+        let mut data = Vec::new();
+        let written_len = region.write_to(&mut data).unwrap();
+
+        // Source are not changed.
+        assert_eq!(region.source.get_ref(), &source_data);
+
+        region.compact().unwrap();
+
+        assert_eq!(written_len, data.len() as u64);
+        assert_eq!(&data, region.source.get_ref());
+    }
+
+    #[test]
+    fn test_read_write_chunk_raw() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut source_region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let position = RegionChunkPosition::new(4, 2);
+
+        let (compression_scheme, data) = source_region.read_chunk_raw(position).unwrap();
+        assert_eq!(compression_scheme, CompressionScheme::Zlib);
+
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        region
+            .write_chunk_raw(position, compression_scheme, &data)
+            .unwrap();
+
+        assert_eq!(region.read_chunk_raw(position).unwrap().1, data);
+
+        let compound_tag = region.read_chunk(position).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 4);
+        assert!(region.chunk_last_modified_timestamp(position).unwrap() > 0);
+
+        region
+            .write_compressed_chunk(position, compression_scheme, &data, 1570215508)
+            .unwrap();
+
+        assert_eq!(region.read_chunk_raw(position).unwrap().1, data);
+        assert_eq!(
+            region.chunk_last_modified_timestamp(position).unwrap(),
+            1570215508
+        );
+    }
+
+    #[test]
+    fn test_write_chunk_raw_sectors_count_boundary() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position = RegionChunkPosition::new(0, 0);
+
+        // With prefix and padding chunk takes 256 sectors which doesn't fit into one byte.
+        let data = vec![0; REGION_SECTOR_BYTES_LENGTH as usize * 255 - 5];
+
+        match region
+            .write_chunk_raw(position, CompressionScheme::Uncompressed, &data)
+            .err()
+            .unwrap()
+        {
+            ChunkWriteError::LengthExceedsMaximum { length, .. } => {
+                assert_eq!(length, REGION_SECTOR_BYTES_LENGTH as u32 * 255)
+            }
+            write_error => panic!(
+                "Expected `LengthExceedsMaximum` but got `{:?}`",
+                write_error
+            ),
+        }
+
+        assert!(!region.contains_chunk(position));
+
+        let data = vec![0; REGION_SECTOR_BYTES_LENGTH as usize * 255 - 6];
+        region
+            .write_chunk_raw(position, CompressionScheme::Uncompressed, &data)
+            .unwrap();
+
+        assert_eq!(region.get_metadata(&position).sectors, 255);
+        assert_eq!(region.read_chunk_raw(position).unwrap().1, data);
+    }
+
+    #[test]
+    fn test_overlapping_sectors() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
+
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i32_vec("test_i32_vec", (0..3000).collect());
+
+        region.write_chunk(position_1, large_compound_tag).unwrap();
+        region.write_chunk(position_2, CompoundTag::new()).unwrap();
+        assert!(!region.has_overlapping_sectors());
+
+        // Corrupting header so second chunk points to the last sector of first chunk.
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::new(3, 1, 0);
+        region.write_header().unwrap();
+
+        let data = region.source.get_ref().clone();
+        let mut region =
+            Region::load(RegionPosition::new(0, 0), Cursor::new(data.clone())).unwrap();
+
+        assert!(region.has_overlapping_sectors());
+
+        // Shared sector are not reused in place.
+        region.write_chunk(position_2, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(&position_2).start_sector_index, 4);
+        assert!(region.used_sectors[3]);
+
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+
+        // First chunk shrinks, but shared sector stays used by second chunk.
+        region.write_chunk(position_1, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(&position_1).start_sector_index, 2);
+        assert!(region.used_sectors[3]);
+
+        let position_3 = RegionChunkPosition::new(2, 0);
+        region.write_chunk(position_3, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(&position_3).start_sector_index, 4);
+    }
+
+    #[test]
+    fn test_wipe_freed_sectors() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
+
+        region.write_chunk(position_1, CompoundTag::new()).unwrap();
+        region.write_chunk(position_2, CompoundTag::new()).unwrap();
+        region.set_wipe_freed_sectors(true);
+
+        // First chunk doesn't fit anymore and moves to the end, second chunk are removed.
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i32_vec("test_i32_vec", (0..3000).collect());
+
+        region.write_chunk(position_1, large_compound_tag).unwrap();
+        region.remove_chunk(position_2).unwrap();
+
+        assert_eq!(region.get_metadata(&position_1).start_sector_index, 4);
+
+        let sector_len = REGION_SECTOR_BYTES_LENGTH as usize;
+        let data = region.source.get_ref();
+
+        assert!(data[2 * sector_len..4 * sector_len]
+            .iter()
+            .all(|byte| *byte == 0));
+        assert!(region.read_chunk(position_1).is_ok());
+    }
+
+    #[test]
+    fn test_from_into_bytes() {
+        let mut region = Region::from_bytes(2, -1, Vec::new()).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(7, 3), CompoundTag::new())
+            .unwrap();
+
+        let bytes = region.into_bytes().unwrap();
+        assert_eq!(bytes.len(), 3 * REGION_SECTOR_BYTES_LENGTH as usize);
+
+        let region = Region::from_bytes(2, -1, bytes).unwrap();
+
+        assert_eq!(region.position, RegionPosition::new(2, -1));
+        assert!(region.contains_chunk(RegionChunkPosition::new(7, 3)));
+    }
+
+    #[test]
+    fn test_verify_write_positions() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(-1, 2), cursor).unwrap();
+        let position = RegionChunkPosition::new(3, 5);
+        let chunk_position = ChunkPosition::new(-29, 69);
+
+        // Chunk without coordinates are written if verification disabled.
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        region.set_verify_write_positions(true);
+
+        match region.write_chunk(position, CompoundTag::new()) {
+            Err(ChunkWriteError::PositionMismatch { expected, found }) => {
+                assert_eq!(expected, chunk_position);
+                assert_eq!(found, None);
+            }
+            _ => panic!("Expected `PositionMismatch` but got another result"),
+        }
+
+        let wrong_compound_tag = ChunkTagBuilder::new(2586, ChunkPosition::new(-29, 70)).build();
+        let chunks = vec![
+            (position, ChunkTagBuilder::new(3465, chunk_position).build()),
+            (RegionChunkPosition::new(3, 7), wrong_compound_tag),
+        ];
+        let metadata = region.get_metadata(&position);
+
+        match region.write_chunks(chunks) {
+            Err(ChunkWriteError::PositionMismatch { expected, found }) => {
+                assert_eq!(expected, ChunkPosition::new(-29, 71));
+                assert_eq!(found, Some(ChunkPosition::new(-29, 70)));
+            }
+            _ => panic!("Expected `PositionMismatch` but got another result"),
+        }
+
+        // Nothing are written if any chunk position mismatch.
+        assert_eq!(region.get_metadata(&position), metadata);
+
+        region
+            .write_chunk(position, ChunkTagBuilder::new(3465, chunk_position).build())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut region = region_with_gap();
+        region.remove_chunk(RegionChunkPosition::new(1, 0)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(31, 31), CompoundTag::new())
+            .unwrap();
+
+        let positions: Vec<_> = region.positions().collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                RegionChunkPosition::new(0, 0),
+                RegionChunkPosition::new(31, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_chunk_into() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let mut buffer = Vec::new();
+
+        for (x, z) in [(15, 3), (4, 2), (15, 3)] {
+            let position = RegionChunkPosition::new(x, z);
+            let compound_tag = region.read_chunk_into(position, &mut buffer).unwrap();
+            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_tag.get_i32("xPos").unwrap(), x as i32);
+            assert_eq!(level_tag.get_i32("zPos").unwrap(), z as i32);
+            assert_eq!(buffer, region.read_compressed_chunk(position).unwrap().1);
+        }
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let positions = [
+            RegionChunkPosition::new(15, 3),
+            RegionChunkPosition::new(31, 31),
+            RegionChunkPosition::new(4, 2),
+            RegionChunkPosition::new(0, 0),
+        ];
+
+        let results = region.read_chunks(&positions);
+
+        assert_eq!(results.len(), positions.len());
+
+        for (position, result) in positions.iter().zip(results) {
+            match region.read_chunk(*position) {
+                Ok(_) => {
+                    let compound_tag = result.unwrap();
+                    let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+                    assert_eq!(level_tag.get_i32("xPos").unwrap(), position.x() as i32);
+                    assert_eq!(level_tag.get_i32("zPos").unwrap(), position.z() as i32);
+                }
+                Err(_) => assert!(result.is_err()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_chunk_not_found() {
+        let file = File::open("test/empty_region.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let load_error = region
+            .read_chunk(RegionChunkPosition::new(14, 12))
+            .err()
+            .unwrap();
+
+        match load_error {
+            ChunkReadError::ChunkNotFound { position, region } => {
+                assert_eq!(position.x(), 14);
+                assert_eq!(position.z(), 12);
+                assert_eq!(region, Some(RegionPosition::new(0, 0)));
+            }
+            _ => panic!("Expected `ChunkNotFound` but got `{:?}`", load_error),
+        }
+    }
+
+    #[test]
+    fn test_chunk_compression() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let compression_scheme = region
+            .chunk_compression(RegionChunkPosition::new(15, 3))
+            .unwrap();
+
+        assert_eq!(compression_scheme, Some(CompressionScheme::Zlib));
+
+        let compression_scheme = region
+            .chunk_compression(RegionChunkPosition::new(28, 1))
+            .unwrap();
+
+        assert_eq!(compression_scheme, None);
+    }
+
+    #[test]
+    fn test_write_chunk_uncompressed() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        region.set_write_compression_scheme(CompressionScheme::Uncompressed);
+
+        let position = RegionChunkPosition::new(2, 3);
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_str("test_str", "uncompressed");
+
+        region.write_chunk(position, write_compound_tag).unwrap();
+
+        let compression_scheme = region.chunk_compression(position).unwrap();
+        assert_eq!(compression_scheme, Some(CompressionScheme::Uncompressed));
+
+        let read_compound_tag = region.read_chunk(position).unwrap();
+        assert_eq!(
+            read_compound_tag.get_str("test_str").unwrap(),
+            "uncompressed"
+        );
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_write_chunk_lz4() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        region.set_write_compression_scheme(CompressionScheme::Lz4);
+
+        let position = RegionChunkPosition::new(2, 3);
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_i32_vec("test_i32_vec", vec![7; 3000]);
+
+        region.write_chunk(position, write_compound_tag).unwrap();
+
+        let compression_scheme = region.chunk_compression(position).unwrap();
+        assert_eq!(compression_scheme, Some(CompressionScheme::Lz4));
+
+        let read_compound_tag = region.read_chunk(position).unwrap();
+        assert_eq!(
+            read_compound_tag.get_i32_vec("test_i32_vec").unwrap(),
+            &vec![7; 3000]
+        );
+    }
+
+    #[test]
+    fn test_write_chunk_compression_level() {
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_i32_vec("test_i32_vec", (0..3000).map(|x| x % 7).collect());
+
+        let position = RegionChunkPosition::new(2, 3);
+        let mut lengths = Vec::new();
+
+        for &level in &[0, 9] {
+            let cursor = Cursor::new(Vec::new());
+            let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+            region.set_write_compression_scheme(CompressionScheme::Gzip);
+            region.set_write_compression_level(level);
+
+            region
+                .write_chunk(position, write_compound_tag.clone())
+                .unwrap();
+
+            let (length, _) = region.read_chunk_prefix(position).unwrap();
+            lengths.push(length);
+
+            let read_compound_tag = region.read_chunk(position).unwrap();
+            assert_eq!(
+                read_compound_tag.get_i32_vec("test_i32_vec").unwrap().len(),
+                3000
+            );
+        }
+
+        assert!(lengths[1] < lengths[0]);
+    }
+
+    #[test]
+    fn test_write_chunks() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        let chunks = (0..32).map(|x| {
+            let mut compound_tag = CompoundTag::new();
+            compound_tag.insert_i32("x", x);
+
+            (RegionChunkPosition::new(x as u8, 0), compound_tag)
+        });
+
+        region.write_chunks(chunks).unwrap();
+
+        for x in 0..32 {
+            let compound_tag = region.read_chunk(RegionChunkPosition::new(x, 0)).unwrap();
+            assert_eq!(compound_tag.get_i32("x").unwrap(), x as i32);
+        }
+
+        let cursor = Cursor::new(region.source.get_ref().clone());
+        let region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        assert!(region.contains_chunk(RegionChunkPosition::new(31, 0)));
+    }
+
+    #[test]
+    fn test_write_chunks_coalescing() {
+        let chunks: Vec<_> = (0..32)
+            .map(|x| (RegionChunkPosition::new(x, 0), CompoundTag::new()))
+            .collect();
+
+        let source = CountingCursor::default();
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        for (position, compound_tag) in chunks.clone() {
+            region.write_chunk(position, compound_tag).unwrap();
+        }
+
+        let chunk_writes = region.source.writes;
+
+        let source = CountingCursor::default();
+        let mut region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        region.write_chunks(chunks).unwrap();
+
+        // Extending source for every new chunk still takes separate write call.
+        assert_eq!(chunk_writes, 129);
+        assert_eq!(region.source.writes, 35);
+    }
+
+    /// Cursor which counts read and write calls.
+    #[derive(Default)]
+    struct CountingCursor {
+        inner: Cursor<Vec<u8>>,
+        reads: usize,
+        writes: usize,
+    }
+
+    impl Read for CountingCursor {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl Write for CountingCursor {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.writes += 1;
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    impl Seek for CountingCursor {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_load_reads() {
+        let mut data = Vec::new();
+        let mut file = File::open("test/region/r.0.0.mca").unwrap();
+        file.read_to_end(&mut data).unwrap();
+
+        let source = CountingCursor {
+            inner: Cursor::new(data),
+            ..Default::default()
+        };
+
+        let region = Region::load(RegionPosition::new(0, 0), source).unwrap();
+
+        // Header and backup header magic.
+        assert_eq!(region.source.reads, 2);
+    }
+
+    #[test]
+    fn test_load_at() {
+        let region_data = std::fs::read("test/region/r.0.0.mca").unwrap();
+
+        let mut data = vec![1; 1000];
+        data.extend_from_slice(&region_data);
+        data.extend_from_slice(&[1; 1000]);
+
+        let cursor = Cursor::new(data);
+        let mut region = Region::load_at(
+            RegionPosition::new(0, 0),
+            cursor,
+            1000,
+            region_data.len() as u64,
+        )
+        .unwrap();
+
+        let compound_tag = region.read_chunk(RegionChunkPosition::new(15, 3)).unwrap();
+        let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_tag.get_i32("xPos").unwrap(), 15);
+        assert_eq!(level_tag.get_i32("zPos").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_contains_chunk() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(region.contains_chunk(RegionChunkPosition::new(15, 3)));
+        assert!(!region.contains_chunk(RegionChunkPosition::new(28, 1)));
+        assert!(!region.is_empty());
+
+        let file = File::open("test/empty_region.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn test_presence_bitmap() {
+        let region = region_with_gap();
+        let bitmap = region.presence_bitmap();
+
+        assert_eq!(bitmap[0], 0b11);
+        assert!(bitmap[1..].iter().all(|word| *word == 0));
+    }
+
+    #[test]
+    fn test_read_chunk_verify_reads() {
+        let mut region = region_with_gap();
+        region.set_verify_reads(true);
+
+        let position = RegionChunkPosition::new(1, 0);
+        assert!(region.read_chunk(position).is_ok());
+
+        // Another writer changes chunk timestamp in header.
+        let timestamp_offset = REGION_SECTOR_BYTES_LENGTH as usize + position.metadata_index() * 4;
+        region.source.get_mut()[timestamp_offset] ^= 0xFF;
+
+        let error = region.read_chunk(position).err().unwrap();
+
+        match error {
+            ChunkReadError::ConcurrentModification { position, .. } => {
+                assert_eq!(position.x(), 1);
+                assert_eq!(position.z(), 0);
+            }
+            _ => panic!("Expected `ConcurrentModification` but got `{:?}`", error),
+        }
+    }
+
+    #[test]
+    fn test_region_index() {
+        let mut file = File::open("test/region/r.0.0.mca").unwrap();
+        let index = RegionIndex::read(RegionPosition::new(0, 0), &mut file).unwrap();
+
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let position = RegionChunkPosition::new(15, 3);
+
+        assert!(index.contains_chunk(position));
+        assert!(!index.contains_chunk(RegionChunkPosition::new(28, 1)));
+        assert_eq!(
+            index.chunk_last_modified_timestamp(position),
+            region.chunk_last_modified_timestamp(position)
+        );
+        assert_eq!(
+            index.chunks_count() as u32,
+            region
+                .presence_bitmap()
+                .iter()
+                .map(|word| word.count_ones())
+                .sum::<u32>()
+        );
+
+        let mut reader = [0u8; 10].as_ref();
+        let index = RegionIndex::read(RegionPosition::new(0, 0), &mut reader).unwrap();
+        assert_eq!(index.chunks_count(), 0);
+    }
+
+    #[test]
+    fn test_iterate_region() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let mut hit = false;
+
+        for compound_tag in region.into_iter() {
+            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            if level_tag.get_i32("xPos").unwrap() == 15 && level_tag.get_i32("zPos").unwrap() == 3 {
+                hit = true;
+            }
+        }
+
+        assert!(hit);
+    }
+
+    #[test]
+    fn test_iterate_region_not_found() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        for compound_tag in region.into_iter() {
+            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+            if level_tag.get_i32("xPos").unwrap() == 28 && level_tag.get_i32("zPos").unwrap() == 1 {
+                panic!("this chunk should not be hit")
+            }
+        }
+    }
+
+    #[test]
+    fn test_iterate_region_empty() {
+        let file = File::open("test/empty_region.mca").unwrap();
+        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(region.into_iter().next().is_none());
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut region = region_with_gap();
+
+        // Corrupting length of second chunk.
+        let seek_offset = 3 * REGION_SECTOR_BYTES_LENGTH as usize;
+        region.source.get_mut()[seek_offset] = 0xFF;
+
+        let chunks: Vec<_> = region.chunks().collect();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(
+            chunks[0].as_ref().unwrap().0,
+            RegionChunkPosition::new(0, 0)
+        );
+        assert!(chunks[1].is_err());
+
+        let readable_chunks: Vec<_> = region.readable_chunks().collect();
+
+        assert_eq!(readable_chunks.len(), 1);
+        assert_eq!(readable_chunks[0].0, RegionChunkPosition::new(0, 0));
+        assert_eq!(
+            readable_chunks[0]
+                .1
+                .get_i32_vec("test_i32_vec")
+                .unwrap()
+                .len(),
+            3000
+        );
+    }
+
+    #[test]
+    fn test_chunks_empty() {
+        let file = File::open("test/empty_region.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(region.chunks().next().is_none());
+    }
+
+    #[test]
+    fn test_write_chunk_preserve_compression_scheme() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position = RegionChunkPosition::new(3, 4);
+
+        region.set_write_compression_scheme(CompressionScheme::Gzip);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+
+        region.set_write_compression_scheme(CompressionScheme::Zlib);
+        region.set_preserve_compression_scheme(true);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        region
+            .write_chunks(vec![(position, CompoundTag::new())])
+            .unwrap();
+
+        assert_eq!(
+            region.chunk_compression(position).unwrap(),
+            Some(CompressionScheme::Gzip)
+        );
+
+        // New chunk are written with region write compression scheme.
+        let other_position = RegionChunkPosition::new(5, 5);
+        region
+            .write_chunk(other_position, CompoundTag::new())
+            .unwrap();
+
+        assert_eq!(
+            region.chunk_compression(other_position).unwrap(),
+            Some(CompressionScheme::Zlib)
+        );
+
+        region.set_preserve_compression_scheme(false);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+
+        assert_eq!(
+            region.chunk_compression(position).unwrap(),
+            Some(CompressionScheme::Zlib)
+        );
+    }
+
+    #[test]
+    fn test_write_chunk_with_timestamp() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position = RegionChunkPosition::new(3, 4);
+
+        region
+            .write_chunk_with_timestamp(position, CompoundTag::new(), 1570215508)
+            .unwrap();
+
+        assert_eq!(
+            region.chunk_last_modified_timestamp(position),
+            Some(1570215508)
+        );
+
+        // Existing timestamp are kept when chunk are rewritten.
+        region.set_preserve_timestamps(true);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        region
+            .write_chunks(vec![(position, CompoundTag::new())])
+            .unwrap();
+
+        assert_eq!(
+            region.chunk_last_modified_timestamp(position),
+            Some(1570215508)
+        );
+
+        // New chunk gets current time.
+        let other_position = RegionChunkPosition::new(5, 5);
+        region
+            .write_chunk(other_position, CompoundTag::new())
+            .unwrap();
+
+        assert!(
+            region
+                .chunk_last_modified_timestamp(other_position)
+                .unwrap()
+                > 1570215508
+        );
+
+        region.set_preserve_timestamps(false);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+
+        assert!(region.chunk_last_modified_timestamp(position).unwrap() > 1570215508);
+    }
+
+    #[test]
+    fn test_update_metadata() {
+        let cursor = Cursor::new(vec![0; REGION_HEADER_BYTES_LENGTH as usize]);
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let metadata = ChunkMetadata::new(500, 10, region::current_timestamp());
+
+        let position = RegionChunkPosition::new(15, 15);
+
+        region.update_metadata(&position, metadata).unwrap();
+
+        // Reset current cursor position.
+        region.source.set_position(0);
+
+        let chunks_metadata = read_header(&mut *region.source, REGION_HEADER_BYTES_LENGTH).unwrap();
+        let metadata_index = position.metadata_index();
+
+        // In memory metadata.
+        assert_eq!(region.get_metadata(&position), metadata);
+        // Written to file metadata.
+        assert_eq!(chunks_metadata[metadata_index], metadata);
+    }
+
+    #[test]
+    fn test_write_chunk_with_source_extend() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+        write_compound_tag.insert_str("test_str", "test");
+
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag)
+            .unwrap();
+
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
+        );
+
+        assert_eq!(region.used_sectors.len(), 3);
+
+        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(15, 15)).unwrap();
+
+        assert!(read_compound_tag.get_bool("test_bool").unwrap());
+        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
+    }
+
+    #[test]
+    fn test_write_chunk_same_sector() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let mut write_compound_tag_1 = CompoundTag::new();
+        write_compound_tag_1.insert_bool("test_bool", true);
+        write_compound_tag_1.insert_str("test_str", "test");
+        write_compound_tag_1.insert_f32("test_f32", 1.23);
+
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_1)
+            .unwrap();
+
+        let mut write_compound_tag_2 = CompoundTag::new();
+        write_compound_tag_2.insert_bool("test_bool", true);
+        write_compound_tag_2.insert_str("test_str", "test");
+
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
+            .unwrap();
+
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
+        );
+
+        assert_eq!(region.used_sectors.len(), 3);
+
+        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(15, 15)).unwrap();
+
+        assert!(read_compound_tag.get_bool("test_bool").unwrap());
+        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
+        assert!(!read_compound_tag.contains_key("test_f32"));
+    }
+
+    #[test]
+    fn test_write_chunk_same_sector_with_source_expand() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let mut write_compound_tag_1 = CompoundTag::new();
+        write_compound_tag_1.insert_bool("test_bool", true);
+        write_compound_tag_1.insert_str("test_str", "test");
+
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_1)
+            .unwrap();
+
+        let mut write_compound_tag_2 = CompoundTag::new();
+        let mut i32_vec = Vec::new();
+
+        // Extending chunk to second sector.
+        // Due compression we need to write more than 1024 ints.
+        for i in 0..3000 {
+            i32_vec.push(i)
         }
-    }
 
-    fn update_last_modified_timestamp(&mut self) {
-        let system_time = SystemTime::now();
-        let time = system_time.duration_since(UNIX_EPOCH).unwrap();
+        write_compound_tag_2.insert_i32_vec("test_i32_vec", i32_vec);
 
-        self.last_modified_timestamp = time.as_secs() as u32
-    }
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
+            .unwrap();
 
-    fn is_empty(&self) -> bool {
-        self.sectors == 0
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 2
+        );
+
+        assert_eq!(region.used_sectors.len(), 4);
     }
-}
 
-/// Trait adds additional helper methods for `Seek`.
-trait SeekExt {
-    fn len(&mut self) -> Result<u64, io::Error>;
-}
+    #[test]
+    fn test_write_chunk_with_insert_in_middle_gap() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
 
-impl<S: Seek> SeekExt for S {
-    fn len(&mut self) -> Result<u64, Error> {
-        let old_pos = self.seek(SeekFrom::Current(0))?;
-        self.seek(SeekFrom::Start(0))?;
-        let len = self.seek(SeekFrom::End(0))?;
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
+        write_compound_tag.insert_str("test_str", "test");
 
-        if old_pos != len {
-            self.seek(SeekFrom::Start(old_pos))?;
+        // First two sectors are occupied by header.
+        for _ in 0..3 {
+            region.used_sectors.push(true);
         }
 
-        Ok(len)
-    }
-}
+        region.used_sectors.set(2, false);
 
-/// Trait adds additional helper methods for `Seek+Write`.
-trait SeekWriteExt {
-    fn extend_len(&mut self, new_len: u64) -> Result<(), io::Error>;
-}
+        let length = REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 3;
+        region.source.extend_len(length).unwrap();
 
-impl<S: Seek + Write> SeekWriteExt for S {
-    fn extend_len(&mut self, new_len: u64) -> Result<(), Error> {
-        let old_pos = self.seek(SeekFrom::Current(0))?;
-        self.seek(SeekFrom::Start(0))?;
-        let len = self.seek(SeekFrom::End(0))?;
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag)
+            .unwrap();
 
-        if new_len > len {
-            let padding_len = new_len - len;
-            self.write_all(&vec![0; padding_len as usize])?;
+        for i in 0..5 {
+            assert!(region.used_sectors.get(i).unwrap());
         }
 
-        if old_pos != len {
-            self.seek(SeekFrom::Start(old_pos))?;
+        assert_eq!(region.source.len().unwrap(), length);
+        assert_eq!(region.used_sectors.len(), 5);
+    }
+
+    #[test]
+    fn test_write_chunk_not_enough_gap() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let mut write_compound_tag_1 = CompoundTag::new();
+        write_compound_tag_1.insert_bool("test_bool", true);
+        write_compound_tag_1.insert_str("test_str", "test");
+
+        region
+            .write_chunk(
+                RegionChunkPosition::new(15, 15),
+                write_compound_tag_1.clone(),
+            )
+            .unwrap();
+
+        region
+            .write_chunk(RegionChunkPosition::new(0, 0), write_compound_tag_1)
+            .unwrap();
+
+        let mut write_compound_tag_2 = CompoundTag::new();
+        let mut i32_vec = Vec::new();
+
+        // Extending chunk to second sector.
+        // Due compression we need to write more than 1024 ints.
+        for i in 0..3000 {
+            i32_vec.push(i)
         }
 
-        Ok(())
-    }
-}
+        write_compound_tag_2.insert_i32_vec("test_i32_vec", i32_vec);
 
-#[cfg(test)]
-mod tests {
-    use crate::error::ChunkReadError;
-    use crate::position::{RegionChunkPosition, RegionPosition};
-    use crate::region;
-    use crate::region::{
-        read_header, ChunkMetadata, Region, SeekExt, SeekWriteExt, REGION_HEADER_BYTES_LENGTH,
-        REGION_SECTOR_BYTES_LENGTH,
-    };
-    use nbt::CompoundTag;
-    use std::fs::File;
-    use std::io::Cursor;
+        region
+            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
+            .unwrap();
+
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00111011);
+        assert_eq!(region.used_sectors.len(), 6);
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 4
+        );
+    }
 
     #[test]
-    fn test_header_read() {
-        let expected_data = vec![
-            ChunkMetadata::new(61, 2, 1570215508),
-            ChunkMetadata::new(102, 2, 1570215511),
-            ChunkMetadata::new(177, 2, 1570215515),
-            ChunkMetadata::new(265, 2, 1570215519),
-            ChunkMetadata::new(56, 2, 1570215508),
-        ];
+    fn test_remove_chunk() {
+        let mut region = region_with_gap();
+        let position = RegionChunkPosition::new(1, 0);
 
-        let file = File::open("test/region/r.0.0.mca").unwrap();
-        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        assert!(region.remove_chunk(position).unwrap());
+        assert!(!region.remove_chunk(position).unwrap());
 
-        for (index, expected_chunk_metadata) in expected_data.iter().enumerate() {
-            let chunk_metadata = region.chunks_metadata[256 + index];
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00110011);
+        assert_eq!(region.get_metadata(&position), ChunkMetadata::default());
 
-            assert_eq!(&chunk_metadata, expected_chunk_metadata);
+        let error = region.read_chunk(position).err().unwrap();
+
+        match error {
+            ChunkReadError::ChunkNotFound { position, .. } => {
+                assert_eq!(position.x(), 1);
+                assert_eq!(position.z(), 0);
+            }
+            _ => panic!("Expected `ChunkNotFound` but got `{:?}`", error),
         }
+
+        let cursor = Cursor::new(region.source.get_ref().clone());
+        let region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+
+        assert_eq!(region.get_metadata(&position), ChunkMetadata::default());
     }
 
     #[test]
-    fn test_read_chunk() {
+    fn test_copy_raw_to() {
         let file = File::open("test/region/r.0.0.mca").unwrap();
         let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
 
+        let mut buffer = Vec::new();
+        let copied_len = region.copy_raw_to(&mut buffer).unwrap();
+
+        assert_eq!(copied_len, buffer.len() as u64);
+        assert_eq!(buffer, std::fs::read("test/region/r.0.0.mca").unwrap());
+    }
+
+    #[test]
+    fn test_copy_chunk_to() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut source_region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let cursor = Cursor::new(Vec::new());
+        let mut destination_region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        let from = RegionChunkPosition::new(4, 2);
+        let to = RegionChunkPosition::new(7, 9);
+
+        assert!(source_region
+            .copy_chunk_to(&mut destination_region, from, to)
+            .unwrap());
+
+        assert_eq!(
+            destination_region.chunk_last_modified_timestamp(to),
+            source_region.chunk_last_modified_timestamp(from)
+        );
+        assert_eq!(
+            destination_region.read_compressed_chunk(to).unwrap(),
+            source_region.read_compressed_chunk(from).unwrap()
+        );
+
+        let missing_position = RegionChunkPosition::new(31, 31);
+
+        assert!(!source_region
+            .copy_chunk_to(&mut destination_region, missing_position, to)
+            .unwrap());
+    }
+
+    #[test]
+    fn test_replace_from() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut source_region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+
+        let mut buffer = Vec::new();
+        source_region.copy_raw_to(&mut buffer).unwrap();
+
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        region.replace_from(&mut buffer.as_slice()).unwrap();
+
+        assert_eq!(region.source.get_ref(), &buffer);
+
         let compound_tag = region.read_chunk(RegionChunkPosition::new(15, 3)).unwrap();
         let level_tag = compound_tag.get_compound_tag("Level").unwrap();
 
@@ -488,260 +3888,658 @@ mod tests {
     }
 
     #[test]
-    fn test_read_chunk_not_found() {
-        let file = File::open("test/empty_region.mca").unwrap();
-        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+    fn test_replace_from_truncates_source() {
+        let cursor = Cursor::new(Vec::new());
+        let mut small_region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        small_region
+            .write_chunk(RegionChunkPosition::new(1, 0), CompoundTag::new())
+            .unwrap();
 
-        let load_error = region
-            .read_chunk(RegionChunkPosition::new(14, 12))
+        let mut buffer = Vec::new();
+        small_region.copy_raw_to(&mut buffer).unwrap();
+
+        let mut region = region_with_gap();
+        region.set_backup_header(true);
+        region.flush().unwrap();
+
+        assert!(region.backup_header_sector_index.is_some());
+
+        region.replace_from(&mut buffer.as_slice()).unwrap();
+
+        assert_eq!(region.source.get_ref(), &buffer);
+        assert_eq!(region.backup_header_sector_index, None);
+        assert!(region.read_chunk(RegionChunkPosition::new(1, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_replace_from_chunk_outside_of_data() {
+        let buffer = std::fs::read("test/region/r.0.0.mca").unwrap();
+        let truncated_buffer = &buffer[..REGION_HEADER_BYTES_LENGTH as usize + 4096];
+
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        let error = region
+            .replace_from(&mut &truncated_buffer[..])
             .err()
             .unwrap();
 
-        match load_error {
-            ChunkReadError::ChunkNotFound { position } => {
-                assert_eq!(position.x, 14);
-                assert_eq!(position.z, 12);
-            }
-            _ => panic!("Expected `ChunkNotFound` but got `{:?}`", load_error),
-        }
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(region.source.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_replace_from_overlapping_sectors() {
+        let mut source_region = region_with_gap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
+
+        let metadata = source_region.get_metadata(&position_1);
+        source_region.chunks_metadata[position_2.metadata_index()] = metadata;
+        source_region.write_header().unwrap();
+
+        let mut buffer = Vec::new();
+        source_region.copy_raw_to(&mut buffer).unwrap();
+
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+
+        let error = region.replace_from(&mut buffer.as_slice()).err().unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(region.source.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut region = region_with_gap();
+
+        region.compact().unwrap();
+
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 3
+        );
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00011111);
+
+        let read_compound_tag_1 = region.read_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+        let read_compound_tag_2 = region.read_chunk(RegionChunkPosition::new(1, 0)).unwrap();
+
+        assert_eq!(
+            read_compound_tag_1
+                .get_i32_vec("test_i32_vec")
+                .unwrap()
+                .len(),
+            3000
+        );
+        assert!(read_compound_tag_2.get_bool("test_bool").unwrap());
+    }
+
+    #[test]
+    fn test_flush_auto_defrag() {
+        let mut region = region_with_gap();
+        let length = region.source.len().unwrap();
+
+        // One of four data sectors are free.
+        region.set_auto_defrag_threshold(Some(30));
+        region.flush().unwrap();
+
+        assert_eq!(region.source.len().unwrap(), length);
+
+        region.set_auto_defrag_threshold(Some(20));
+        region.flush().unwrap();
+
+        assert_eq!(
+            region.source.len().unwrap(),
+            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 3
+        );
+    }
+
+    #[test]
+    fn test_backup_header() {
+        let mut region = region_with_gap();
+        let length = region.source.len().unwrap();
+
+        region.set_backup_header(true);
+        region.flush().unwrap();
+        region.flush().unwrap();
+
+        let backup_length = BACKUP_HEADER_SECTORS as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        assert_eq!(region.source.len().unwrap(), length + backup_length);
+
+        let mut data = region.source.get_ref().clone();
+
+        // Corrupting primary header.
+        for byte in data[..REGION_SECTOR_BYTES_LENGTH as usize].iter_mut() {
+            *byte = 0xAB;
+        }
+
+        let cursor = Cursor::new(data);
+        let mut region =
+            Region::load_with_backup_header(RegionPosition::new(1, 1), cursor).unwrap();
+
+        let read_compound_tag_1 = region.read_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+        let read_compound_tag_2 = region.read_chunk(RegionChunkPosition::new(1, 0)).unwrap();
+
+        assert_eq!(
+            read_compound_tag_1
+                .get_i32_vec("test_i32_vec")
+                .unwrap()
+                .len(),
+            3000
+        );
+        assert!(read_compound_tag_2.get_bool("test_bool").unwrap());
+
+        region.strip_backup_header().unwrap();
+        assert_eq!(region.source.len().unwrap(), length);
+    }
+
+    #[test]
+    fn test_safe_writes() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position = RegionChunkPosition::new(0, 0);
+
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(&position).start_sector_index, 2);
+
+        region.set_safe_writes(true);
+
+        // Chunk which still fits are written to free sectors instead of the old place.
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        assert_eq!(region.get_metadata(&position).start_sector_index, 3);
+        assert!(!region.used_sectors[2]);
+
+        region
+            .write_chunks(vec![
+                (position, CompoundTag::new()),
+                (position, CompoundTag::new()),
+            ])
+            .unwrap();
+
+        assert_eq!(region.get_metadata(&position).start_sector_index, 2);
+        assert_eq!(region.used_sectors.count_ones(), 3);
+        assert!(region.read_chunk(position).is_ok());
+    }
+
+    #[test]
+    fn test_drop_flushes_region() {
+        let world = temp_world(&[]);
+        let path = world.path().join("drop-flush.mca");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        region.set_backup_header(true);
+        region
+            .write_chunk(RegionChunkPosition::new(2, 2), CompoundTag::new())
+            .unwrap();
+
+        assert!(region.pending_flush.is_some());
+
+        // Backup header are written only on flush.
+        drop(region);
+
+        let file = File::open(&path).unwrap();
+        let mut region = Region::load_with_backup_header(RegionPosition::new(0, 0), file).unwrap();
+
+        assert!(region.backup_header_sector_index.is_some());
+        assert!(region.read_chunk(RegionChunkPosition::new(2, 2)).is_ok());
+    }
+
+    #[test]
+    fn test_sync_all() {
+        let world = temp_world(&[]);
+        let path = world.path().join("sync-all.mca");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(2, 2), CompoundTag::new())
+            .unwrap();
+        region.sync_all().unwrap();
+
+        assert!(region.pending_flush.is_none());
+
+        drop(region);
     }
 
     #[test]
-    fn test_iterate_region() {
-        let file = File::open("test/region/r.0.0.mca").unwrap();
-        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+    fn test_truncate_freed_sectors() {
+        let mut region = region_with_gap();
+        region.set_truncate_freed_sectors(true);
 
-        let mut hit = false;
+        // Chunk in the middle doesn't change source length.
+        region.remove_chunk(RegionChunkPosition::new(1, 0)).unwrap();
+        assert_eq!(
+            region.source.len().unwrap(),
+            6 * REGION_SECTOR_BYTES_LENGTH as u64
+        );
 
-        for compound_tag in region.into_iter() {
-            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+        // Chunk shrinks and moves to the first free sectors.
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", true);
 
-            if level_tag.get_i32("xPos").unwrap() == 15 && level_tag.get_i32("zPos").unwrap() == 3 {
-                hit = true;
-            }
-        }
+        region
+            .write_chunk(RegionChunkPosition::new(0, 0), write_compound_tag)
+            .unwrap();
+        assert_eq!(
+            region.source.len().unwrap(),
+            3 * REGION_SECTOR_BYTES_LENGTH as u64
+        );
 
-        assert_eq!(hit, true);
+        region.remove_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+        assert_eq!(region.source.len().unwrap(), REGION_HEADER_BYTES_LENGTH);
+        assert!(region.is_empty());
     }
 
     #[test]
-    fn test_iterate_region_not_found() {
-        let file = File::open("test/region/r.0.0.mca").unwrap();
-        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+    fn test_truncate_after_last_valid_chunk() {
+        let mut region = region_with_gap();
+        let length = region.source.len().unwrap();
 
-        for compound_tag in region.into_iter() {
-            let level_tag = compound_tag.get_compound_tag("Level").unwrap();
+        // Half-written sector after the last chunk.
+        region.source.get_mut().extend_from_slice(&[1; 1000]);
 
-            if level_tag.get_i32("xPos").unwrap() == 28 && level_tag.get_i32("zPos").unwrap() == 1 {
-                panic!("this chunk should not be hit")
-            }
-        }
-    }
+        assert_eq!(region.truncate_after_last_valid_chunk().unwrap(), 1000);
+        assert_eq!(region.source.len().unwrap(), length);
+        assert_eq!(region.truncate_after_last_valid_chunk().unwrap(), 0);
 
-    #[test]
-    fn test_iterate_region_empty() {
-        let file = File::open("test/empty_region.mca").unwrap();
-        let region = Region::load(RegionPosition::new(0, 0), file).unwrap();
+        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(0, 0)).unwrap();
+        assert_eq!(
+            read_compound_tag.get_i32_vec("test_i32_vec").unwrap().len(),
+            3000
+        );
 
-        for _compound_tag in region {
-            panic!("there should not be anything in there!")
-        }
+        // Chunk points outside of source.
+        let mut metadata = region.get_metadata(&RegionChunkPosition::new(1, 0));
+        metadata.start_sector_index = 100;
+        region.chunks_metadata[RegionChunkPosition::new(1, 0).metadata_index()] = metadata;
+
+        let error = region.truncate_after_last_valid_chunk().err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
     }
 
+    #[cfg(feature = "allocation-trace")]
     #[test]
-    fn test_update_metadata() {
-        let cursor = Cursor::new(vec![0; REGION_HEADER_BYTES_LENGTH as usize]);
+    fn test_allocation_trace() {
+        use crate::trace::AllocationEvent;
+
+        let cursor = Cursor::new(Vec::new());
         let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+        region.start_allocation_trace();
 
-        let mut metadata = ChunkMetadata::new(500, 10, 0);
-        metadata.update_last_modified_timestamp();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
 
-        let position = RegionChunkPosition::new(15, 15);
+        let mut small_compound_tag = CompoundTag::new();
+        small_compound_tag.insert_bool("test_bool", true);
 
-        region.update_metadata(&position, metadata).unwrap();
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i32_vec("test_i32_vec", (0..3000).collect::<Vec<_>>());
 
-        // Reset current cursor position.
-        region.source.set_position(0);
+        region
+            .write_chunk(position_1, small_compound_tag.clone())
+            .unwrap();
+        region
+            .write_chunk(position_2, small_compound_tag.clone())
+            .unwrap();
+        region.write_chunk(position_2, small_compound_tag).unwrap();
+        region.write_chunk(position_1, large_compound_tag).unwrap();
+        region.remove_chunk(position_2).unwrap();
 
-        let chunks_metadata = read_header(&mut region.source, REGION_HEADER_BYTES_LENGTH).unwrap();
-        let metadata_index = position.metadata_index();
+        let allocation_trace = region.take_allocation_trace().unwrap();
 
-        // In memory metadata.
-        assert_eq!(region.get_metadata(&position), metadata);
-        // Written to file metadata.
-        assert_eq!(chunks_metadata[metadata_index], metadata);
+        assert_eq!(
+            allocation_trace.events(),
+            &[
+                AllocationEvent::Extend {
+                    position: position_1,
+                    start_sector_index: 2,
+                    sectors: 1,
+                    extend_sectors: 1,
+                },
+                AllocationEvent::Extend {
+                    position: position_2,
+                    start_sector_index: 3,
+                    sectors: 1,
+                    extend_sectors: 1,
+                },
+                AllocationEvent::Reuse {
+                    position: position_2,
+                    start_sector_index: 3,
+                    sectors: 1,
+                },
+                AllocationEvent::Free {
+                    position: position_1,
+                    start_sector_index: 2,
+                    sectors: 1,
+                },
+                AllocationEvent::Extend {
+                    position: position_1,
+                    start_sector_index: 4,
+                    sectors: 2,
+                    extend_sectors: 2,
+                },
+                AllocationEvent::Free {
+                    position: position_2,
+                    start_sector_index: 3,
+                    sectors: 1,
+                },
+            ]
+        );
+        assert!(region.take_allocation_trace().is_none());
     }
 
     #[test]
-    fn test_write_chunk_with_source_extend() {
-        let cursor = Cursor::new(Vec::new());
-        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+    fn test_validate() {
+        let file = File::open("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), file).unwrap();
 
-        let mut write_compound_tag = CompoundTag::new();
-        write_compound_tag.insert_bool("test_bool", true);
-        write_compound_tag.insert_str("test_str", "test");
+        assert!(region.validate().unwrap().is_valid());
 
-        region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag)
-            .unwrap();
+        let mut region = region_with_gap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
+        let position_3 = RegionChunkPosition::new(2, 0);
+        let position_4 = RegionChunkPosition::new(3, 0);
+
+        // Second chunk overlaps first one and declares length longer than its sector.
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::new(5, 1, 0);
+        region.chunks_metadata[position_3.metadata_index()] = ChunkMetadata::new(1, 1, 0);
+        region.chunks_metadata[position_4.metadata_index()] = ChunkMetadata::new(6, 1, 0);
+
+        let seek_offset = 5 * REGION_SECTOR_BYTES_LENGTH as usize;
+        region.source.get_mut()[seek_offset..seek_offset + 4].copy_from_slice(&[0, 0, 16, 0]);
+
+        let report = region.validate().unwrap();
 
+        assert!(!report.is_valid());
         assert_eq!(
-            region.source.len().unwrap(),
-            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
+            report.issues(),
+            &[
+                ValidationIssue::OverlappingSectors {
+                    position: position_2,
+                    other_position: position_1,
+                },
+                ValidationIssue::LengthExceedsSectors {
+                    position: position_2,
+                    length: 4096,
+                    maximum_length: 4092,
+                },
+                ValidationIssue::OffsetInsideHeader {
+                    position: position_3,
+                    start_sector_index: 1,
+                },
+                ValidationIssue::SectorsBeyondEndOfSource {
+                    position: position_4,
+                    end_sector_index: 7,
+                    total_sectors: 6,
+                },
+            ]
         );
+    }
 
-        assert_eq!(region.used_sectors.len(), 3);
+    #[test]
+    fn test_validate_chunk_prefix() {
+        let mut region = region_with_gap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
 
-        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(15, 15)).unwrap();
+        // First chunk has unknown compression scheme and second one zero length.
+        let start_sector_index = region.get_metadata(&position_1).start_sector_index as usize;
+        let offset = start_sector_index * REGION_SECTOR_BYTES_LENGTH as usize;
+        region.source.get_mut()[offset + 4] = 42;
 
-        assert!(read_compound_tag.get_bool("test_bool").unwrap());
-        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
+        let start_sector_index = region.get_metadata(&position_2).start_sector_index as usize;
+        let offset = start_sector_index * REGION_SECTOR_BYTES_LENGTH as usize;
+        region.source.get_mut()[offset..offset + 4].copy_from_slice(&[0; 4]);
+
+        let report = region.validate().unwrap();
+
+        assert_eq!(
+            report.issues(),
+            &[
+                ValidationIssue::UnsupportedCompressionScheme {
+                    position: position_1,
+                    compression_scheme: 42,
+                },
+                ValidationIssue::ZeroLength {
+                    position: position_2
+                },
+            ]
+        );
     }
 
     #[test]
-    fn test_write_chunk_same_sector() {
-        let cursor = Cursor::new(Vec::new());
-        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+    fn test_open_header_only() {
+        let data = region_with_gap().source.get_ref().clone();
+        let cursor = Cursor::new(data);
+        let mut region = Region::open_header_only(RegionPosition::new(1, 1), cursor).unwrap();
 
-        let mut write_compound_tag_1 = CompoundTag::new();
-        write_compound_tag_1.insert_bool("test_bool", true);
-        write_compound_tag_1.insert_str("test_str", "test");
-        write_compound_tag_1.insert_f32("test_f32", 1.23);
+        assert!(region.used_sectors.is_empty());
 
-        region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_1)
-            .unwrap();
+        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(1, 0)).unwrap();
+        assert!(read_compound_tag.get_bool("test_bool").unwrap());
 
-        let mut write_compound_tag_2 = CompoundTag::new();
-        write_compound_tag_2.insert_bool("test_bool", true);
-        write_compound_tag_2.insert_str("test_str", "test");
+        // Used sectors are computed on write and chunk placed in the gap.
+        let mut write_compound_tag = CompoundTag::new();
+        write_compound_tag.insert_bool("test_bool", false);
 
         region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
+            .write_chunk(RegionChunkPosition::new(2, 0), write_compound_tag)
             .unwrap();
 
+        let metadata = region.get_metadata(&RegionChunkPosition::new(2, 0));
+
+        assert_eq!(metadata.start_sector_index, 2);
+        assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00111111);
         assert_eq!(
             region.source.len().unwrap(),
-            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64
+            6 * REGION_SECTOR_BYTES_LENGTH as u64
         );
+    }
 
-        assert_eq!(region.used_sectors.len(), 3);
-
-        let read_compound_tag = region.read_chunk(RegionChunkPosition::new(15, 15)).unwrap();
-
-        assert!(read_compound_tag.get_bool("test_bool").unwrap());
-        assert_eq!(read_compound_tag.get_str("test_str").unwrap(), "test");
-        assert!(!read_compound_tag.contains_key("test_f32"));
+    #[test]
+    fn test_stats() {
+        let mut region = region_with_gap();
+        let stats = region.stats().unwrap();
+
+        let mut length = [0; 4];
+        let seek_offset = 4 * REGION_SECTOR_BYTES_LENGTH as u64;
+        region.source.seek(SeekFrom::Start(seek_offset)).unwrap();
+        region.source.read_exact(&mut length).unwrap();
+
+        let wasted_bytes = 3 * REGION_SECTOR_BYTES_LENGTH as u64
+            - 8
+            - u32::from_be_bytes(length) as u64
+            - region
+                .read_chunk_prefix(RegionChunkPosition::new(1, 0))
+                .unwrap()
+                .0 as u64;
+
+        assert_eq!(stats.chunks, 2);
+        assert_eq!(stats.used_sectors, 3);
+        assert_eq!(stats.free_sectors, 1);
+        assert_eq!(stats.file_size, 6 * REGION_SECTOR_BYTES_LENGTH as u64);
+        assert_eq!(stats.wasted_bytes, wasted_bytes);
+        assert_eq!(stats.fragmentation, 0.25);
+
+        region.compact().unwrap();
+        let stats = region.stats().unwrap();
+
+        assert_eq!(stats.free_sectors, 0);
+        assert_eq!(stats.file_size, 5 * REGION_SECTOR_BYTES_LENGTH as u64);
+        assert_eq!(stats.fragmentation, 0.0);
     }
 
     #[test]
-    fn test_write_chunk_same_sector_with_source_expand() {
-        let cursor = Cursor::new(Vec::new());
-        let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
+    fn test_rebuild_header() {
+        let data = std::fs::read("test/region/r.0.0.mca").unwrap();
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+        let chunks_metadata = region.chunks_metadata;
+        let chunks = region.chunks().count();
 
-        let mut write_compound_tag_1 = CompoundTag::new();
-        write_compound_tag_1.insert_bool("test_bool", true);
-        write_compound_tag_1.insert_str("test_str", "test");
+        // Zeroing header.
+        region.source.get_mut()[..REGION_HEADER_BYTES_LENGTH as usize].fill(0);
 
-        region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_1)
-            .unwrap();
+        let data = region.source.get_ref().clone();
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
 
-        let mut write_compound_tag_2 = CompoundTag::new();
-        let mut i32_vec = Vec::new();
+        assert_eq!(region.chunks().count(), 0);
+        assert_eq!(region.rebuild_header().unwrap(), chunks);
 
-        // Extending chunk to second sector.
-        // Due compression we need to write more than 1024 ints.
-        for i in 0..3000 {
-            i32_vec.push(i)
+        for (metadata, rebuilt_metadata) in chunks_metadata.iter().zip(region.chunks_metadata) {
+            assert_eq!(
+                metadata.start_sector_index,
+                rebuilt_metadata.start_sector_index
+            );
+            assert_eq!(rebuilt_metadata.last_modified_timestamp, 0);
         }
 
-        write_compound_tag_2.insert_i32_vec("test_i32_vec", i32_vec);
+        assert_eq!(region.readable_chunks().count(), chunks);
 
-        region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
-            .unwrap();
+        region.source.seek(SeekFrom::Start(0)).unwrap();
+        let header = read_header(&mut *region.source, REGION_HEADER_BYTES_LENGTH).unwrap();
+        assert_eq!(header, region.chunks_metadata);
+    }
+
+    #[test]
+    fn test_rebuild_header_skips_oversized_chunk() {
+        let compound_tag = ChunkTagBuilder::new(3465, ChunkPosition::new(1, 1))
+            .insert("test_i64_vec", vec![0i64; 130_750])
+            .build();
+
+        let mut chunk_data = Vec::new();
+        nbt::encode::write_compound_tag(&mut chunk_data, &compound_tag).unwrap();
+
+        // Chunk data takes 256 sectors with prefix.
+        let length = chunk_data.len() as u32 + 1;
+        assert!((length + 4).div_ceil(REGION_SECTOR_BYTES_LENGTH as u32) > 255);
 
+        let mut data = vec![0; REGION_HEADER_BYTES_LENGTH as usize];
+        data.extend_from_slice(&length.to_be_bytes());
+        data.push(CompressionScheme::Uncompressed.id());
+        data.extend_from_slice(&chunk_data);
+
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+
+        assert_eq!(region.rebuild_header().unwrap(), 0);
         assert_eq!(
-            region.source.len().unwrap(),
-            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 2
+            region.get_metadata(&RegionChunkPosition::new(1, 1)),
+            ChunkMetadata::default()
         );
-
-        assert_eq!(region.used_sectors.len(), 4);
     }
 
     #[test]
-    fn test_write_chunk_with_insert_in_middle_gap() {
+    fn test_salvage() {
         let cursor = Cursor::new(Vec::new());
         let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
 
-        let mut write_compound_tag = CompoundTag::new();
-        write_compound_tag.insert_bool("test_bool", true);
-        write_compound_tag.insert_str("test_str", "test");
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(5, 3);
+        let position_3 = RegionChunkPosition::new(7, 7);
 
-        // First two sectors are occupied by header.
-        for _ in 0..3 {
-            region.used_sectors.push(true);
+        for position in &[position_3, position_1, position_2] {
+            let chunk_position =
+                ChunkPosition::new(32 + position.x() as i32, 32 + position.z() as i32);
+            let compound_tag = ChunkTagBuilder::new(3465, chunk_position).build();
+
+            region.write_chunk(*position, compound_tag).unwrap();
         }
 
-        region.used_sectors.set(2, false);
+        // Third chunk moves to the end and leaves previous version in free sector.
+        let large_compound_tag = ChunkTagBuilder::new(3465, ChunkPosition::new(39, 39))
+            .insert("test_i32_vec", (0..3000).collect::<Vec<_>>())
+            .build();
 
-        let length = REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 3;
-        region.source.extend_len(length).unwrap();
+        region.write_chunk(position_3, large_compound_tag).unwrap();
 
-        region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag)
-            .unwrap();
+        // Damaging data of first and third chunks and header entry of second chunk.
+        for sector_index in &[3, 5] {
+            let offset = sector_index * REGION_SECTOR_BYTES_LENGTH as usize + 5;
+            region.source.get_mut()[offset..offset + 8].copy_from_slice(&[0xFF; 8]);
+        }
 
-        for i in 0..5 {
-            assert!(region.used_sectors.get(i).unwrap());
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::default();
+
+        let chunks: Vec<_> = region.salvage().unwrap().collect();
+        assert_eq!(chunks.len(), 3);
+
+        match &chunks[0] {
+            Err(ChunkReadError::TagDecodeError { .. }) => {}
+            _ => panic!("Expected `TagDecodeError` but got another result"),
         }
 
-        assert_eq!(region.source.len().unwrap(), length);
-        assert_eq!(region.used_sectors.len(), 5);
+        let (position, compound_tag) = chunks[1].as_ref().unwrap();
+        assert_eq!(*position, position_2);
+        assert_eq!(compound_tag.get_i32("zPos").unwrap(), 35);
+
+        let (position, compound_tag) = chunks[2].as_ref().unwrap();
+        assert_eq!(*position, position_3);
+        assert!(!compound_tag.contains_key("test_i32_vec"));
     }
 
     #[test]
-    fn test_write_chunk_not_enough_gap() {
+    fn test_salvage_zero_length() {
+        let mut region = region_with_gap();
+        let position = RegionChunkPosition::new(1, 0);
+
+        let start_sector_index = region.get_metadata(&position).start_sector_index as usize;
+        let offset = start_sector_index * REGION_SECTOR_BYTES_LENGTH as usize;
+        region.source.get_mut()[offset..offset + 4].copy_from_slice(&[0; 4]);
+
+        let chunks: Vec<_> = region.salvage().unwrap().collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].is_ok());
+
+        match &chunks[1] {
+            Err(ChunkReadError::ZeroLength {
+                position: Some(chunk_position),
+                ..
+            }) => assert_eq!(*chunk_position, position),
+            result => panic!("Expected `ZeroLength` but got `{:?}`", result),
+        }
+    }
+
+    /// Creates region where second sector after header are free.
+    fn region_with_gap() -> Region<Cursor<Vec<u8>>> {
         let cursor = Cursor::new(Vec::new());
         let mut region = Region::load(RegionPosition::new(1, 1), cursor).unwrap();
 
         let mut write_compound_tag_1 = CompoundTag::new();
         write_compound_tag_1.insert_bool("test_bool", true);
-        write_compound_tag_1.insert_str("test_str", "test");
 
         region
-            .write_chunk(
-                RegionChunkPosition::new(15, 15),
-                write_compound_tag_1.clone(),
-            )
+            .write_chunk(RegionChunkPosition::new(0, 0), write_compound_tag_1.clone())
             .unwrap();
 
         region
-            .write_chunk(RegionChunkPosition::new(0, 0), write_compound_tag_1)
+            .write_chunk(RegionChunkPosition::new(1, 0), write_compound_tag_1)
             .unwrap();
 
         let mut write_compound_tag_2 = CompoundTag::new();
-        let mut i32_vec = Vec::new();
-
-        // Extending chunk to second sector.
-        // Due compression we need to write more than 1024 ints.
-        for i in 0..3000 {
-            i32_vec.push(i)
-        }
-
-        write_compound_tag_2.insert_i32_vec("test_i32_vec", i32_vec);
+        write_compound_tag_2.insert_i32_vec("test_i32_vec", (0..3000).collect::<Vec<_>>());
 
+        // Chunk doesn't fit anymore and moves to the end.
         region
-            .write_chunk(RegionChunkPosition::new(15, 15), write_compound_tag_2)
+            .write_chunk(RegionChunkPosition::new(0, 0), write_compound_tag_2)
             .unwrap();
 
         assert_eq!(region.used_sectors.clone().into_vec()[0], 0b00111011);
-        assert_eq!(region.used_sectors.len(), 6);
-        assert_eq!(
-            region.source.len().unwrap(),
-            REGION_HEADER_BYTES_LENGTH + REGION_SECTOR_BYTES_LENGTH as u64 * 4
-        );
+
+        region
     }
 
     #[test]
@@ -787,4 +4585,163 @@ mod tests {
 
         assert_eq!(len, 10);
     }
+
+    #[test]
+    fn test_error_context() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(-1, 2), cursor).unwrap();
+        let position = RegionChunkPosition::new(4, 2);
+
+        region
+            .write_chunk_raw(position, CompressionScheme::Zlib, b"not a zlib stream")
+            .unwrap();
+
+        let read_error = region.read_chunk(position).err().unwrap();
+
+        match &read_error {
+            ChunkReadError::TagDecodeError {
+                region, position, ..
+            }
+            | ChunkReadError::IOError {
+                region, position, ..
+            } => {
+                assert_eq!(*region, Some(RegionPosition::new(-1, 2)));
+                assert_eq!(*position, Some(RegionChunkPosition::new(4, 2)));
+            }
+            _ => panic!("Expected decode error but got `{:?}`", read_error),
+        }
+
+        assert!(read_error
+            .to_string()
+            .contains("in r.-1.2 at region chunk 4,2"));
+
+        let not_found_error = region
+            .read_chunk(RegionChunkPosition::new(0, 0))
+            .err()
+            .unwrap();
+
+        assert!(not_found_error.to_string().ends_with("not found in r.-1.2"));
+
+        region
+            .write_chunk_raw(position, CompressionScheme::Zlib, b"")
+            .unwrap();
+        region.source.get_mut()[REGION_HEADER_BYTES_LENGTH as usize + 4] = 42;
+
+        match region.read_chunk_raw(position).err().unwrap() {
+            ChunkReadError::UnsupportedCompressionScheme {
+                compression_scheme,
+                region,
+                position,
+            } => {
+                assert_eq!(compression_scheme, 42);
+                assert_eq!(region, Some(RegionPosition::new(-1, 2)));
+                assert_eq!(position, Some(RegionChunkPosition::new(4, 2)));
+            }
+            read_error => panic!(
+                "Expected `UnsupportedCompressionScheme` but got `{:?}`",
+                read_error
+            ),
+        }
+
+        let mut large_compound_tag = CompoundTag::new();
+        large_compound_tag.insert_i8_vec("test_i8_vec", vec![0; 2_000_000]);
+        region.set_write_compression_scheme(CompressionScheme::Uncompressed);
+
+        match region
+            .write_chunk(position, large_compound_tag)
+            .err()
+            .unwrap()
+        {
+            ChunkWriteError::LengthExceedsMaximum {
+                region, position, ..
+            } => {
+                assert_eq!(region, Some(RegionPosition::new(-1, 2)));
+                assert_eq!(position, Some(RegionChunkPosition::new(4, 2)));
+            }
+            write_error => panic!(
+                "Expected `LengthExceedsMaximum` but got `{:?}`",
+                write_error
+            ),
+        }
+    }
+
+    #[test]
+    fn test_invalid_header_entries() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position_1 = RegionChunkPosition::new(0, 0);
+        let position_2 = RegionChunkPosition::new(1, 0);
+
+        region.write_chunk(position_1, CompoundTag::new()).unwrap();
+        region.write_chunk(position_2, CompoundTag::new()).unwrap();
+
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::new(2, 1, 0);
+
+        // Sectors sharing are detected only with read verification.
+        assert!(region.read_chunk(position_2).is_ok());
+
+        region.set_verify_reads(true);
+
+        match region.read_chunk(position_2) {
+            Err(ChunkReadError::OverlappingSectors {
+                position,
+                other_position,
+                ..
+            }) => {
+                assert_eq!(position, position_2);
+                assert_eq!(other_position, position_1);
+            }
+            result => panic!("Expected `OverlappingSectors` but got `{:?}`", result),
+        }
+
+        region.set_verify_reads(false);
+
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::new(3, 0, 0);
+
+        match region.read_chunk(position_2) {
+            Err(ChunkReadError::ZeroSectorsCount {
+                start_sector_index, ..
+            }) => assert_eq!(start_sector_index, 3),
+            result => panic!("Expected `ZeroSectorsCount` but got `{:?}`", result),
+        }
+
+        region.chunks_metadata[position_2.metadata_index()] = ChunkMetadata::new(100, 1, 0);
+
+        match region.read_chunk(position_2) {
+            Err(ChunkReadError::OffsetPastEnd {
+                start_sector_index, ..
+            }) => assert_eq!(start_sector_index, 100),
+            result => panic!("Expected `OffsetPastEnd` but got `{:?}`", result),
+        }
+
+        assert!(region.read_chunk(position_1).is_ok());
+    }
+
+    #[test]
+    fn test_zero_chunk_length() {
+        let cursor = Cursor::new(Vec::new());
+        let mut region = Region::load(RegionPosition::new(0, 0), cursor).unwrap();
+        let position = RegionChunkPosition::new(1, 0);
+
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+
+        let start_sector_index = region.get_metadata(&position).start_sector_index as usize;
+        let mut data = region.into_inner().unwrap().into_inner();
+        let offset = start_sector_index * REGION_SECTOR_BYTES_LENGTH as usize;
+
+        data[offset..offset + 4].copy_from_slice(&[0; 4]);
+
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+
+        match region.read_chunk(position) {
+            Err(ChunkReadError::ZeroLength {
+                region: Some(region_position),
+                position: Some(chunk_position),
+            }) => {
+                assert_eq!(region_position, RegionPosition::new(0, 0));
+                assert_eq!(chunk_position, position);
+            }
+            result => panic!("Expected `ZeroLength` but got `{:?}`", result),
+        }
+    }
 }
@@ -0,0 +1,225 @@
+//! Reusable buffers for encoding chunks which are written to region.
+use crate::builder::BUILDER_CHUNK_MAXIMUM_BYTES_LENGTH;
+use crate::compression::CompressionScheme;
+use crate::error::ChunkWriteError;
+use crate::region::REGION_SECTOR_BYTES_LENGTH;
+use byteorder::{BigEndian, ByteOrder};
+use flate2::write::GzEncoder;
+use flate2::{Compress, Compression, FlushCompress, Status};
+use nbt::encode::write_compound_tag;
+use nbt::CompoundTag;
+use std::io;
+
+/// Length of chunk data prefix: 4 bytes for data length and 1 byte for compression scheme.
+const CHUNK_PREFIX_BYTES_LENGTH: usize = 5;
+
+/// Encodes chunks to data which are written to sectors.
+///
+/// Buffers and zlib compressor are kept between chunks, so writing many chunks
+/// doesn't allocate them for every chunk.
+#[derive(Default)]
+pub(crate) struct ChunkEncoder {
+    /// Uncompressed chunk data.
+    uncompressed_buffer: Vec<u8>,
+    /// Chunk data with length prefix and padding to align sector.
+    chunk_buffer: Vec<u8>,
+    /// Zlib compressor with compression level it was created for.
+    zlib_compress: Option<(u32, Compress)>,
+}
+
+impl ChunkEncoder {
+    /// Encodes chunk with compression scheme and level.
+    ///
+    /// Returns chunk length without padding, encoded data are available with `data`.
+    pub(crate) fn encode(
+        &mut self,
+        chunk_compound_tag: &CompoundTag,
+        compression_scheme: CompressionScheme,
+        compression_level: u32,
+    ) -> Result<u32, ChunkWriteError> {
+        self.start_frame();
+
+        match compression_scheme {
+            CompressionScheme::Gzip => {
+                let compression = Compression::new(compression_level);
+                let mut encoder = GzEncoder::new(&mut self.chunk_buffer, compression);
+                write_compound_tag(&mut encoder, chunk_compound_tag)?;
+                encoder.finish()?;
+            }
+            CompressionScheme::Zlib => {
+                self.uncompressed_buffer.clear();
+                write_compound_tag(&mut self.uncompressed_buffer, chunk_compound_tag)?;
+
+                self.zlib_compress(compression_level)?;
+            }
+            CompressionScheme::Uncompressed => {
+                write_compound_tag(&mut self.chunk_buffer, chunk_compound_tag)?
+            }
+            #[cfg(feature = "lz4")]
+            CompressionScheme::Lz4 => {
+                self.uncompressed_buffer.clear();
+                write_compound_tag(&mut self.uncompressed_buffer, chunk_compound_tag)?;
+
+                let data = crate::lz4::compress(&self.uncompressed_buffer)?;
+                self.chunk_buffer.extend_from_slice(&data);
+            }
+        }
+
+        self.finish_frame(compression_scheme.id())
+    }
+
+    /// Frames already compressed chunk data with compression scheme type id.
+    ///
+    /// Returns chunk length without padding, framed data are available with `data`.
+    pub(crate) fn frame(
+        &mut self,
+        compression_scheme: u8,
+        data: &[u8],
+    ) -> Result<u32, ChunkWriteError> {
+        self.start_frame();
+        self.chunk_buffer.extend_from_slice(data);
+
+        self.finish_frame(compression_scheme)
+    }
+
+    /// Returns last encoded or framed chunk data with length prefix and padding.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.chunk_buffer
+    }
+
+    /// Clears chunk buffer and reserves place for prefix.
+    fn start_frame(&mut self) {
+        self.chunk_buffer.clear();
+        self.chunk_buffer.resize(CHUNK_PREFIX_BYTES_LENGTH, 0);
+    }
+
+    /// Writes prefix and padding to align sector.
+    fn finish_frame(&mut self, compression_scheme: u8) -> Result<u32, ChunkWriteError> {
+        let length = self.chunk_buffer.len() as u32;
+
+        // With padding chunk of maximum length would take 256 sectors which doesn't fit into one byte.
+        if length >= BUILDER_CHUNK_MAXIMUM_BYTES_LENGTH {
+            return Err(ChunkWriteError::LengthExceedsMaximum {
+                length,
+                region: None,
+                position: None,
+            });
+        }
+
+        BigEndian::write_u32(&mut self.chunk_buffer[..4], length - 4);
+        self.chunk_buffer[4] = compression_scheme;
+
+        // Padding to align sector.
+        let padding_len = REGION_SECTOR_BYTES_LENGTH - length as u16 % REGION_SECTOR_BYTES_LENGTH;
+        self.chunk_buffer
+            .resize(length as usize + padding_len as usize, 0);
+
+        Ok(length)
+    }
+
+    /// Compresses uncompressed buffer with zlib to the end of chunk buffer.
+    fn zlib_compress(&mut self, compression_level: u32) -> Result<(), io::Error> {
+        let compress = match &mut self.zlib_compress {
+            Some((level, compress)) if *level == compression_level => {
+                compress.reset();
+                compress
+            }
+            zlib_compress => {
+                let compress = Compress::new(Compression::new(compression_level), true);
+                &mut zlib_compress.insert((compression_level, compress)).1
+            }
+        };
+
+        let mut input = self.uncompressed_buffer.as_slice();
+
+        loop {
+            // Output are written only to spare capacity.
+            self.chunk_buffer.reserve(input.len() / 2 + 64);
+
+            let total_in = compress.total_in();
+            let status =
+                compress.compress_vec(input, &mut self.chunk_buffer, FlushCompress::Finish)?;
+            input = &input[(compress.total_in() - total_in) as usize..];
+
+            if status == Status::StreamEnd {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compression::CompressionScheme;
+    use crate::error::ChunkWriteError;
+    use crate::region::decode_chunk;
+    use crate::scratch::ChunkEncoder;
+    use byteorder::{BigEndian, ByteOrder};
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_encode_reuses_buffers() {
+        let mut encoder = ChunkEncoder::default();
+
+        for (compression_scheme, level) in [
+            (CompressionScheme::Zlib, 6),
+            (CompressionScheme::Zlib, 6),
+            (CompressionScheme::Zlib, 1),
+            (CompressionScheme::Gzip, 6),
+            (CompressionScheme::Uncompressed, 6),
+        ] {
+            let mut compound_tag = CompoundTag::new();
+            compound_tag.insert_i32_vec("test_i32_vec", (0..3000).collect());
+
+            let length = encoder
+                .encode(&compound_tag, compression_scheme, level)
+                .unwrap();
+            let data = encoder.data();
+
+            assert_eq!(data.len() % 4096, 0);
+            assert_eq!(data[4], compression_scheme.id());
+            assert_eq!(BigEndian::read_u32(&data[..4]), length - 4);
+
+            let compound_tag = decode_chunk(data[4], &data[5..length as usize]).unwrap();
+            assert_eq!(
+                compound_tag.get_i32_vec("test_i32_vec").unwrap().len(),
+                3000
+            );
+        }
+    }
+
+    #[test]
+    fn test_frame_length_exceeds_maximum() {
+        let mut encoder = ChunkEncoder::default();
+        let data = vec![0; 4096 * 256];
+
+        match encoder.frame(CompressionScheme::Zlib.id(), &data) {
+            Err(ChunkWriteError::LengthExceedsMaximum { length, .. }) => {
+                assert_eq!(length, 4096 * 256 + 5)
+            }
+            _ => panic!("Expected `LengthExceedsMaximum` but got another result"),
+        }
+    }
+
+    #[test]
+    fn test_frame_sectors_count_boundary() {
+        let mut encoder = ChunkEncoder::default();
+
+        // With prefix and padding chunk takes 256 sectors.
+        let data = vec![0; 4096 * 255 - 5];
+
+        match encoder.frame(CompressionScheme::Zlib.id(), &data) {
+            Err(ChunkWriteError::LengthExceedsMaximum { length, .. }) => {
+                assert_eq!(length, 4096 * 255)
+            }
+            _ => panic!("Expected `LengthExceedsMaximum` but got another result"),
+        }
+
+        // With prefix and padding chunk takes exactly 255 sectors.
+        let data = vec![0; 4096 * 255 - 6];
+        let length = encoder.frame(CompressionScheme::Zlib.id(), &data).unwrap();
+
+        assert_eq!(length, 4096 * 255 - 1);
+        assert_eq!(encoder.data().len(), 4096 * 255);
+    }
+}
@@ -0,0 +1,228 @@
+//! Provider wrapper which can be shared between threads.
+use crate::error::{ChunkReadError, ChunkWriteError};
+use crate::position::{ChunkPosition, RegionPosition};
+use crate::provider::RegionProvider;
+use crate::region::Region;
+use nbt::CompoundTag;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Seek, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Region which are locked by thread accessing it.
+pub type SharedRegion<S> = Arc<Mutex<Region<S>>>;
+
+/// Keeps regions open behind per-region mutexes, so threads access different
+/// regions concurrently and the same region one by one.
+///
+/// Map of open regions are locked only to find region, loading of region
+/// and chunks access are done without global lock. Regions are kept open
+/// until `close_region` called or provider dropped.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::position::ChunkPosition;
+/// use anvil_region::provider::FolderRegionProvider;
+/// use anvil_region::shared::SharedRegionProvider;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let provider = Arc::new(SharedRegionProvider::new(FolderRegionProvider::new("test/region")));
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|x| {
+///         let provider = provider.clone();
+///         thread::spawn(move || provider.load_chunk(ChunkPosition::new(x, 0)).is_ok())
+///     })
+///     .collect();
+///
+/// for handle in handles {
+///     assert!(handle.join().unwrap());
+/// }
+/// ```
+pub struct SharedRegionProvider<S, P> {
+    /// Provider which regions are shared.
+    provider: P,
+    /// Open regions.
+    regions: Mutex<BTreeMap<RegionPosition, SharedRegion<S>>>,
+}
+
+impl<S, P: RegionProvider<S>> SharedRegionProvider<S, P> {
+    pub fn new(provider: P) -> Self {
+        SharedRegionProvider {
+            provider,
+            regions: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns open region, region are loaded from provider if it not open.
+    pub fn region(&self, position: RegionPosition) -> Result<SharedRegion<S>, io::Error> {
+        if let Some(region) = self.lock_regions().get(&position) {
+            return Ok(region.clone());
+        }
+
+        // Region are loaded without global lock, if another thread loaded
+        // the same region meanwhile its region are used.
+        let region = Arc::new(Mutex::new(self.provider.get_region(position)?));
+        let mut regions = self.lock_regions();
+        let region = regions.entry(position).or_insert(region);
+
+        Ok(region.clone())
+    }
+
+    /// Closes region and drops it.
+    ///
+    /// Region which are still used by another thread are kept open, otherwise the next
+    /// access would load it second time while old one are written. Returns `true`
+    /// if region was open and error of `WouldBlock` kind if region are in use.
+    pub fn close_region(&self, position: RegionPosition) -> Result<bool, io::Error> {
+        let mut regions = self.lock_regions();
+
+        match regions.get(&position) {
+            // New references are created only with map locked, so count can't grow here.
+            Some(region) if Arc::strong_count(region) > 1 => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!(
+                    "Region x: {}, z: {} are used by another thread",
+                    position.x, position.z
+                ),
+            )),
+            Some(_) => Ok(regions.remove(&position).is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns amount of open regions.
+    pub fn open_count(&self) -> usize {
+        self.lock_regions().len()
+    }
+
+    /// Returns wrapped provider, open regions are dropped when not used by other threads.
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+
+    fn lock_regions(&self) -> MutexGuard<'_, BTreeMap<RegionPosition, SharedRegion<S>>> {
+        // Map stays consistent even if thread panicked while were holding lock.
+        self.regions
+            .lock()
+            .unwrap_or_else(|poison_error| poison_error.into_inner())
+    }
+}
+
+impl<S: Read + Seek, P: RegionProvider<S>> SharedRegionProvider<S, P> {
+    /// Reads chunk at position in the world.
+    pub fn load_chunk(&self, position: ChunkPosition) -> Result<CompoundTag, ChunkReadError> {
+        let region = self.region(position.region())?;
+        let mut region = lock_region(&region)?;
+
+        region.read_chunk(position.region_chunk())
+    }
+}
+
+impl<S: Write + Seek, P: RegionProvider<S>> SharedRegionProvider<S, P> {
+    /// Writes chunk at position in the world.
+    pub fn save_chunk(
+        &self,
+        position: ChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        let region = self.region(position.region())?;
+        let mut region = lock_region(&region)?;
+
+        region.write_chunk(position.region_chunk(), chunk_compound_tag)
+    }
+
+    /// Flushes all open regions.
+    pub fn flush(&self) -> Result<(), io::Error> {
+        let regions: Vec<_> = self.lock_regions().values().cloned().collect();
+
+        for region in regions {
+            lock_region(&region)?.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Locks region, region which lock are poisoned can be in inconsistent state
+/// and are not accessed.
+fn lock_region<S>(region: &SharedRegion<S>) -> Result<MutexGuard<'_, Region<S>>, io::Error> {
+    region.lock().map_err(|_| {
+        io::Error::other("Region lock are poisoned by thread panicked while were using it")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::FolderRegionProvider;
+    use crate::shared::SharedRegionProvider;
+    use nbt::CompoundTag;
+    use std::io::ErrorKind;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_writes() {
+        let world = temp_world(&[]);
+        let dir = world.path();
+
+        let provider = Arc::new(SharedRegionProvider::new(FolderRegionProvider::new(dir)));
+
+        let handles: Vec<_> = (0..8)
+            .map(|thread_index| {
+                let provider = provider.clone();
+
+                thread::spawn(move || {
+                    for index in 0..16 {
+                        let position = ChunkPosition::new(thread_index * 32 + index, thread_index);
+                        let mut chunk_compound_tag = CompoundTag::new();
+                        chunk_compound_tag.insert_i32("index", index);
+
+                        provider.save_chunk(position, chunk_compound_tag).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(provider.open_count(), 8);
+        provider.flush().unwrap();
+
+        assert!(provider.close_region(RegionPosition::new(0, 0)).unwrap());
+        assert!(!provider.close_region(RegionPosition::new(0, 0)).unwrap());
+
+        for thread_index in 0..8 {
+            for index in 0..16 {
+                let position = ChunkPosition::new(thread_index * 32 + index, thread_index);
+                let chunk_compound_tag = provider.load_chunk(position).unwrap();
+
+                assert_eq!(chunk_compound_tag.get_i32("index").unwrap(), index);
+            }
+        }
+    }
+
+    #[test]
+    fn test_close_region_in_use() {
+        let world = temp_world(&[]);
+        let provider = SharedRegionProvider::new(FolderRegionProvider::new(world.path()));
+        let position = RegionPosition::new(0, 0);
+
+        let region = provider.region(position).unwrap();
+        let error = provider.close_region(position).err().unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        assert!(Arc::ptr_eq(&region, &provider.region(position).unwrap()));
+
+        drop(region);
+
+        assert!(provider.close_region(position).unwrap());
+        assert_eq!(provider.open_count(), 0);
+    }
+}
@@ -0,0 +1,208 @@
+//! User-defined per-chunk metadata stored next to region files.
+//!
+//! Every region metadata are stored in a separate zlib compressed NBT file `r.x.z.meta`.
+use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+use nbt::decode::read_zlib_compound_tag;
+use nbt::encode::write_zlib_compound_tag;
+use nbt::CompoundTag;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Chunks metadata of a single region.
+type RegionChunksMetadata = BTreeMap<RegionChunkPosition, CompoundTag>;
+
+/// Store of arbitrary per-chunk metadata.
+///
+/// Region metadata are loaded on first access and written only on `save`.
+pub struct ChunkSidecar {
+    /// Folder where metadata files located.
+    folder_path: PathBuf,
+    /// Loaded regions metadata.
+    regions: BTreeMap<RegionPosition, RegionChunksMetadata>,
+    /// Regions which metadata was changed since load.
+    modified_regions: BTreeSet<RegionPosition>,
+}
+
+impl ChunkSidecar {
+    /// Creates store of metadata in folder, path are copied so store doesn't borrow it.
+    pub fn new<P: AsRef<Path>>(folder: P) -> ChunkSidecar {
+        ChunkSidecar {
+            folder_path: folder.as_ref().to_path_buf(),
+            regions: BTreeMap::new(),
+            modified_regions: BTreeSet::new(),
+        }
+    }
+
+    /// Returns chunk metadata or `None` if chunk doesn't have any.
+    pub fn get(&mut self, position: ChunkPosition) -> Result<Option<&CompoundTag>, io::Error> {
+        let region_metadata = self.region_metadata(position.region())?;
+
+        Ok(region_metadata.get(&position.region_chunk()))
+    }
+
+    /// Sets chunk metadata replacing previous one.
+    pub fn insert(
+        &mut self,
+        position: ChunkPosition,
+        metadata: CompoundTag,
+    ) -> Result<(), io::Error> {
+        let region_metadata = self.region_metadata(position.region())?;
+        region_metadata.insert(position.region_chunk(), metadata);
+
+        self.modified_regions.insert(position.region());
+
+        Ok(())
+    }
+
+    /// Removes chunk metadata and returns it.
+    pub fn remove(&mut self, position: ChunkPosition) -> Result<Option<CompoundTag>, io::Error> {
+        let region_metadata = self.region_metadata(position.region())?;
+        let metadata = region_metadata.remove(&position.region_chunk());
+
+        if metadata.is_some() {
+            self.modified_regions.insert(position.region());
+        }
+
+        Ok(metadata)
+    }
+
+    /// Writes changed regions metadata to files.
+    ///
+    /// Files of regions without metadata are removed.
+    pub fn save(&mut self) -> Result<(), io::Error> {
+        if !self.modified_regions.is_empty() && !self.folder_path.exists() {
+            fs::create_dir(&self.folder_path)?;
+        }
+
+        for region_position in std::mem::take(&mut self.modified_regions) {
+            let path = self.metadata_path(region_position);
+            let region_metadata = &self.regions[&region_position];
+
+            if region_metadata.is_empty() {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+
+                continue;
+            }
+
+            let mut chunks_compound_tags = Vec::with_capacity(region_metadata.len());
+
+            for (position, metadata) in region_metadata {
+                let mut chunk_compound_tag = CompoundTag::new();
+                chunk_compound_tag.insert_i8("x", position.x() as i8);
+                chunk_compound_tag.insert_i8("z", position.z() as i8);
+                chunk_compound_tag.insert_compound_tag("Data", metadata.clone());
+
+                chunks_compound_tags.push(chunk_compound_tag);
+            }
+
+            let mut compound_tag = CompoundTag::new();
+            compound_tag.insert_compound_tag_vec("Chunks", chunks_compound_tags);
+
+            let mut file = File::create(path)?;
+            write_zlib_compound_tag(&mut file, &compound_tag)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns region metadata loading it from file if necessary.
+    fn region_metadata(
+        &mut self,
+        region_position: RegionPosition,
+    ) -> Result<&mut RegionChunksMetadata, io::Error> {
+        let path = self.metadata_path(region_position);
+
+        match self.regions.entry(region_position) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(read_region_metadata(&path)?)),
+        }
+    }
+
+    fn metadata_path(&self, region_position: RegionPosition) -> PathBuf {
+        let filename = format!("r.{}.{}.meta", region_position.x, region_position.z);
+
+        self.folder_path.join(filename)
+    }
+}
+
+/// Reads region metadata file, missing file are treated as empty metadata.
+fn read_region_metadata(path: &Path) -> Result<RegionChunksMetadata, io::Error> {
+    let mut region_metadata = BTreeMap::new();
+
+    if !path.exists() {
+        return Ok(region_metadata);
+    }
+
+    let mut file = File::open(path)?;
+    let compound_tag = read_zlib_compound_tag(&mut file).map_err(invalid_data)?;
+    let chunks_compound_tags = compound_tag
+        .get_compound_tag_vec("Chunks")
+        .map_err(invalid_data)?;
+
+    for chunk_compound_tag in chunks_compound_tags {
+        let x = chunk_compound_tag.get_i8("x").map_err(invalid_data)?;
+        let z = chunk_compound_tag.get_i8("z").map_err(invalid_data)?;
+        let metadata = chunk_compound_tag
+            .get_compound_tag("Data")
+            .map_err(invalid_data)?;
+
+        // Negative coordinates are wrapped above region bounds.
+        let position = RegionChunkPosition::try_new(x as u8, z as u8).map_err(invalid_data)?;
+
+        region_metadata.insert(position, metadata.clone());
+    }
+
+    Ok(region_metadata)
+}
+
+fn invalid_data<E: std::fmt::Debug>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::ChunkPosition;
+    use crate::sidecar::ChunkSidecar;
+    use nbt::CompoundTag;
+
+    #[test]
+    fn test_sidecar() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("sidecar");
+
+        let position = ChunkPosition::new(-33, 40);
+
+        let mut metadata = CompoundTag::new();
+        metadata.insert_str("claimed_by", "player");
+        metadata.insert_i64("last_rendered_at", 1_600_000_000);
+
+        let mut sidecar = ChunkSidecar::new(&dir);
+        sidecar.insert(position, metadata).unwrap();
+        sidecar
+            .insert(ChunkPosition::new(0, 0), CompoundTag::new())
+            .unwrap();
+        sidecar.save().unwrap();
+
+        assert!(dir.join("r.-2.1.meta").exists());
+        assert!(dir.join("r.0.0.meta").exists());
+
+        let mut sidecar = ChunkSidecar::new(&dir);
+        let metadata = sidecar.get(position).unwrap().unwrap();
+
+        assert_eq!(metadata.get_str("claimed_by").unwrap(), "player");
+        assert_eq!(metadata.get_i64("last_rendered_at").unwrap(), 1_600_000_000);
+        assert!(sidecar.get(ChunkPosition::new(-33, 41)).unwrap().is_none());
+
+        assert!(sidecar.remove(ChunkPosition::new(0, 0)).unwrap().is_some());
+        sidecar.save().unwrap();
+
+        assert!(!dir.join("r.0.0.meta").exists());
+    }
+}
@@ -0,0 +1,109 @@
+//! Region source adapters.
+use std::io;
+use std::io::{Error, Read, Seek, SeekFrom};
+
+/// Read only window into source which starts at offset and has fixed length.
+///
+/// Window start are treated as the source start.
+pub struct Window<S> {
+    /// Source which contains window.
+    inner: S,
+    /// Window start offset in source.
+    offset: u64,
+    /// Window length in bytes.
+    len: u64,
+    /// Current position relative to window start.
+    position: u64,
+}
+
+impl<S: Seek> Window<S> {
+    /// Creates window into source of specified length starting from offset.
+    pub fn new(mut inner: S, offset: u64, len: u64) -> Result<Self, io::Error> {
+        inner.seek(SeekFrom::Start(offset))?;
+
+        let window = Window {
+            inner,
+            offset,
+            len,
+            position: 0,
+        };
+
+        Ok(window)
+    }
+}
+
+impl<S> Window<S> {
+    /// Returns source which contains window.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Read> Read for Window<S> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let remaining_len = self.len.saturating_sub(self.position);
+        let read_len = (buf.len() as u64).min(remaining_len) as usize;
+
+        if read_len == 0 {
+            return Ok(0);
+        }
+
+        let len = self.inner.read(&mut buf[..read_len])?;
+        self.position += len as u64;
+
+        Ok(len)
+    }
+}
+
+impl<S: Seek> Seek for Window<S> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        let position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek to a negative or overflowing position",
+            )
+        })?;
+
+        self.inner.seek(SeekFrom::Start(self.offset + position))?;
+        self.position = position;
+
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::Window;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    #[test]
+    fn test_window_read() {
+        let cursor = Cursor::new(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut window = Window::new(cursor, 2, 4).unwrap();
+
+        let mut buffer = Vec::new();
+        window.read_to_end(&mut buffer).unwrap();
+
+        assert_eq!(buffer, vec![3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_window_seek() {
+        let cursor = Cursor::new(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut window = Window::new(cursor, 2, 4).unwrap();
+
+        assert_eq!(window.seek(SeekFrom::End(-1)).unwrap(), 3);
+
+        let mut buffer = [0; 2];
+        assert_eq!(window.read(&mut buffer).unwrap(), 1);
+        assert_eq!(buffer[0], 6);
+
+        assert!(window.seek(SeekFrom::Current(-5)).is_err());
+    }
+}
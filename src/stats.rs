@@ -0,0 +1,20 @@
+//! Region usage and fragmentation statistics.
+
+/// Usage statistics of region source.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RegionStats {
+    /// Amount of chunks present in region.
+    pub chunks: usize,
+    /// Amount of sectors after header which are used by chunks.
+    pub used_sectors: u32,
+    /// Amount of sectors after header which are not used by chunks.
+    pub free_sectors: u32,
+    /// Source length in bytes.
+    pub file_size: u64,
+    /// Bytes of padding after chunks data in their last sectors.
+    pub wasted_bytes: u64,
+    /// Part of sectors between header and the last used sector which are free, from 0 to 1.
+    ///
+    /// Region without gaps between chunks has zero fragmentation.
+    pub fragmentation: f64,
+}
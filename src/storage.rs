@@ -0,0 +1,234 @@
+//! Storage abstraction which allows regions to live outside the local filesystem.
+//!
+//! Region files are stored as blobs, for example objects of S3 or GCS bucket.
+//! Blobs are loaded to memory, so regions are accessed without network round trips
+//! and written back to storage on flush.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::position::{RegionChunkPosition, RegionPosition};
+//! use anvil_region::provider::RegionProvider;
+//! use anvil_region::storage::{MemoryRegionStorage, StorageRegionProvider};
+//! use nbt::CompoundTag;
+//!
+//! let mut provider = StorageRegionProvider::new(MemoryRegionStorage::default());
+//!
+//! let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! region.write_chunk(RegionChunkPosition::new(4, 2), CompoundTag::new()).unwrap();
+//!
+//! provider.save_region(region).unwrap();
+//! provider.flush().unwrap();
+//!
+//! let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! assert!(region.contains_chunk(RegionChunkPosition::new(4, 2)));
+//! ```
+use crate::position::RegionPosition;
+use crate::provider::RegionProvider;
+use crate::region::Region;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::Cursor;
+#[cfg(any(feature = "zstd", feature = "encryption"))]
+use std::{fs, io::Write, path::Path};
+
+/// Storage of region file blobs addressed by region position.
+pub trait RegionStorage {
+    /// Returns region blob or `None` if region not stored.
+    fn get(&self, position: RegionPosition) -> Result<Option<Vec<u8>>, io::Error>;
+
+    /// Stores region blob replacing previous one.
+    fn put(&mut self, position: RegionPosition, data: Vec<u8>) -> Result<(), io::Error>;
+
+    /// Returns positions of all stored regions.
+    fn list(&self) -> Result<Vec<RegionPosition>, io::Error>;
+}
+
+/// Storage which keeps region blobs in memory.
+#[derive(Default)]
+pub struct MemoryRegionStorage {
+    blobs: BTreeMap<RegionPosition, Vec<u8>>,
+}
+
+impl RegionStorage for MemoryRegionStorage {
+    fn get(&self, position: RegionPosition) -> Result<Option<Vec<u8>>, io::Error> {
+        Ok(self.blobs.get(&position).cloned())
+    }
+
+    fn put(&mut self, position: RegionPosition, data: Vec<u8>) -> Result<(), io::Error> {
+        self.blobs.insert(position, data);
+
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<RegionPosition>, io::Error> {
+        Ok(self.blobs.keys().copied().collect())
+    }
+}
+
+/// Writes data to temporary file next to the path and renames it over the path,
+/// so file are not lost if write interrupted.
+///
+/// Temporary file are synchronized with storage through the handle which wrote it.
+#[cfg(any(feature = "zstd", feature = "encryption"))]
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> Result<(), io::Error> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+
+    fs::rename(&temp_path, path)
+}
+
+/// Provider of in-memory regions loaded from storage.
+///
+/// Changed regions are passed to `save_region` and written to storage on `flush`.
+pub struct StorageRegionProvider<T> {
+    /// Storage where region blobs are kept.
+    storage: T,
+    /// Data of saved regions which are written to storage on flush.
+    staged_regions: BTreeMap<RegionPosition, Vec<u8>>,
+}
+
+impl<T: RegionStorage> StorageRegionProvider<T> {
+    pub fn new(storage: T) -> Self {
+        StorageRegionProvider {
+            storage,
+            staged_regions: BTreeMap::new(),
+        }
+    }
+
+    /// Returns positions of stored regions including saved but not flushed ones.
+    pub fn iter_positions(&self) -> Result<impl Iterator<Item = RegionPosition>, io::Error> {
+        let mut positions = self.storage.list()?;
+        positions.extend(self.staged_regions.keys());
+        positions.sort();
+        positions.dedup();
+
+        Ok(positions.into_iter())
+    }
+
+    /// Stages region data to be written to storage on flush.
+    ///
+    /// Following `get_region` calls return saved region.
+    pub fn save_region(&mut self, region: Region<Cursor<Vec<u8>>>) -> Result<(), io::Error> {
+        let position = region.position();
+        self.staged_regions.insert(position, region.into_bytes()?);
+
+        Ok(())
+    }
+
+    /// Returns `true` if there are saved regions which are not written to storage yet.
+    pub fn has_staged_regions(&self) -> bool {
+        !self.staged_regions.is_empty()
+    }
+
+    /// Writes saved regions to storage.
+    ///
+    /// Regions which failed to be written are kept staged, so flush can be retried.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        while let Some((position, data)) = self.staged_regions.pop_first() {
+            if let Err(io_error) = self.storage.put(position, data.clone()) {
+                self.staged_regions.insert(position, data);
+                return Err(io_error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns storage, saved regions which are not flushed are lost.
+    pub fn into_inner(self) -> T {
+        self.storage
+    }
+}
+
+impl<T: RegionStorage> RegionProvider<Cursor<Vec<u8>>> for StorageRegionProvider<T> {
+    /// Returns region from storage or empty region if storage doesn't contain it.
+    fn get_region(&self, position: RegionPosition) -> Result<Region<Cursor<Vec<u8>>>, io::Error> {
+        let data = match self.staged_regions.get(&position) {
+            Some(data) => data.clone(),
+            None => self.storage.get(position)?.unwrap_or_default(),
+        };
+
+        Region::from_bytes(position.x, position.z, data)
+    }
+
+    /// Returns `true` if region are saved or stored, storage errors are treated as missing region.
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.staged_regions.contains_key(&position)
+            || matches!(self.storage.get(position), Ok(Some(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::RegionProvider;
+    use crate::region::Region;
+    use crate::storage::{MemoryRegionStorage, RegionStorage, StorageRegionProvider};
+    use nbt::CompoundTag;
+    use std::io;
+
+    /// Storage which fails to put blobs.
+    struct ReadOnlyStorage(MemoryRegionStorage);
+
+    impl RegionStorage for ReadOnlyStorage {
+        fn get(&self, position: RegionPosition) -> Result<Option<Vec<u8>>, io::Error> {
+            self.0.get(position)
+        }
+
+        fn put(&mut self, _: RegionPosition, _: Vec<u8>) -> Result<(), io::Error> {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "Read only"))
+        }
+
+        fn list(&self) -> Result<Vec<RegionPosition>, io::Error> {
+            self.0.list()
+        }
+    }
+
+    #[test]
+    fn test_save_region_flush() {
+        let mut provider = StorageRegionProvider::new(MemoryRegionStorage::default());
+        let position = RegionChunkPosition::new(1, 1);
+
+        let mut region = provider.get_region(RegionPosition::new(-1, 3)).unwrap();
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        provider.save_region(region).unwrap();
+
+        assert!(provider.has_staged_regions());
+        assert!(provider.into_inner().list().unwrap().is_empty());
+
+        let mut provider = StorageRegionProvider::new(MemoryRegionStorage::default());
+        let mut region = provider.get_region(RegionPosition::new(-1, 3)).unwrap();
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        provider.save_region(region).unwrap();
+        provider.flush().unwrap();
+
+        assert!(!provider.has_staged_regions());
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(-1, 3)]
+        );
+
+        let storage = provider.into_inner();
+        let data = storage.get(RegionPosition::new(-1, 3)).unwrap().unwrap();
+        let region = Region::from_bytes(-1, 3, data).unwrap();
+
+        assert!(region.contains_chunk(position));
+    }
+
+    #[test]
+    fn test_flush_failure_keeps_staged_regions() {
+        let mut provider = StorageRegionProvider::new(ReadOnlyStorage(Default::default()));
+
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        provider.save_region(region).unwrap();
+
+        assert!(provider.flush().is_err());
+        assert!(provider.has_staged_regions());
+        assert_eq!(provider.iter_positions().unwrap().count(), 1);
+    }
+}
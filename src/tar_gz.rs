@@ -0,0 +1,236 @@
+//! Reader of region files from gzip compressed tar archives, such as world backups.
+use crate::provider::find_region_entries;
+use crate::storage::{MemoryRegionStorage, RegionStorage};
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Length of tar header and data blocks.
+const TAR_BLOCK_LENGTH: u64 = 512;
+/// Type of tar entry which are regular file.
+const TAR_REGULAR_FILE: u8 = b'0';
+/// Type of tar entry which are regular file in archives written by old tar versions.
+const TAR_OLD_REGULAR_FILE: u8 = 0;
+/// Type of GNU tar entry which contains long name of the following entry.
+const TAR_GNU_LONG_NAME: u8 = b'L';
+
+/// Reads region files of world from `.tar.gz` archive to memory.
+///
+/// Region folder are discovered in the same way as in zip archives. Archive are
+/// decompressed twice, first to find region folder and then to read only its region files.
+pub(crate) fn read_tar_gz_regions(path: &Path) -> Result<MemoryRegionStorage, io::Error> {
+    let mut names = Vec::new();
+
+    visit_tar_gz_entries(path, |name, _| {
+        names.push(name.to_owned());
+        Ok(())
+    })?;
+
+    let (_, region_entries) = find_region_entries(names.iter().map(String::as_str));
+
+    let positions: BTreeMap<_, _> = region_entries
+        .into_iter()
+        .map(|(position, name)| (name, position))
+        .collect();
+
+    let mut storage = MemoryRegionStorage::default();
+
+    visit_tar_gz_entries(path, |name, reader| {
+        if let Some(position) = positions.get(name) {
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+
+            storage.put(*position, data)?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(storage)
+}
+
+/// Calls `visit` with name and data reader of every regular file in archive.
+fn visit_tar_gz_entries<F>(path: &Path, mut visit: F) -> Result<(), io::Error>
+where
+    F: FnMut(&str, &mut dyn Read) -> Result<(), io::Error>,
+{
+    let mut reader = GzDecoder::new(BufReader::new(File::open(path)?));
+    let mut header = [0u8; TAR_BLOCK_LENGTH as usize];
+    let mut long_name = None;
+
+    loop {
+        reader.read_exact(&mut header)?;
+
+        // Archive ends with zero blocks.
+        if header.iter().all(|&byte| byte == 0) {
+            return Ok(());
+        }
+
+        let size = parse_octal(&header[124..136])?;
+        let mut data = (&mut reader).take(size);
+
+        match header[156] {
+            TAR_GNU_LONG_NAME => {
+                let mut name = Vec::new();
+                data.read_to_end(&mut name)?;
+
+                long_name = Some(String::from_utf8_lossy(field(&name)).into_owned());
+            }
+            TAR_REGULAR_FILE | TAR_OLD_REGULAR_FILE => {
+                let name = long_name.take().unwrap_or_else(|| header_name(&header));
+                visit(&name, &mut data)?;
+            }
+            _ => long_name = None,
+        }
+
+        // Skipping data which was not read by visitor and padding to the block end.
+        io::copy(&mut data, &mut io::sink())?;
+
+        let padding = (TAR_BLOCK_LENGTH - size % TAR_BLOCK_LENGTH) % TAR_BLOCK_LENGTH;
+        io::copy(&mut (&mut reader).take(padding), &mut io::sink())?;
+    }
+}
+
+/// Returns entry name from header, ustar headers store beginning of long names in prefix.
+fn header_name(header: &[u8]) -> String {
+    let name = String::from_utf8_lossy(field(&header[..100]));
+
+    if &header[257..262] != b"ustar" {
+        return name.into_owned();
+    }
+
+    let prefix = String::from_utf8_lossy(field(&header[345..500]));
+
+    if prefix.is_empty() {
+        name.into_owned()
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Returns header field value up to the first zero byte.
+fn field(bytes: &[u8]) -> &[u8] {
+    let length = bytes
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(bytes.len());
+
+    &bytes[..length]
+}
+
+/// Parses octal number of header field which can be padded with spaces and zero bytes.
+fn parse_octal(bytes: &[u8]) -> Result<u64, io::Error> {
+    let value = String::from_utf8_lossy(field(bytes));
+    let value = value.trim_matches(' ');
+
+    if value.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(value, 8).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid tar header number {:?}", value),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ChunkWriteError;
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::open_world;
+    use crate::storage::RegionStorage;
+    use crate::tar_gz::{read_tar_gz_regions, TAR_BLOCK_LENGTH};
+    use crate::temp::TempWorldProvider;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::fs;
+    use std::fs::File;
+    use std::io;
+    use std::io::Write;
+    use std::path::Path;
+
+    /// Writes `.tar.gz` archive with specified regular files.
+    fn write_tar_gz(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = GzEncoder::new(File::create(path).unwrap(), Compression::fast());
+
+        for (name, data) in entries {
+            let mut header = [0u8; TAR_BLOCK_LENGTH as usize];
+
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[100..107].copy_from_slice(b"0000644");
+            header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            header[263..265].copy_from_slice(b"00");
+
+            // Checksum are computed with checksum field filled with spaces.
+            header[148..156].copy_from_slice(b"        ");
+            let checksum: u32 = header.iter().map(|&byte| byte as u32).sum();
+            header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+
+            writer.write_all(&header).unwrap();
+            writer.write_all(data).unwrap();
+
+            let block_length = TAR_BLOCK_LENGTH as usize;
+            let padding = (block_length - data.len() % block_length) % block_length;
+            writer.write_all(&vec![0; padding]).unwrap();
+        }
+
+        // Archive ends with two zero blocks.
+        writer
+            .write_all(&[0; 2 * TAR_BLOCK_LENGTH as usize])
+            .unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_tar_gz_regions() {
+        let world = TempWorldProvider::new().unwrap();
+        let path = world.path().join("world.tar.gz");
+        let region_data = fs::read("test/region/r.0.0.mca").unwrap();
+
+        write_tar_gz(
+            &path,
+            &[
+                ("World/level.dat", &[1, 2, 3]),
+                ("World/DIM-1/region/r.0.0.mca", &[4; 10]),
+                ("World/region/r.0.0.mca", &region_data),
+            ],
+        );
+
+        let storage = read_tar_gz_regions(&path).unwrap();
+
+        assert_eq!(storage.list().unwrap(), vec![RegionPosition::new(0, 0)]);
+        assert_eq!(
+            storage.get(RegionPosition::new(0, 0)).unwrap().unwrap(),
+            region_data
+        );
+    }
+
+    #[test]
+    fn test_open_world() {
+        let world = TempWorldProvider::new().unwrap();
+        let path = world.path().join("world.tar.gz");
+        let region_data = fs::read("test/region/r.0.0.mca").unwrap();
+
+        write_tar_gz(&path, &[("region/r.0.0.mca", &region_data)]);
+
+        let mut provider = open_world(&path).unwrap();
+        let chunk_compound_tag = provider.load_chunk(ChunkPosition::new(4, 2)).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+
+        match provider.save_chunk(ChunkPosition::new(4, 2), chunk_compound_tag) {
+            Err(ChunkWriteError::IOError { io_error, .. }) => {
+                assert_eq!(io_error.kind(), io::ErrorKind::Unsupported)
+            }
+            result => panic!("Expected unsupported error, got {:?}", result),
+        }
+    }
+}
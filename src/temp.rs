@@ -0,0 +1,145 @@
+//! Temporary world which are removed when no longer needed.
+use crate::position::RegionPosition;
+use crate::provider::{FolderRegionProvider, RegionKind, RegionProvider};
+use crate::region::Region;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Counter which makes names of temporary worlds created by this process unique.
+static TEMP_WORLD_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Provider of regions in temporary world folder with `region` folder.
+///
+/// World folder with all its content are removed on drop.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::fixtures::minimal_chunk;
+/// use anvil_region::position::{RegionChunkPosition, RegionPosition};
+/// use anvil_region::provider::RegionProvider;
+/// use anvil_region::temp::TempWorldProvider;
+///
+/// let provider = TempWorldProvider::new().unwrap();
+/// let world_path = provider.path().to_path_buf();
+///
+/// let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+/// region.write_chunk(RegionChunkPosition::new(4, 2), minimal_chunk(4, 2)).unwrap();
+/// drop(region);
+///
+/// drop(provider);
+/// assert!(!world_path.exists());
+/// ```
+pub struct TempWorldProvider {
+    /// Temporary world folder.
+    path: PathBuf,
+    /// Provider of regions in `region` folder.
+    provider: FolderRegionProvider,
+    /// Whether world folder are removed on drop.
+    remove_on_drop: bool,
+}
+
+impl TempWorldProvider {
+    /// Creates empty world folder with `region` folder in system temporary directory.
+    pub fn new() -> Result<Self, io::Error> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let counter = TEMP_WORLD_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        let name = format!(
+            "anvil-region-temp-world-{}-{}-{}",
+            process::id(),
+            time.as_nanos(),
+            counter
+        );
+
+        let path = std::env::temp_dir().join(name);
+        fs::create_dir(&path)?;
+
+        let region_folder_path = path.join(RegionKind::Blocks.folder_name());
+
+        if let Err(io_error) = fs::create_dir(&region_folder_path) {
+            let _ = fs::remove_dir_all(&path);
+            return Err(io_error);
+        }
+
+        Ok(TempWorldProvider {
+            path,
+            provider: FolderRegionProvider::new(region_folder_path),
+            remove_on_drop: true,
+        })
+    }
+
+    /// Returns temporary world folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns provider of regions in `region` folder.
+    pub fn provider(&self) -> &FolderRegionProvider {
+        &self.provider
+    }
+
+    /// Returns temporary world folder which are kept after drop.
+    pub fn into_path(mut self) -> PathBuf {
+        self.remove_on_drop = false;
+        self.path.clone()
+    }
+}
+
+impl RegionProvider<File> for TempWorldProvider {
+    fn get_region(&self, position: RegionPosition) -> Result<Region<File>, io::Error> {
+        self.provider.get_region(position)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.provider.region_exists(position)
+    }
+
+    fn remove_region(&self, position: RegionPosition) -> Result<bool, io::Error> {
+        self.provider.remove_region(position)
+    }
+}
+
+impl Drop for TempWorldProvider {
+    /// Removes world folder, regions which are still open are not written anymore.
+    fn drop(&mut self) {
+        if self.remove_on_drop {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::position::RegionPosition;
+    use crate::provider::RegionProvider;
+    use crate::temp::TempWorldProvider;
+    use std::fs;
+
+    #[test]
+    fn test_unique_and_removed() {
+        let provider = TempWorldProvider::new().unwrap();
+        let other_provider = TempWorldProvider::new().unwrap();
+
+        assert_ne!(provider.path(), other_provider.path());
+        assert!(provider.path().join("region").is_dir());
+
+        provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(provider.region_exists(RegionPosition::new(0, 0)));
+        assert!(!other_provider.region_exists(RegionPosition::new(0, 0)));
+
+        let path = provider.path().to_path_buf();
+        drop(provider);
+        assert!(!path.exists());
+
+        let path = other_provider.into_path();
+        assert!(path.exists());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
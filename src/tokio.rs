@@ -0,0 +1,521 @@
+//! Asynchronous region and provider for Tokio runtime.
+//!
+//! Mirrors basic synchronous API, so chunks can be accessed without `spawn_blocking`.
+//! Crate format extensions such as backup header, safe writes and event log are not supported,
+//! regions with backup header can be read but not written.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::position::{RegionChunkPosition, RegionPosition};
+//! use anvil_region::tokio::AsyncFolderRegionProvider;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let provider = AsyncFolderRegionProvider::new("test/region");
+//! let mut region = provider.get_region(RegionPosition::new(0, 0)).await.unwrap();
+//!
+//! let chunk_compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).await.unwrap();
+//! let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+//!
+//! assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+//! # });
+//! ```
+use crate::compression::CompressionScheme;
+use crate::error::{ChunkReadError, ChunkWriteError};
+use crate::position::{RegionChunkPosition, RegionPosition};
+use crate::provider::region_position_filename;
+use crate::region::{
+    allocate_sectors, backup_header_sector_index, current_timestamp, decode_chunk, free_sectors,
+    header_bytes, read_header, sectors_required, total_sectors, used_sectors, ChunkMetadata,
+    BACKUP_HEADER_MAGIC, CHUNK_MAXIMUM_BYTES_LENGTH, DEFAULT_COMPRESSION_LEVEL, REGION_CHUNKS,
+    REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use crate::scratch::ChunkEncoder;
+use bitvec::prelude::*;
+use nbt::CompoundTag;
+use std::io;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+/// Region which source are accessed asynchronously.
+pub struct AsyncRegion<S> {
+    /// Region position in the world.
+    position: RegionPosition,
+    /// Source in which region are stored.
+    source: S,
+    /// Array of chunks metadata.
+    chunks_metadata: [ChunkMetadata; REGION_CHUNKS],
+    /// Used sectors for chunks data.
+    used_sectors: BitVec,
+    /// Whether source ends with backup header which can't be updated asynchronously.
+    backup_header: bool,
+    /// Compression scheme which are used to write chunks.
+    write_compression_scheme: CompressionScheme,
+    /// Compression level from 0 to 9 which are used to write Gzip and Zlib compressed chunks.
+    write_compression_level: u32,
+    /// Buffers which are reused to encode written chunks.
+    chunk_encoder: ChunkEncoder,
+}
+
+impl<S> AsyncRegion<S> {
+    /// Returns region position in the world.
+    pub fn position(&self) -> RegionPosition {
+        self.position
+    }
+
+    /// Returns `true` if chunk present in region.
+    pub fn contains_chunk(&self, position: RegionChunkPosition) -> bool {
+        !self.chunks_metadata[position.metadata_index()].is_empty()
+    }
+
+    /// Returns last time in seconds when chunk was modified or `None` if chunk not present.
+    pub fn chunk_last_modified_timestamp(&self, position: RegionChunkPosition) -> Option<u32> {
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        if metadata.is_empty() {
+            return None;
+        }
+
+        Some(metadata.last_modified_timestamp)
+    }
+
+    /// Sets compression scheme which are used to write chunks.
+    pub fn set_write_compression_scheme(&mut self, compression_scheme: CompressionScheme) {
+        self.write_compression_scheme = compression_scheme;
+    }
+
+    /// Sets compression level from 0 to 9 which are used to write Gzip and Zlib
+    /// compressed chunks.
+    pub fn set_write_compression_level(&mut self, level: u32) {
+        self.write_compression_level = level.min(9);
+    }
+
+    /// Returns underlying source.
+    ///
+    /// Unlike synchronous region source are not flushed, call `flush` before.
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> AsyncRegion<S> {
+    /// Loads region by reading header from source.
+    ///
+    /// Source shorter than header are treated as empty region. If backup header
+    /// present at the end of source, region can be read but not written.
+    pub async fn load(position: RegionPosition, mut source: S) -> Result<Self, io::Error> {
+        let source_len = source.seek(SeekFrom::End(0)).await?;
+        source.seek(SeekFrom::Start(0)).await?;
+
+        let mut buffer = Vec::with_capacity(REGION_HEADER_BYTES_LENGTH as usize);
+        (&mut source)
+            .take(REGION_HEADER_BYTES_LENGTH)
+            .read_to_end(&mut buffer)
+            .await?;
+
+        let chunks_metadata = read_header(&mut buffer.as_slice(), buffer.len() as u64)?;
+        let used_sectors = used_sectors(total_sectors(source_len), &chunks_metadata);
+        let backup_header = has_backup_header(&mut source, source_len).await?;
+
+        Ok(AsyncRegion {
+            position,
+            source,
+            chunks_metadata,
+            used_sectors,
+            backup_header,
+            write_compression_scheme: CompressionScheme::Zlib,
+            write_compression_level: DEFAULT_COMPRESSION_LEVEL,
+            chunk_encoder: ChunkEncoder::default(),
+        })
+    }
+
+    /// Reads chunk, errors contain position of region and chunk.
+    pub async fn read_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<CompoundTag, ChunkReadError> {
+        self.read_chunk_data(position)
+            .await
+            .map_err(|read_error| read_error.with_context(self.position, Some(position)))
+    }
+
+    async fn read_chunk_data(
+        &mut self,
+        position: RegionChunkPosition,
+    ) -> Result<CompoundTag, ChunkReadError> {
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        if metadata.is_empty() {
+            return Err(ChunkReadError::ChunkNotFound {
+                position,
+                region: None,
+            });
+        }
+
+        let seek_offset = metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+        let maximum_length = (metadata.sectors as u32 * REGION_SECTOR_BYTES_LENGTH as u32)
+            .min(CHUNK_MAXIMUM_BYTES_LENGTH);
+
+        self.source.seek(SeekFrom::Start(seek_offset)).await?;
+        let length = self.source.read_u32().await?;
+
+        if length == 0 {
+            return Err(ChunkReadError::ZeroLength {
+                region: None,
+                position: Some(position),
+            });
+        }
+
+        if length > maximum_length {
+            return Err(ChunkReadError::LengthExceedsMaximum {
+                length,
+                maximum_length,
+                region: None,
+                position: None,
+            });
+        }
+
+        let compression_scheme = self.source.read_u8().await?;
+
+        // Length includes compression scheme byte.
+        let mut buffer = vec![0; length.saturating_sub(1) as usize];
+        self.source.read_exact(&mut buffer).await?;
+
+        decode_chunk(compression_scheme, &buffer)
+    }
+}
+
+impl<S: AsyncWrite + AsyncSeek + Unpin> AsyncRegion<S> {
+    /// Writes chunk, errors contain position of region and chunk.
+    pub async fn write_chunk(
+        &mut self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        self.write_chunk_data(position, chunk_compound_tag)
+            .await
+            .map_err(|write_error| write_error.with_context(self.position, Some(position)))
+    }
+
+    async fn write_chunk_data(
+        &mut self,
+        position: RegionChunkPosition,
+        chunk_compound_tag: CompoundTag,
+    ) -> Result<(), ChunkWriteError> {
+        self.check_writable()?;
+
+        let length = self.chunk_encoder.encode(
+            &chunk_compound_tag,
+            self.write_compression_scheme,
+            self.write_compression_level,
+        )?;
+
+        let mut metadata = self.find_place(&position, length).await?;
+        let seek_offset = metadata.start_sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+        self.source.seek(SeekFrom::Start(seek_offset)).await?;
+        self.source.write_all(self.chunk_encoder.data()).await?;
+
+        metadata.last_modified_timestamp = current_timestamp();
+        self.update_metadata(&position, metadata).await?;
+
+        Ok(())
+    }
+
+    /// Removes chunk from region and releases sectors used by it.
+    ///
+    /// Returns `true` if chunk was present in region.
+    pub async fn remove_chunk(&mut self, position: RegionChunkPosition) -> Result<bool, io::Error> {
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        if metadata.is_empty() {
+            return Ok(false);
+        }
+
+        self.check_writable()?;
+        self.release_sectors(&position, metadata);
+        self.update_metadata(&position, ChunkMetadata::default())
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Flushes source.
+    ///
+    /// Unlike synchronous region, region are not compacted and backup header are not written.
+    pub async fn flush(&mut self) -> Result<(), io::Error> {
+        self.source.flush().await
+    }
+
+    /// Finds a place where chunk data of a given length can be put.
+    ///
+    /// If cannot find a place to put chunk data will extend source.
+    async fn find_place(
+        &mut self,
+        position: &RegionChunkPosition,
+        chunk_length: u32,
+    ) -> Result<ChunkMetadata, io::Error> {
+        let source_len = self.source.seek(SeekFrom::End(0)).await?;
+
+        // Header are written before the first chunk of empty source.
+        if source_len < REGION_HEADER_BYTES_LENGTH {
+            let buffer = header_bytes(&self.chunks_metadata)?;
+
+            self.source.seek(SeekFrom::Start(0)).await?;
+            self.source.write_all(&buffer).await?;
+        }
+
+        let sectors_required = sectors_required(chunk_length)?;
+        let metadata = self.chunks_metadata[position.metadata_index()];
+
+        // Chunk still fits in the old place.
+        if metadata.sectors == sectors_required {
+            return Ok(metadata);
+        }
+
+        self.release_sectors(position, metadata);
+
+        let (start_sector_index, _) = allocate_sectors(&mut self.used_sectors, sectors_required);
+
+        Ok(ChunkMetadata::new(start_sector_index, sectors_required, 0))
+    }
+
+    /// Marks chunk sectors as free, sectors shared with another chunk are kept used.
+    fn release_sectors(&mut self, position: &RegionChunkPosition, metadata: ChunkMetadata) {
+        free_sectors(
+            &mut self.used_sectors,
+            &self.chunks_metadata,
+            position.metadata_index(),
+            metadata,
+        );
+    }
+
+    /// Returns error if region can't be changed asynchronously.
+    ///
+    /// Backup header are not updated by asynchronous writes, so stale backup
+    /// header would be used to recover region.
+    fn check_writable(&self) -> Result<(), io::Error> {
+        if self.backup_header {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Region with backup header can't be written asynchronously",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Updates chunk metadata.
+    async fn update_metadata(
+        &mut self,
+        position: &RegionChunkPosition,
+        metadata: ChunkMetadata,
+    ) -> Result<(), io::Error> {
+        let metadata_index = position.metadata_index();
+        self.chunks_metadata[metadata_index] = metadata;
+
+        let offset = (metadata.start_sector_index << 8) | metadata.sectors as u32;
+        let timestamp_seek_offset = REGION_SECTOR_BYTES_LENGTH as u64 + metadata_index as u64 * 4;
+
+        self.source
+            .seek(SeekFrom::Start(metadata_index as u64 * 4))
+            .await?;
+        self.source.write_u32(offset).await?;
+
+        self.source
+            .seek(SeekFrom::Start(timestamp_seek_offset))
+            .await?;
+        self.source
+            .write_u32(metadata.last_modified_timestamp)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns `true` if source ends with backup header.
+async fn has_backup_header<S: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut S,
+    source_len: u64,
+) -> Result<bool, io::Error> {
+    let sector_index = match backup_header_sector_index(source_len) {
+        Some(sector_index) => sector_index,
+        None => return Ok(false),
+    };
+
+    let mut magic = vec![0; BACKUP_HEADER_MAGIC.len()];
+
+    source
+        .seek(SeekFrom::Start(
+            sector_index as u64 * REGION_SECTOR_BYTES_LENGTH as u64,
+        ))
+        .await?;
+    source.read_exact(&mut magic).await?;
+
+    Ok(magic == BACKUP_HEADER_MAGIC)
+}
+
+/// Asynchronous provider of regions stored in folder.
+pub struct AsyncFolderRegionProvider {
+    /// Folder where region files located.
+    folder_path: PathBuf,
+}
+
+impl AsyncFolderRegionProvider {
+    /// Creates provider of regions in folder, path are copied so provider doesn't borrow it.
+    pub fn new<P: AsRef<Path>>(folder: P) -> AsyncFolderRegionProvider {
+        AsyncFolderRegionProvider {
+            folder_path: folder.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Opens region file, folder and file are created if not exists.
+    pub async fn get_region(
+        &self,
+        position: RegionPosition,
+    ) -> Result<AsyncRegion<File>, io::Error> {
+        fs::create_dir_all(&self.folder_path).await?;
+
+        let region_name = region_position_filename(position);
+        let region_path = self.folder_path.join(region_name);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .create(true)
+            .truncate(false)
+            .open(region_path)
+            .await?;
+
+        AsyncRegion::load(position, file).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::region::Region;
+    use crate::tokio::{AsyncFolderRegionProvider, AsyncRegion};
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn test_read_chunk() {
+        let data = fs::read("test/region/r.0.0.mca").unwrap();
+        let mut region = AsyncRegion::load(RegionPosition::new(0, 0), Cursor::new(data))
+            .await
+            .unwrap();
+
+        let compound_tag = region
+            .read_chunk(RegionChunkPosition::new(4, 2))
+            .await
+            .unwrap();
+        let level_compound_tag = compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+        assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), 2);
+        assert!(region
+            .read_chunk(RegionChunkPosition::new(31, 31))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_remove_chunk() {
+        let mut region = AsyncRegion::load(RegionPosition::new(0, 0), Cursor::new(Vec::new()))
+            .await
+            .unwrap();
+
+        for index in 0..3 {
+            let mut compound_tag = CompoundTag::new();
+            compound_tag.insert_i32_vec("test_i32_vec", vec![index; 2000]);
+
+            region
+                .write_chunk(RegionChunkPosition::new(index as u8, 0), compound_tag)
+                .await
+                .unwrap();
+        }
+
+        assert!(region
+            .remove_chunk(RegionChunkPosition::new(1, 0))
+            .await
+            .unwrap());
+        assert!(!region.contains_chunk(RegionChunkPosition::new(1, 0)));
+
+        // Released sectors are reused.
+        region
+            .write_chunk(RegionChunkPosition::new(3, 0), CompoundTag::new())
+            .await
+            .unwrap();
+        region.flush().await.unwrap();
+
+        let data = region.into_inner().into_inner();
+        assert_eq!(data.len(), 4096 * 5);
+
+        // Data are readable by synchronous region.
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(data)).unwrap();
+
+        assert_eq!(
+            region.chunk_start_sector_index(&RegionChunkPosition::new(3, 0)),
+            Some(3)
+        );
+
+        let compound_tag = region.read_chunk(RegionChunkPosition::new(2, 0)).unwrap();
+        assert_eq!(
+            compound_tag.get_i32_vec("test_i32_vec").unwrap(),
+            &vec![2; 2000]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backup_header_region_not_written() {
+        let position = RegionChunkPosition::new(1, 1);
+
+        let mut region = Region::load(RegionPosition::new(0, 0), Cursor::new(Vec::new())).unwrap();
+        region.set_backup_header(true);
+        region.write_chunk(position, CompoundTag::new()).unwrap();
+        let data = region.into_inner().unwrap().into_inner();
+
+        let mut region = AsyncRegion::load(RegionPosition::new(0, 0), Cursor::new(data.clone()))
+            .await
+            .unwrap();
+
+        assert!(region.read_chunk(position).await.is_ok());
+        assert!(region
+            .write_chunk(RegionChunkPosition::new(2, 2), CompoundTag::new())
+            .await
+            .is_err());
+        assert!(region.remove_chunk(position).await.is_err());
+        assert_eq!(region.into_inner().into_inner(), data);
+    }
+
+    #[tokio::test]
+    async fn test_folder_provider() {
+        let world = temp_world(&[]);
+        let dir = world.path().join("async-provider");
+
+        let provider = AsyncFolderRegionProvider::new(&dir);
+        let position = RegionChunkPosition::new(7, 7);
+
+        let mut region = provider
+            .get_region(RegionPosition::new(-1, 0))
+            .await
+            .unwrap();
+        region
+            .write_chunk(position, CompoundTag::new())
+            .await
+            .unwrap();
+        region.flush().await.unwrap();
+
+        let region = provider
+            .get_region(RegionPosition::new(-1, 0))
+            .await
+            .unwrap();
+        assert!(region.contains_chunk(position));
+        assert!(region.chunk_last_modified_timestamp(position).unwrap() > 0);
+    }
+}
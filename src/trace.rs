@@ -0,0 +1,54 @@
+//! Tracing of sector allocation decisions made by region.
+use crate::position::RegionChunkPosition;
+
+/// Sector allocation decision made while writing or removing chunk.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum AllocationEvent {
+    /// Chunk still fits in the sectors which it used before.
+    Reuse {
+        position: RegionChunkPosition,
+        start_sector_index: u32,
+        sectors: u8,
+    },
+    /// Sectors previously used by chunk are released.
+    Free {
+        position: RegionChunkPosition,
+        start_sector_index: u32,
+        sectors: u8,
+    },
+    /// Chunk placed in the gap between used sectors.
+    Allocate {
+        position: RegionChunkPosition,
+        start_sector_index: u32,
+        sectors: u8,
+    },
+    /// Source extended to place chunk at the end.
+    Extend {
+        position: RegionChunkPosition,
+        start_sector_index: u32,
+        sectors: u8,
+        /// Amount of sectors added to the source.
+        extend_sectors: u8,
+    },
+}
+
+/// Records allocation events in the order they happen.
+#[derive(Debug, Default, Clone)]
+pub struct AllocationTrace {
+    events: Vec<AllocationEvent>,
+}
+
+impl AllocationTrace {
+    pub fn new() -> Self {
+        AllocationTrace { events: Vec::new() }
+    }
+
+    /// Returns recorded events.
+    pub fn events(&self) -> &[AllocationEvent] {
+        &self.events
+    }
+
+    pub(crate) fn record(&mut self, event: AllocationEvent) {
+        self.events.push(event);
+    }
+}
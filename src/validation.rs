@@ -0,0 +1,64 @@
+//! Region integrity validation report.
+use crate::position::RegionChunkPosition;
+
+/// Problem found in region during validation.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ValidationIssue {
+    /// Chunk data starts inside header.
+    OffsetInsideHeader {
+        position: RegionChunkPosition,
+        start_sector_index: u32,
+    },
+    /// Chunk sectors extends beyond the end of source.
+    SectorsBeyondEndOfSource {
+        position: RegionChunkPosition,
+        /// Sector index after the last chunk sector.
+        end_sector_index: u32,
+        /// Amount of sectors in source.
+        total_sectors: u32,
+    },
+    /// Chunk sectors are overlapping with sectors of another chunk.
+    OverlappingSectors {
+        position: RegionChunkPosition,
+        /// Position of chunk which sectors are overlapped.
+        other_position: RegionChunkPosition,
+    },
+    /// Chunk declared length doesn't fit into chunk sectors.
+    LengthExceedsSectors {
+        position: RegionChunkPosition,
+        /// Chunk declared length.
+        length: u32,
+        /// Chunk maximum length allowed by sectors.
+        maximum_length: u32,
+    },
+    /// Chunk declared length are zero, so chunk has no data.
+    ZeroLength { position: RegionChunkPosition },
+    /// Chunk compression scheme are unknown.
+    UnsupportedCompressionScheme {
+        position: RegionChunkPosition,
+        /// Compression scheme type id.
+        compression_scheme: u8,
+    },
+}
+
+/// Result of region validation.
+#[derive(Debug, Default, Clone)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no issues was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns found issues.
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    pub(crate) fn push(&mut self, issue: ValidationIssue) {
+        self.issues.push(issue);
+    }
+}
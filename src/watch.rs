@@ -0,0 +1,301 @@
+//! Provider of regions which are changed by another process, for example by running server.
+//!
+//! Region folder are watched for changes of region files. Headers of regions are
+//! kept, so changed chunks are derived by comparing new header with previous one.
+use crate::position::{RegionChunkPosition, RegionPosition};
+use crate::provider::{FilenameScheme, FolderRegionProvider, RegionProvider};
+use crate::region::{Region, RegionIndex, REGION_HEADER_BYTES_LENGTH};
+use log::debug;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Amount of attempts to read header of changed region which can be partially written.
+const READ_INDEX_ATTEMPTS: u32 = 3;
+/// Delay between attempts to read header of changed region.
+const READ_INDEX_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Headers of watched regions by region position.
+type RegionIndexes = Arc<Mutex<BTreeMap<RegionPosition, RegionIndex>>>;
+
+/// Change of region file in watched folder.
+#[derive(Debug, Eq, PartialEq)]
+pub struct RegionChange {
+    /// Position of changed region.
+    pub position: RegionPosition,
+    /// Positions of changed chunks or `None` if they can't be derived,
+    /// for example when region file was created or removed.
+    pub chunks: Option<Vec<RegionChunkPosition>>,
+}
+
+/// Read only provider of regions in folder which watches region files for changes.
+///
+/// Changes are sent to channel returned by `changes`, regions which are cached
+/// by user should be invalidated on change, for example with `CachedRegionProvider::invalidate`.
+/// Region file writes which don't change header are not reported.
+pub struct WatchingRegionProvider {
+    /// Provider which opens regions.
+    provider: FolderRegionProvider,
+    /// Receiver of region changes sent by watcher.
+    changes: Receiver<RegionChange>,
+    /// Watcher of region folder, folder are watched until it dropped.
+    _watcher: RecommendedWatcher,
+}
+
+impl WatchingRegionProvider {
+    /// Starts watching region folder, headers of existing regions are read.
+    pub fn new<P: AsRef<Path>>(folder: P) -> Result<Self, io::Error> {
+        let folder_path = folder.as_ref();
+        let provider = FolderRegionProvider::read_only(folder_path);
+
+        let indexes: BTreeMap<_, _> = provider
+            .iter_indexes()?
+            .filter_map(Result::ok)
+            .map(|index| (index.position(), index))
+            .collect();
+
+        let indexes = Arc::new(Mutex::new(indexes));
+        let (sender, changes) = channel();
+
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            if let Ok(event) = result {
+                handle_event(event, &indexes, &sender);
+            }
+        })
+        .map_err(io::Error::other)?;
+
+        watcher
+            .watch(folder_path, RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+
+        Ok(WatchingRegionProvider {
+            provider,
+            changes,
+            _watcher: watcher,
+        })
+    }
+
+    /// Returns receiver of region changes.
+    pub fn changes(&self) -> &Receiver<RegionChange> {
+        &self.changes
+    }
+}
+
+impl RegionProvider<File> for WatchingRegionProvider {
+    fn get_region(&self, position: RegionPosition) -> Result<Region<File>, io::Error> {
+        self.provider.get_region(position)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.provider.region_exists(position)
+    }
+}
+
+/// Updates kept headers of regions which files are changed and sends changes.
+fn handle_event(event: Event, indexes: &RegionIndexes, sender: &Sender<RegionChange>) {
+    let removed = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => false,
+        EventKind::Remove(_) => true,
+        _ => return,
+    };
+
+    for path in event.paths {
+        let position = match FilenameScheme::anvil().position(&path) {
+            Ok(position) => position,
+            Err(_) => continue,
+        };
+
+        let index = if removed {
+            None
+        } else {
+            // File can be renamed or removed before event are handled.
+            match read_changed_index(&path, position) {
+                Ok(index) => Some(index),
+                Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => None,
+                Err(io_error) => {
+                    // Header can be partially written, the next event of write reports change.
+                    debug!(
+                        target: "anvil-region",
+                        "Region x: {}, z: {} header read failed: {}",
+                        position.x, position.z, io_error
+                    );
+
+                    continue;
+                }
+            }
+        };
+
+        let mut indexes = indexes.lock().unwrap();
+
+        let index = match index {
+            Some(index) => index,
+            None => {
+                if indexes.remove(&position).is_some() {
+                    let change = RegionChange {
+                        position,
+                        chunks: None,
+                    };
+
+                    let _ = sender.send(change);
+                }
+
+                continue;
+            }
+        };
+
+        let chunks = match indexes.get(&position) {
+            Some(previous_index) => {
+                let chunks: Vec<_> = index.changed_chunks(previous_index).collect();
+
+                if chunks.is_empty() {
+                    continue;
+                }
+
+                Some(chunks)
+            }
+            None => None,
+        };
+
+        indexes.insert(position, index);
+
+        let _ = sender.send(RegionChange { position, chunks });
+    }
+}
+
+/// Reads header of changed region, reads which failed not because file are missing are retried.
+fn read_changed_index(path: &Path, position: RegionPosition) -> Result<RegionIndex, io::Error> {
+    let mut attempt = 1;
+
+    loop {
+        match read_index(path, position) {
+            Err(io_error)
+                if io_error.kind() != io::ErrorKind::NotFound && attempt < READ_INDEX_ATTEMPTS =>
+            {
+                thread::sleep(READ_INDEX_RETRY_DELAY);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn read_index(path: &Path, position: RegionPosition) -> Result<RegionIndex, io::Error> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+
+    // Empty file are new region, while file shorter than header are still being written.
+    if file_len > 0 && file_len < REGION_HEADER_BYTES_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Region header are partially written",
+        ));
+    }
+
+    RegionIndex::read(position, &mut file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{minimal_chunk, temp_world};
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+    use crate::watch::{handle_event, read_index, RegionChange, WatchingRegionProvider};
+    use notify::event::ModifyKind;
+    use notify::{Event, EventKind};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_region_changes() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let watching_provider = WatchingRegionProvider::new(&region_folder).unwrap();
+        let provider = FolderRegionProvider::new(&region_folder);
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(3, 1), minimal_chunk(3, 1))
+            .unwrap();
+        drop(region);
+
+        let change = watching_provider
+            .changes()
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+
+        assert_eq!(
+            change,
+            RegionChange {
+                position: RegionPosition::new(0, 0),
+                chunks: Some(vec![RegionChunkPosition::new(3, 1)]),
+            }
+        );
+
+        let mut region = watching_provider
+            .get_region(RegionPosition::new(0, 0))
+            .unwrap();
+        assert!(region.read_chunk(RegionChunkPosition::new(3, 1)).is_ok());
+
+        fs::remove_file(region_folder.join("r.0.0.mca")).unwrap();
+
+        let change = watching_provider
+            .changes()
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+
+        assert_eq!(
+            change,
+            RegionChange {
+                position: RegionPosition::new(0, 0),
+                chunks: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_partially_written_header() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let path = dir.join("region").join("r.0.0.mca");
+        let position = RegionPosition::new(0, 0);
+
+        let mut indexes = BTreeMap::new();
+        indexes.insert(position, read_index(&path, position).unwrap());
+        let indexes = Arc::new(Mutex::new(indexes));
+        let (sender, changes) = channel();
+
+        let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.clone());
+
+        // Header are cut in the middle as if it was being written.
+        let data = fs::read(&path).unwrap();
+        fs::write(&path, &data[..100]).unwrap();
+        handle_event(event.clone(), &indexes, &sender);
+
+        assert!(changes.try_recv().is_err());
+        assert!(indexes.lock().unwrap().contains_key(&position));
+
+        fs::remove_file(&path).unwrap();
+        handle_event(event, &indexes, &sender);
+
+        assert_eq!(
+            changes.try_recv().unwrap(),
+            RegionChange {
+                position,
+                chunks: None,
+            }
+        );
+        assert!(indexes.lock().unwrap().is_empty());
+    }
+}
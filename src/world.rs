@@ -0,0 +1,361 @@
+//! World folder with dimensions and copying of chunks between worlds.
+//!
+//! Every dimension keeps chunks, entities and points of interest in separate region folders.
+use crate::error::{ChunkCopyError, ChunkReadError, ChunkWriteError};
+use crate::lock::WorldLock;
+use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+use crate::provider::{FolderRegionProvider, RegionKind, RegionProvider};
+use crate::region::Region;
+use nbt::CompoundTag;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of file with world settings in world folder.
+const LEVEL_DAT_FILENAME: &str = "level.dat";
+
+/// Dimension of the world.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    /// Returns folder of dimension inside world folder.
+    ///
+    /// Overworld are stored in world folder itself.
+    pub fn folder_name(&self) -> Option<&'static str> {
+        match self {
+            Dimension::Overworld => None,
+            Dimension::Nether => Some("DIM-1"),
+            Dimension::End => Some("DIM1"),
+        }
+    }
+}
+
+/// Provider of regions of every dimension and kind in world folder.
+///
+/// # Example
+///
+/// ```
+/// use anvil_region::fixtures::minimal_world;
+/// use anvil_region::position::RegionPosition;
+/// use anvil_region::provider::{RegionKind, RegionProvider};
+/// use anvil_region::world::{Dimension, WorldProvider};
+///
+/// let dir = std::env::temp_dir().join("anvil-region-world-provider-doc");
+/// minimal_world(&dir, &[(0, 0)]).unwrap();
+///
+/// let world = WorldProvider::open(&dir).unwrap();
+/// let provider = world.regions(Dimension::Overworld, RegionKind::Blocks);
+/// let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+///
+/// assert!(!region.is_empty());
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct WorldProvider {
+    /// World folder.
+    path: PathBuf,
+}
+
+impl WorldProvider {
+    /// Opens world folder, folder must contain `level.dat` or `region` folder.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+
+        if !path.join(LEVEL_DAT_FILENAME).is_file() && !path.join("region").is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Folder doesn't contain world",
+            ));
+        }
+
+        Ok(WorldProvider { path })
+    }
+
+    /// Returns world folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns path of `level.dat` if it present.
+    pub fn level_dat_path(&self) -> Option<PathBuf> {
+        let path = self.path.join(LEVEL_DAT_FILENAME);
+
+        path.is_file().then_some(path)
+    }
+
+    /// Returns folder where data of dimension are stored.
+    pub fn dimension_path(&self, dimension: Dimension) -> PathBuf {
+        match dimension.folder_name() {
+            Some(folder_name) => self.path.join(folder_name),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Returns dimensions which have chunk regions folder.
+    pub fn dimensions(&self) -> Vec<Dimension> {
+        [Dimension::Overworld, Dimension::Nether, Dimension::End]
+            .iter()
+            .copied()
+            .filter(|dimension| {
+                let folder_name = RegionKind::Blocks.folder_name();
+                self.dimension_path(*dimension).join(folder_name).is_dir()
+            })
+            .collect()
+    }
+
+    /// Acquires session lock of world.
+    ///
+    /// Lock can be set to region providers with `FolderRegionProvider::world_lock`.
+    pub fn lock(&self) -> Result<WorldLock, io::Error> {
+        WorldLock::acquire(&self.path)
+    }
+
+    /// Returns provider of regions of specified kind in dimension.
+    pub fn regions(&self, dimension: Dimension, kind: RegionKind) -> FolderRegionProvider {
+        FolderRegionProvider::world(self.dimension_path(dimension), kind)
+    }
+}
+
+/// Copies all chunks from source world to destination.
+///
+/// Every chunk position are passed to `map` which returns position where chunk
+/// should be placed in destination or `None` if chunk should be skipped.
+/// Chunk coordinates inside chunk data are updated to the new position.
+///
+/// Source regions are opened only for reading. Destination regions are flushed and
+/// closed after every source region, so only regions written from one source region are open.
+pub fn copy_world<S, P, F>(
+    source: &FolderRegionProvider,
+    destination: &P,
+    mut map: F,
+) -> Result<(), ChunkCopyError>
+where
+    S: Read + Write + Seek,
+    P: RegionProvider<S>,
+    F: FnMut(ChunkPosition) -> Option<ChunkPosition>,
+{
+    for region_position in source.iter_positions().map_err(ChunkReadError::from)? {
+        let mut source_region = source
+            .open_region(region_position, true)
+            .map_err(|io_error| {
+                ChunkReadError::from(io_error).with_context(region_position, None)
+            })?;
+        let mut destination_regions: BTreeMap<RegionPosition, Region<S>> = BTreeMap::new();
+
+        for region_chunk_position in RegionChunkPosition::iter_all() {
+            if source_region
+                .chunk_start_sector_index(&region_chunk_position)
+                .is_none()
+            {
+                continue;
+            }
+
+            let chunk_position =
+                ChunkPosition::from_region_chunk(region_position, region_chunk_position);
+
+            let destination_position = match map(chunk_position) {
+                Some(destination_position) => destination_position,
+                None => continue,
+            };
+
+            let mut chunk_compound_tag = source_region.read_chunk(region_chunk_position)?;
+            set_chunk_coordinates(&mut chunk_compound_tag, destination_position);
+
+            let destination_region = match destination_regions.entry(destination_position.region())
+            {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let destination_region = destination
+                        .get_region(destination_position.region())
+                        .map_err(ChunkWriteError::from)?;
+
+                    entry.insert(destination_region)
+                }
+            };
+
+            destination_region
+                .write_chunk(destination_position.region_chunk(), chunk_compound_tag)?;
+        }
+
+        for (destination_position, mut destination_region) in destination_regions {
+            destination_region.flush().map_err(|io_error| {
+                ChunkWriteError::from(io_error).with_context(destination_position, None)
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies compressed chunk data from source provider to destination without decoding.
+///
+/// Compression scheme and last modified timestamp of chunk are preserved.
+/// Returns `false` if source doesn't contain chunk.
+pub fn copy_chunk<S, D, SP, DP>(
+    source: &SP,
+    destination: &DP,
+    position: ChunkPosition,
+) -> Result<bool, ChunkCopyError>
+where
+    S: Read + Seek,
+    D: Write + Seek,
+    SP: RegionProvider<S>,
+    DP: RegionProvider<D>,
+{
+    let region_chunk_position = position.region_chunk();
+
+    let mut source_region = source
+        .get_region(position.region())
+        .map_err(ChunkReadError::from)?;
+
+    if !source_region.contains_chunk(region_chunk_position) {
+        return Ok(false);
+    }
+
+    let mut destination_region = destination
+        .get_region(position.region())
+        .map_err(ChunkWriteError::from)?;
+
+    source_region.copy_chunk_to(
+        &mut destination_region,
+        region_chunk_position,
+        region_chunk_position,
+    )
+}
+
+/// Updates chunk coordinates in chunk compound tag.
+///
+/// Before 1.18 coordinates are stored inside `Level` compound tag.
+fn set_chunk_coordinates(chunk_compound_tag: &mut CompoundTag, position: ChunkPosition) {
+    let coordinates_compound_tag = match chunk_compound_tag.get_mut::<&mut CompoundTag>("Level") {
+        Ok(level_compound_tag) => level_compound_tag,
+        Err(_) => chunk_compound_tag,
+    };
+
+    coordinates_compound_tag.insert_i32("xPos", position.x);
+    coordinates_compound_tag.insert_i32("zPos", position.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::{minimal_level_dat, minimal_world, temp_world};
+    use crate::position::{ChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionKind, RegionProvider};
+    use crate::world::{copy_chunk, copy_world, Dimension, WorldProvider};
+    use std::fs;
+
+    #[test]
+    fn test_copy_world() {
+        let source_world = temp_world(&[(0, 0), (1, 2), (40, -3)]);
+        let source_dir = source_world.path();
+        let destination_world = temp_world(&[]);
+        let destination_dir = destination_world.path();
+
+        let source_folder = source_dir.join("region");
+        let destination_folder = destination_dir.join("region");
+
+        let source = FolderRegionProvider::new(source_folder.to_str().unwrap());
+        let destination = FolderRegionProvider::new(destination_folder.to_str().unwrap());
+
+        // Mirroring along x axis and skipping one chunk.
+        copy_world(&source, &destination, |position| {
+            if position == ChunkPosition::new(1, 2) {
+                return None;
+            }
+
+            Some(ChunkPosition::new(-position.x - 1, position.z))
+        })
+        .unwrap();
+
+        let mut positions: Vec<_> = destination.iter_positions().unwrap().collect();
+        positions.sort();
+
+        assert_eq!(
+            positions,
+            vec![RegionPosition::new(-2, -1), RegionPosition::new(-1, 0)]
+        );
+
+        for &position in &[ChunkPosition::new(-1, 0), ChunkPosition::new(-41, -3)] {
+            let mut region = destination.get_region(position.region()).unwrap();
+            let chunk_compound_tag = region.read_chunk(position.region_chunk()).unwrap();
+            let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+            assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), position.x);
+            assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), position.z);
+        }
+
+        let mut region = destination.get_region(RegionPosition::new(-1, 0)).unwrap();
+        let skipped_position = ChunkPosition::new(-2, 2).region_chunk();
+
+        assert!(region.read_chunk(skipped_position).is_err());
+    }
+
+    #[test]
+    fn test_copy_chunk() {
+        let source_world = temp_world(&[(40, -3)]);
+        let source_dir = source_world.path();
+        let destination_world = temp_world(&[]);
+        let destination_dir = destination_world.path();
+
+        let source_folder = source_dir.join("region");
+        let destination_folder = destination_dir.join("region");
+
+        let source = FolderRegionProvider::new(source_folder.to_str().unwrap());
+        let destination = FolderRegionProvider::new(destination_folder.to_str().unwrap());
+
+        let position = ChunkPosition::new(40, -3);
+
+        assert!(copy_chunk(&source, &destination, position).unwrap());
+        assert!(!copy_chunk(&source, &destination, ChunkPosition::new(41, -3)).unwrap());
+
+        let source_region = source.get_region(position.region()).unwrap();
+        let mut destination_region = destination.get_region(position.region()).unwrap();
+
+        assert_eq!(
+            destination_region.chunk_last_modified_timestamp(position.region_chunk()),
+            source_region.chunk_last_modified_timestamp(position.region_chunk())
+        );
+
+        let chunk_compound_tag = destination_region
+            .read_chunk(position.region_chunk())
+            .unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 40);
+        assert_eq!(level_compound_tag.get_i32("zPos").unwrap(), -3);
+    }
+
+    #[test]
+    fn test_world_provider() {
+        let temp_world = temp_world(&[]);
+        let dir = temp_world.path().join("world");
+        fs::create_dir(&dir).unwrap();
+
+        assert!(WorldProvider::open(&dir).is_err());
+
+        minimal_level_dat(&dir, "World").unwrap();
+        minimal_world(dir.join("DIM-1"), &[(-1, -1)]).unwrap();
+
+        let world = WorldProvider::open(&dir).unwrap();
+
+        assert_eq!(world.level_dat_path(), Some(dir.join("level.dat")));
+        assert_eq!(world.dimensions(), vec![Dimension::Nether]);
+        assert_eq!(world.dimension_path(Dimension::End), dir.join("DIM1"));
+
+        let provider = world.regions(Dimension::Nether, RegionKind::Blocks);
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(-1, -1)]
+        );
+
+        let provider = world.regions(Dimension::Overworld, RegionKind::Entities);
+        provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(dir.join("entities").join("r.0.0.mca").is_file());
+    }
+}
@@ -0,0 +1,342 @@
+//! Provider of regions stored in zip archive, such as downloaded worlds.
+//!
+//! Region folder are discovered in the same way as with `open_world`: archive can
+//! contain world folder with `region` folder or region files itself.
+use crate::position::RegionPosition;
+use crate::provider::{find_region_entries, region_position_filename, RegionProvider};
+use crate::region::{
+    Region, REGION_CHUNKS, REGION_HEADER_BYTES_LENGTH, REGION_SECTOR_BYTES_LENGTH,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Maximum length of region file, every chunk takes at most 255 sectors.
+const REGION_MAXIMUM_BYTES_LENGTH: u64 =
+    REGION_HEADER_BYTES_LENGTH + REGION_CHUNKS as u64 * 255 * REGION_SECTOR_BYTES_LENGTH as u64;
+
+/// Provider of regions stored in zip archive.
+///
+/// Regions are loaded to memory, so changes of returned regions are not saved to archive
+/// unless provider opened with `open_writable` and regions are passed to `save_region`.
+pub struct ZipRegionProvider {
+    /// Path to archive.
+    path: PathBuf,
+    /// Opened archive.
+    archive: RefCell<ZipArchive<File>>,
+    /// Entry name prefix of region folder in archive.
+    region_folder: String,
+    /// Entry name of every region file in region folder of archive.
+    region_entries: BTreeMap<RegionPosition, String>,
+    /// Whether regions can be saved to archive.
+    writable: bool,
+    /// Data of saved regions which are written to archive on flush.
+    staged_regions: BTreeMap<RegionPosition, Vec<u8>>,
+}
+
+impl ZipRegionProvider {
+    /// Opens archive and discovers region folder in it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_path_buf();
+        let archive = ZipArchive::new(File::open(&path)?)?;
+        let (region_folder, region_entries) = find_region_entries(archive.file_names());
+
+        Ok(ZipRegionProvider {
+            path,
+            archive: RefCell::new(archive),
+            region_folder,
+            region_entries,
+            writable: false,
+            staged_regions: BTreeMap::new(),
+        })
+    }
+
+    /// Opens archive which regions can be saved with `save_region`.
+    ///
+    /// Saved regions are kept in memory until `flush`, which rewrites archive.
+    pub fn open_writable<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let mut provider = ZipRegionProvider::open(path)?;
+        provider.writable = true;
+
+        Ok(provider)
+    }
+
+    /// Returns positions of regions in archive including saved but not flushed ones.
+    pub fn iter_positions(&self) -> impl Iterator<Item = RegionPosition> + '_ {
+        let positions: BTreeSet<_> = self
+            .region_entries
+            .keys()
+            .chain(self.staged_regions.keys())
+            .copied()
+            .collect();
+
+        positions.into_iter()
+    }
+
+    /// Stages region data to be written to archive on flush.
+    ///
+    /// Following `get_region` calls return saved region.
+    pub fn save_region(&mut self, region: Region<Cursor<Vec<u8>>>) -> Result<(), io::Error> {
+        if !self.writable {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Zip region provider opened read only",
+            ));
+        }
+
+        let position = region.position();
+        self.staged_regions.insert(position, region.into_bytes()?);
+
+        Ok(())
+    }
+
+    /// Returns `true` if there are saved regions which are not written to archive yet.
+    pub fn has_staged_regions(&self) -> bool {
+        !self.staged_regions.is_empty()
+    }
+
+    /// Rewrites archive with saved regions.
+    ///
+    /// Other entries are copied without recompression. New archive are written next to
+    /// the old one and renamed over it, so archive stays intact if flush fails.
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        if self.staged_regions.is_empty() {
+            return Ok(());
+        }
+
+        let mut temp_filename = self.path.file_name().unwrap_or_default().to_os_string();
+        temp_filename.push(".tmp");
+        let temp_path = self.path.with_file_name(temp_filename);
+
+        let region_entries = &self.region_entries;
+        let replaced_names: BTreeSet<_> = self
+            .staged_regions
+            .keys()
+            .filter_map(|position| region_entries.get(position))
+            .map(String::as_str)
+            .collect();
+
+        let mut writer = ZipWriter::new(File::create(&temp_path)?);
+        let archive = self.archive.get_mut();
+
+        for index in 0..archive.len() {
+            let entry = archive.by_index_raw(index)?;
+
+            if !replaced_names.contains(entry.name()) {
+                writer.raw_copy_file(entry)?;
+            }
+        }
+
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let mut written_entries = Vec::new();
+
+        for (position, data) in &self.staged_regions {
+            let name = match self.region_entries.get(position) {
+                Some(name) => name.clone(),
+                None => format!(
+                    "{}{}",
+                    self.region_folder,
+                    region_position_filename(*position)
+                ),
+            };
+
+            writer.start_file(name.as_str(), options)?;
+            writer.write_all(data)?;
+
+            written_entries.push((*position, name));
+        }
+
+        writer.finish()?.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+
+        *archive = ZipArchive::new(File::open(&self.path)?)?;
+        // Entries are known only after archive with them replaced the old one.
+        self.region_entries.extend(written_entries);
+        self.staged_regions.clear();
+
+        Ok(())
+    }
+}
+
+impl RegionProvider<Cursor<Vec<u8>>> for ZipRegionProvider {
+    /// Returns region from archive or empty region if archive doesn't contain it.
+    fn get_region(&self, position: RegionPosition) -> Result<Region<Cursor<Vec<u8>>>, io::Error> {
+        if let Some(data) = self.staged_regions.get(&position) {
+            return Region::from_bytes(position.x, position.z, data.clone());
+        }
+
+        let mut data = Vec::new();
+
+        if let Some(name) = self.region_entries.get(&position) {
+            let mut archive = self.archive.borrow_mut();
+            let entry = archive.by_name(name)?;
+
+            // Declared size are not trusted, reading stops after maximum region length.
+            entry
+                .take(REGION_MAXIMUM_BYTES_LENGTH + 1)
+                .read_to_end(&mut data)?;
+
+            if data.len() as u64 > REGION_MAXIMUM_BYTES_LENGTH {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Region entry {} exceeds maximum region length", name),
+                ));
+            }
+        }
+
+        Region::from_bytes(position.x, position.z, data)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.staged_regions.contains_key(&position) || self.region_entries.contains_key(&position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ChunkWriteError;
+    use crate::fixtures::temp_world;
+    use crate::position::{ChunkPosition, RegionChunkPosition, RegionPosition};
+    use crate::provider::{open_world, RegionProvider};
+    use crate::temp::TempWorldProvider;
+    use crate::zip_provider::ZipRegionProvider;
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::fs::File;
+    use std::io;
+    use std::io::{Read, Write};
+    use zip::write::FileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    #[test]
+    fn test_get_region() {
+        let world = temp_world(&[]);
+        let path = world.path().join("zip-provider.zip");
+        let region_data = fs::read("test/region/r.0.0.mca").unwrap();
+
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer
+            .add_directory("World/region/", FileOptions::default())
+            .unwrap();
+        writer
+            .start_file("World/region/r.0.0.mca", FileOptions::default())
+            .unwrap();
+        writer.write_all(&region_data).unwrap();
+        writer.finish().unwrap();
+
+        let provider = ZipRegionProvider::open(&path).unwrap();
+
+        assert_eq!(
+            provider.iter_positions().collect::<Vec<_>>(),
+            vec![RegionPosition::new(0, 0)]
+        );
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        let chunk_compound_tag = region.read_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+
+        let region = provider.get_region(RegionPosition::new(1, 1)).unwrap();
+        assert_eq!(region.positions().count(), 0);
+    }
+
+    #[test]
+    fn test_open_world() {
+        let world = TempWorldProvider::new().unwrap();
+        let path = world.path().join("world.zip");
+        let region_data = fs::read("test/region/r.0.0.mca").unwrap();
+
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer
+            .start_file("World/region/r.0.0.mca", FileOptions::default())
+            .unwrap();
+        writer.write_all(&region_data).unwrap();
+        writer.finish().unwrap();
+
+        let mut provider = open_world(&path).unwrap();
+        let chunk_compound_tag = provider.load_chunk(ChunkPosition::new(4, 2)).unwrap();
+        let level_compound_tag = chunk_compound_tag.get_compound_tag("Level").unwrap();
+
+        assert_eq!(level_compound_tag.get_i32("xPos").unwrap(), 4);
+
+        match provider.save_chunk(ChunkPosition::new(4, 2), chunk_compound_tag) {
+            Err(ChunkWriteError::IOError { io_error, .. }) => {
+                assert_eq!(io_error.kind(), io::ErrorKind::Unsupported)
+            }
+            result => panic!("Expected unsupported error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_save_region() {
+        let world = temp_world(&[]);
+        let path = world.path().join("zip-provider-save.zip");
+        let region_data = fs::read("test/region/r.0.0.mca").unwrap();
+
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer
+            .start_file("World/level.dat", FileOptions::default())
+            .unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+        writer
+            .start_file("World/region/r.0.0.mca", FileOptions::default())
+            .unwrap();
+        writer.write_all(&region_data).unwrap();
+        writer.finish().unwrap();
+
+        let mut provider = ZipRegionProvider::open(&path).unwrap();
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(provider.save_region(region).is_err());
+
+        let mut provider = ZipRegionProvider::open_writable(&path).unwrap();
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        region.remove_chunk(RegionChunkPosition::new(4, 2)).unwrap();
+        provider.save_region(region).unwrap();
+
+        let mut region = provider.get_region(RegionPosition::new(-1, 2)).unwrap();
+        region
+            .write_chunk(RegionChunkPosition::new(1, 1), CompoundTag::new())
+            .unwrap();
+        provider.save_region(region).unwrap();
+
+        // Saved regions are visible before flush.
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(!region.contains_chunk(RegionChunkPosition::new(4, 2)));
+        assert!(provider.has_staged_regions());
+
+        provider.flush().unwrap();
+        assert!(!provider.has_staged_regions());
+
+        let provider = ZipRegionProvider::open(&path).unwrap();
+
+        assert_eq!(
+            provider.iter_positions().collect::<Vec<_>>(),
+            vec![RegionPosition::new(-1, 2), RegionPosition::new(0, 0)]
+        );
+
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(!region.contains_chunk(RegionChunkPosition::new(4, 2)));
+        assert!(region.contains_chunk(RegionChunkPosition::new(4, 3)));
+
+        let region = provider.get_region(RegionPosition::new(-1, 2)).unwrap();
+        assert!(region.contains_chunk(RegionChunkPosition::new(1, 1)));
+
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert!(archive.by_name("World/region/r.-1.2.mca").is_ok());
+
+        let mut level_data = Vec::new();
+        archive
+            .by_name("World/level.dat")
+            .unwrap()
+            .read_to_end(&mut level_data)
+            .unwrap();
+        assert_eq!(level_data, vec![1, 2, 3]);
+    }
+}
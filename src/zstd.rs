@@ -0,0 +1,317 @@
+//! Region files compressed at rest with zstd.
+//!
+//! Every region are stored as `r.x.z.mca.zst`, region are decompressed into memory
+//! by `get_region` and compressed again when region are flushed or dropped.
+//!
+//! # Example
+//!
+//! ```
+//! use anvil_region::position::{RegionChunkPosition, RegionPosition};
+//! use anvil_region::provider::{FolderRegionProvider, RegionProvider};
+//! use nbt::CompoundTag;
+//!
+//! # let folder = std::env::temp_dir().join("anvil-region-zstd-doc");
+//! # let _ = std::fs::remove_dir_all(&folder);
+//! let provider = FolderRegionProvider::new(&folder).zstd_at_rest(3);
+//!
+//! let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! region.write_chunk(RegionChunkPosition::new(4, 2), CompoundTag::new()).unwrap();
+//! region.close().unwrap();
+//!
+//! assert!(folder.join("r.0.0.mca.zst").exists());
+//!
+//! let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+//! assert!(region.contains_chunk(RegionChunkPosition::new(4, 2)));
+//! # std::fs::remove_dir_all(&folder).unwrap();
+//! ```
+use crate::position::RegionPosition;
+use crate::provider::{region_not_found, FolderRegionProvider, RegionProvider};
+use crate::region::{Region, SetLen, SyncAll};
+use crate::storage::write_atomically;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension which are appended to region filename of compressed region.
+const ZSTD_EXTENSION: &str = "zst";
+
+/// Region file which are kept decompressed in memory and written compressed on flush.
+///
+/// Uncompressed region file with the same name are replaced when region are written.
+pub struct ZstdFile {
+    /// Decompressed region data.
+    data: Cursor<Vec<u8>>,
+    /// Path of compressed region file.
+    path: PathBuf,
+    /// Path of uncompressed region file which are removed after compressed file written.
+    plain_path: PathBuf,
+    /// Zstd compression level.
+    level: i32,
+    read_only: bool,
+    /// Whether data was changed since the last write of compressed file.
+    modified: bool,
+}
+
+impl ZstdFile {
+    /// Returns path of compressed region file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn check_writable(&mut self) -> Result<(), io::Error> {
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Region are opened only for reading",
+            ));
+        }
+
+        self.modified = true;
+
+        Ok(())
+    }
+}
+
+impl Read for ZstdFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.data.read(buf)
+    }
+}
+
+impl Seek for ZstdFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, io::Error> {
+        self.data.seek(pos)
+    }
+}
+
+impl Write for ZstdFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.check_writable()?;
+        self.data.write(buf)
+    }
+
+    /// Compresses region and replaces region file if region was changed.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        if !self.modified {
+            return Ok(());
+        }
+
+        let compressed_data = zstd::encode_all(self.data.get_ref().as_slice(), self.level)?;
+        write_atomically(&self.path, &compressed_data)?;
+
+        match fs::remove_file(&self.plain_path) {
+            Err(io_error) if io_error.kind() != io::ErrorKind::NotFound => return Err(io_error),
+            _ => {}
+        }
+
+        self.modified = false;
+
+        Ok(())
+    }
+}
+
+impl SetLen for ZstdFile {
+    fn set_len(&mut self, len: u64) -> Result<(), io::Error> {
+        self.check_writable()?;
+        self.data.set_len(len)
+    }
+}
+
+impl SyncAll for ZstdFile {
+    /// Writes compressed region, which are synchronized with storage before it replaces region file.
+    ///
+    /// Whole region are compressed on every call, so crash-safe writes are expensive.
+    fn sync_all(&mut self) -> Result<(), io::Error> {
+        self.flush()
+    }
+}
+
+/// Provider of regions in folder which are stored compressed with zstd.
+///
+/// Settings of folder provider are applied to regions.
+pub struct ZstdFolderRegionProvider {
+    provider: FolderRegionProvider,
+    /// Zstd compression level.
+    level: i32,
+}
+
+impl ZstdFolderRegionProvider {
+    /// Returns positions of compressed and uncompressed regions in folder.
+    pub fn iter_positions(&self) -> Result<impl Iterator<Item = RegionPosition>, io::Error> {
+        let mut positions = Vec::new();
+
+        for entry in fs::read_dir(self.provider.folder_path())? {
+            let path = entry?.path();
+
+            let path = match path.extension() {
+                Some(extension) if extension == ZSTD_EXTENSION => path.with_extension(""),
+                _ => path,
+            };
+
+            if let Ok(position) = self.provider.filename_scheme.position(&path) {
+                positions.push(position);
+            }
+        }
+
+        positions.sort();
+        positions.dedup();
+
+        Ok(positions.into_iter())
+    }
+
+    fn compressed_region_path(&self, position: RegionPosition) -> PathBuf {
+        let mut path = OsString::from(self.provider.region_path(position));
+        path.push(".");
+        path.push(ZSTD_EXTENSION);
+
+        PathBuf::from(path)
+    }
+}
+
+impl RegionProvider<ZstdFile> for ZstdFolderRegionProvider {
+    /// Returns decompressed region, uncompressed region file are read if there is no compressed one.
+    fn get_region(&self, position: RegionPosition) -> Result<Region<ZstdFile>, io::Error> {
+        let read_only = self.provider.read_only;
+
+        if !read_only {
+            self.provider.prepare_region_write()?;
+        }
+
+        let path = self.compressed_region_path(position);
+        let plain_path = self.provider.region_path(position);
+
+        let data = match read_file(&path)? {
+            Some(compressed_data) => zstd::decode_all(compressed_data.as_slice())?,
+            None => match read_file(&plain_path)? {
+                Some(data) => data,
+                None if !read_only && self.provider.create_missing_regions => Vec::new(),
+                None => return Err(region_not_found(position)),
+            },
+        };
+
+        let file = ZstdFile {
+            data: Cursor::new(data),
+            path,
+            plain_path,
+            level: self.level,
+            read_only,
+            modified: false,
+        };
+
+        self.provider.load_region(position, file, read_only)
+    }
+
+    fn region_exists(&self, position: RegionPosition) -> bool {
+        self.compressed_region_path(position).is_file() || self.provider.region_exists(position)
+    }
+
+    /// Deletes compressed and uncompressed region files, read only provider returns
+    /// `PermissionDenied` error.
+    fn remove_region(&self, position: RegionPosition) -> Result<bool, io::Error> {
+        let plain_removed = self.provider.remove_region(position)?;
+
+        match fs::remove_file(self.compressed_region_path(position)) {
+            Ok(()) => Ok(true),
+            Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => Ok(plain_removed),
+            Err(io_error) => Err(io_error),
+        }
+    }
+}
+
+impl FolderRegionProvider {
+    /// Returns provider of regions in folder which are stored compressed with zstd.
+    ///
+    /// Regions are decompressed into memory when loaded and compressed back
+    /// when flushed or dropped.
+    pub fn zstd_at_rest(self, level: i32) -> ZstdFolderRegionProvider {
+        ZstdFolderRegionProvider {
+            provider: self,
+            level,
+        }
+    }
+}
+
+/// Reads file or returns `None` if it doesn't exist.
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>, io::Error> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(io_error) if io_error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(io_error) => Err(io_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::temp_world;
+    use crate::position::{RegionChunkPosition, RegionPosition};
+    use crate::provider::{FolderRegionProvider, RegionProvider};
+    use nbt::CompoundTag;
+    use std::fs;
+    use std::io;
+
+    #[test]
+    fn test_compress_plain_regions() {
+        let world = temp_world(&[(0, 0), (40, 1)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let plain_len = fs::metadata(region_folder.join("r.0.0.mca")).unwrap().len();
+
+        let provider = FolderRegionProvider::new(&region_folder).zstd_at_rest(3);
+
+        assert_eq!(
+            provider.iter_positions().unwrap().collect::<Vec<_>>(),
+            vec![RegionPosition::new(0, 0), RegionPosition::new(1, 0)]
+        );
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(region.contains_chunk(RegionChunkPosition::new(0, 0)));
+
+        // Region without changes are not rewritten.
+        region.flush().unwrap();
+        assert!(region_folder.join("r.0.0.mca").exists());
+
+        region
+            .write_chunk(RegionChunkPosition::new(4, 2), CompoundTag::new())
+            .unwrap();
+        drop(region);
+
+        assert!(!region_folder.join("r.0.0.mca").exists());
+
+        let compressed_len = fs::metadata(region_folder.join("r.0.0.mca.zst"))
+            .unwrap()
+            .len();
+        assert!(compressed_len < plain_len);
+        assert_eq!(provider.iter_positions().unwrap().count(), 2);
+
+        let region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        assert!(region.contains_chunk(RegionChunkPosition::new(0, 0)));
+        assert!(region.contains_chunk(RegionChunkPosition::new(4, 2)));
+
+        assert!(provider.remove_region(RegionPosition::new(0, 0)).unwrap());
+        assert!(!provider.region_exists(RegionPosition::new(0, 0)));
+    }
+
+    #[test]
+    fn test_read_only() {
+        let world = temp_world(&[(0, 0)]);
+        let dir = world.path();
+
+        let region_folder = dir.join("region");
+        let provider = FolderRegionProvider::read_only(&region_folder).zstd_at_rest(3);
+
+        let error = provider
+            .get_region(RegionPosition::new(1, 0))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+        let mut region = provider.get_region(RegionPosition::new(0, 0)).unwrap();
+        let result = region.write_chunk(RegionChunkPosition::new(4, 2), CompoundTag::new());
+
+        assert!(result.is_err());
+        assert!(!region_folder.join("r.0.0.mca.zst").exists());
+    }
+}